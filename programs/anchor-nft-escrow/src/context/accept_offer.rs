@@ -0,0 +1,195 @@
+use solana_program::*;
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, CloseAccount, transfer, close_account},
+    metadata::{MetadataAccount, MasterEditionAccount, Metadata, TokenRecordAccount},
+    associated_token::AssociatedToken
+};
+
+use crate::{
+    state::{Offer, Config},
+    errors::EscrowError,
+    denylist::check_not_denylisted,
+    transfer::{AssetTransfer, is_non_fungible},
+};
+
+#[derive(Accounts)]
+pub struct AcceptOffer<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(mut)]
+    pub buyer: SystemAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = seller
+    )]
+    pub seller_ata_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = mint_b,
+        associated_token::authority = seller
+    )]
+    pub seller_ata_b: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = mint_a,
+        associated_token::authority = buyer
+    )]
+    pub buyer_ata_a: Box<Account<'info, TokenAccount>>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_a: Box<Account<'info, MetadataAccount>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"edition",
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub master_edition_a: Option<Box<Account<'info, MasterEditionAccount>>>,
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"token_record",
+            seller_ata_a.key().as_ref(),
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub seller_token_record_a: Option<Box<Account<'info, TokenRecordAccount>>>,
+    #[account(mut)]
+    /// CHECK: we're checking this later
+    pub buyer_token_record_a: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = offer
+    )]
+    pub offer_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        close = buyer,
+        has_one = buyer,
+        has_one = mint_a,
+        has_one = mint_b,
+        seeds = [b"offer", buyer.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump,
+    )]
+    pub offer: Box<Account<'info, Offer>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    /// CHECK: checked against mint_a later
+    pub mint_denylist: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection of mint_a later
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+
+    #[account(address = sysvar::instructions::id())]
+    /// CHECK: we don't need to check this
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> AcceptOffer<'info> {
+    pub fn accept_offer(
+        &mut self,
+        bumps: AcceptOfferBumps,
+    ) -> Result<()> {
+
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+        let collection_denylist = self.collection_denylist.as_ref().map(|denylist| denylist.to_account_info());
+        check_not_denylisted(&self.mint_a.key(), &self.metadata_a, &self.mint_denylist.to_account_info(), collection_denylist.as_ref())?;
+
+        //All deferred errors
+        let mint_a_key = self.mint_a.key();
+        let mint_b_key = self.mint_b.key();
+        let buyer_key = self.buyer.key();
+        let metadata_a_token_standard = self.metadata_a.token_standard.as_ref().unwrap();
+
+        // Offers can only be made on NFTs
+        require!(is_non_fungible(metadata_a_token_standard), EscrowError::InvalidTokenStandard);
+
+        let master_edition = self.master_edition_a.as_ref().map(|master_edition| master_edition.to_account_info());
+        let seller_token_record = self.seller_token_record_a.as_ref().map(|token_record| token_record.to_account_info());
+        let buyer_token_record = self.buyer_token_record_a.to_account_info();
+
+        // Transfer the token from the seller to the buyer
+        AssetTransfer {
+            token: &self.seller_ata_a.to_account_info(),
+            token_owner: &self.seller.to_account_info(),
+            destination_token: &self.buyer_ata_a.to_account_info(),
+            destination_owner: &self.buyer.to_account_info(),
+            mint: &self.mint_a.to_account_info(),
+            metadata: &self.metadata_a,
+            master_edition: master_edition.as_ref(),
+            token_record: seller_token_record.as_ref(),
+            destination_token_record: Some(&buyer_token_record),
+            authority: &self.seller.to_account_info(),
+            payer: &self.seller.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+            sysvar_instructions: &self.sysvar_instructions.to_account_info(),
+            token_program: &self.token_program.to_account_info(),
+            associated_token_program: &self.associated_token_program.to_account_info(),
+            token_metadata_program: &self.token_metadata_program.to_account_info(),
+            amount: 1,
+        }.invoke()?;
+
+        // Release the payment to the seller and give the rent of the vault back to the buyer
+        let seeds = &[
+            "offer".as_bytes(),
+            buyer_key.as_ref(),
+            mint_a_key.as_ref(),
+            mint_b_key.as_ref(),
+            &[bumps.offer]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: self.offer_vault.to_account_info(),
+            to: self.seller_ata_b.to_account_info(),
+            authority: self.offer.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+        transfer(cpi_ctx, self.offer_vault.amount)?;
+
+        let cpi_accounts = CloseAccount {
+            account: self.offer_vault.to_account_info(),
+            destination: self.buyer.to_account_info(),
+            authority: self.offer.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+        close_account(cpi_ctx)?;
+
+        Ok(())
+    }
+}
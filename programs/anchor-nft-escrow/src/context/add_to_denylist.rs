@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    state::{Config, DenylistEntry},
+    errors::EscrowError,
+};
+
+#[derive(Accounts)]
+#[instruction(key: Pubkey)]
+pub struct AddToDenylist<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        has_one = admin @ EscrowError::InvalidAdmin,
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"denylist", key.as_ref()],
+        bump,
+        space = DenylistEntry::space()
+    )]
+    pub denylist_entry: Box<Account<'info, DenylistEntry>>,
+
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> AddToDenylist<'info> {
+    // The key is a mint, or the mint of a collection
+    pub fn add_to_denylist(
+        &mut self,
+        key: Pubkey,
+    ) -> Result<()> {
+
+        self.denylist_entry.set_inner(
+            DenylistEntry {
+                key,
+            }
+        );
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,84 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, CloseAccount, transfer, close_account},
+    associated_token::AssociatedToken
+};
+
+use crate::state::Bid;
+
+#[derive(Accounts)]
+pub struct CancelBid<'info> {
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = bidder,
+        associated_token::mint = mint_b,
+        associated_token::authority = bidder
+    )]
+    pub bidder_ata: Box<Account<'info, TokenAccount>>,
+
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = bid
+    )]
+    pub bid_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        close = bidder,
+        has_one = bidder,
+        has_one = mint_b,
+        seeds = [b"bid", bidder.key().as_ref(), mint_b.key().as_ref(), bid.merkle_root.as_ref()],
+        bump,
+    )]
+    pub bid: Box<Account<'info, Bid>>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> CancelBid<'info> {
+    pub fn cancel_bid(
+        &mut self,
+        bumps: CancelBidBumps,
+    ) -> Result<()> {
+
+        let bidder_key = self.bidder.key();
+        let mint_b_key = self.mint_b.key();
+        let merkle_root = self.bid.merkle_root;
+
+        let seeds = &[
+            "bid".as_bytes(),
+            bidder_key.as_ref(),
+            mint_b_key.as_ref(),
+            merkle_root.as_ref(),
+            &[bumps.bid]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        // Refund the payment and close the vault
+        let cpi_accounts = Transfer {
+            from: self.bid_vault.to_account_info(),
+            to: self.bidder_ata.to_account_info(),
+            authority: self.bid.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+        transfer(cpi_ctx, self.bid_vault.amount)?;
+
+        let cpi_accounts = CloseAccount {
+            account: self.bid_vault.to_account_info(),
+            destination: self.bidder.to_account_info(),
+            authority: self.bid.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+        close_account(cpi_ctx)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, CloseAccount, transfer, close_account},
+    associated_token::AssociatedToken
+};
+
+use crate::state::Offer;
+
+#[derive(Accounts)]
+pub struct CancelOffer<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = mint_b,
+        associated_token::authority = buyer
+    )]
+    pub buyer_ata: Box<Account<'info, TokenAccount>>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = offer
+    )]
+    pub offer_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        close = buyer,
+        has_one = buyer,
+        has_one = mint_a,
+        has_one = mint_b,
+        seeds = [b"offer", buyer.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump,
+    )]
+    pub offer: Box<Account<'info, Offer>>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> CancelOffer<'info> {
+    pub fn cancel_offer(
+        &mut self,
+        bumps: CancelOfferBumps,
+    ) -> Result<()> {
+
+        let buyer_key = self.buyer.key();
+        let mint_a_key = self.mint_a.key();
+        let mint_b_key = self.mint_b.key();
+
+        let seeds = &[
+            "offer".as_bytes(),
+            buyer_key.as_ref(),
+            mint_a_key.as_ref(),
+            mint_b_key.as_ref(),
+            &[bumps.offer]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        // Refund the payment and close the vault
+        let cpi_accounts = Transfer {
+            from: self.offer_vault.to_account_info(),
+            to: self.buyer_ata.to_account_info(),
+            authority: self.offer.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+        transfer(cpi_ctx, self.offer_vault.amount)?;
+
+        let cpi_accounts = CloseAccount {
+            account: self.offer_vault.to_account_info(),
+            destination: self.buyer.to_account_info(),
+            authority: self.offer.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+        close_account(cpi_ctx)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    state::{Order, OrderStatus, OrderState},
+    errors::EscrowError,
+};
+
+#[derive(Accounts)]
+#[instruction(order: Order)]
+pub struct CancelOrder<'info> {
+    #[account(mut, address = order.maker)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = maker,
+        seeds = [b"order", order.hash()?.as_ref()],
+        bump,
+        space = OrderStatus::space()
+    )]
+    pub order_status: Box<Account<'info, OrderStatus>>,
+
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> CancelOrder<'info> {
+    // Cancels a single signed order without touching the other ones
+    pub fn cancel_order(
+        &mut self,
+    ) -> Result<()> {
+
+        match self.order_status.state {
+            OrderState::Open => {},
+            OrderState::Filled => return err!(EscrowError::OrderFilled),
+            OrderState::Cancelled => return err!(EscrowError::OrderCancelled),
+        }
+
+        self.order_status.set_inner(
+            OrderStatus {
+                maker: self.maker.key(),
+                state: OrderState::Cancelled,
+            }
+        );
+
+        Ok(())
+    }
+}
\ No newline at end of file
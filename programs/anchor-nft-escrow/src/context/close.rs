@@ -3,21 +3,24 @@ use solana_program::*;
 use anchor_lang::prelude::*;
 use anchor_spl::{
     token::{Mint, TokenAccount, Token}, 
-    metadata::{MetadataAccount, MasterEditionAccount, Metadata, TokenRecordAccount, 
-        mpl_token_metadata::{
-            instructions::{TransferCpi, TransferCpiAccounts, TransferInstructionArgs}, 
-            types::TokenStandard}
-        },
+    metadata::{MetadataAccount, MasterEditionAccount, Metadata, TokenRecordAccount},
     associated_token::AssociatedToken
 };
-use mpl_token_metadata::types::TransferArgs;
 
-use crate::state::Escrow;
+use crate::{
+    state::{Escrow, Payees},
+    errors::EscrowError,
+    marketplace::marketplace_seed,
+    transfer::VaultTransfer,
+};
 
 #[derive(Accounts)]
 pub struct Close<'info> {
     #[account(mut)]
     pub maker: Signer<'info>,
+    #[account(mut, address = escrow.payer @ EscrowError::InvalidPayer)]
+    /// CHECK: we're checking the address
+    pub payer: UncheckedAccount<'info>,
     #[account(
         mut,
         associated_token::mint = mint_a,
@@ -73,10 +76,19 @@ pub struct Close<'info> {
         associated_token::authority = escrow
     )]
     pub vault: Box<Account<'info, TokenAccount>>,
+    // Only needed to close a split escrow
+    #[account(
+        mut,
+        close = payer,
+        has_one = escrow,
+        seeds = [b"payees", escrow.key().as_ref()],
+        bump,
+    )]
+    pub payees: Option<Box<Account<'info, Payees>>>,
     #[account(
         mut,
-        close = maker,
-        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref()],
+        close = payer,
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref(), marketplace_seed(&escrow.marketplace)],
         bump,
     )]
     pub escrow: Box<Account<'info, Escrow>>,
@@ -96,109 +108,71 @@ impl<'info> Close<'info> {
         bumps: CloseBumps,
     ) -> Result<()> {
 
-        let mint_a_key = self.mint_a.key();
-        let metadata_a_token_standard = self.metadata_a.token_standard.as_ref().unwrap();
-        let token_metadata_program_key = self.token_metadata_program.key();
-        let master_edition_info: AccountInfo<'_>;
-        let token_record_info: AccountInfo<'_>;
-        let vault_token_record_info: AccountInfo<'_>;
-
-        // Set-up the Instruction based on the token standard
-        let mut edition: Option<&AccountInfo> = None;
-        let mut token_record: Option<&AccountInfo> = None;
-        let mut destination_token_record: Option<&AccountInfo> = None;
-        let mut transfer_args = TransferArgs::V1 {
-            amount: self.vault.amount,
-            authorization_data: None,
-        };
-
-        if metadata_a_token_standard == &TokenStandard::NonFungible || metadata_a_token_standard == &TokenStandard::ProgrammableNonFungible {
-            master_edition_info = self.master_edition_a.as_ref().unwrap().to_account_info();
-            edition = Some(&master_edition_info);
-            transfer_args = TransferArgs::V1 {
-                amount: 1,
-                authorization_data: None,
-            };
-        } 
-        
-        if metadata_a_token_standard == &TokenStandard::ProgrammableNonFungible {
-
-            //Check the token record
-            let token_record_seed = [
-                b"metadata",
-                token_metadata_program_key.as_ref(),
-                mint_a_key.as_ref(),
-                b"token_record",
-                self.maker_ata.to_account_info().key.as_ref(),
-            ];
-            let (maker_token_record_a, _bump) = Pubkey::find_program_address(&token_record_seed, &token_metadata_program_key);
-            require_eq!(maker_token_record_a, self.maker_token_record_a.key());
-
-            token_record_info = self.vault_token_record_a.as_ref().unwrap().to_account_info();
-            vault_token_record_info = self.maker_token_record_a.to_account_info();
-
-            token_record = Some(&token_record_info);
-            destination_token_record = Some(&vault_token_record_info);
-        };
-
-        // Build the TransferCpi instruction to transfer the token from the maker to the escrow
-        let program = &self.token_metadata_program.to_account_info();
-        let token = &self.vault.to_account_info();
-        let token_owner = &self.escrow.to_account_info();
-        let destination_token = &self.maker_ata.to_account_info();
-        let destination_owner = &self.maker.to_account_info();
-        let mint = &self.mint_a.to_account_info();
-        let metadata = &self.metadata_a.to_account_info();
-        let authority = &self.escrow.to_account_info();
-        let payer = &self.maker.to_account_info();
-        let system_program = &self.system_program.to_account_info();
-        let sysvar_instructions = &self.sysvar_instructions.to_account_info();
-        let spl_token_program = &self.token_program.to_account_info();
-        let spl_ata_program = &self.associated_token_program.to_account_info();
-        //TODO After
-        let authorization_rules_program = None;
-        let authorization_rules = None;
-
-        let transfer_cpi = TransferCpi::new(
-            program,
-            TransferCpiAccounts {
-                token,
-                token_owner,
-                destination_token,
-                destination_owner,
-                mint,
-                metadata,
-                edition,
-                token_record,
-                destination_token_record,
-                authority,
-                payer,
-                system_program,
-                sysvar_instructions,
-                spl_token_program,
-                spl_ata_program,
-                authorization_rules_program,
-                authorization_rules,              
-            },
-            TransferInstructionArgs {
-                transfer_args,
-            },
-        );
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+
+        // Delegated listings never moved the token, they're closed with close_delegated
+        require!(!self.escrow.delegated, EscrowError::InvalidListingMode);
+        // Collateral leaves the vault through close_loan or foreclose
+        require!(!self.escrow.collateral, EscrowError::InvalidListingMode);
+
+        // Once someone bid on an Auction, the bids are locked until it gets settled
+        if let Some(english_auction) = &self.escrow.english_auction {
+            require!(english_auction.highest_bidder.is_none(), EscrowError::AuctionHasBids);
+        }
+
+        // Once the payment is deposited, an arbitrated or two-sided escrow can only be settled
+        if let Some(arbitration) = &self.escrow.arbitration {
+            require!(arbitration.buyer.is_none(), EscrowError::PaymentDeposited);
+        }
+        if let Some(confirmation) = &self.escrow.confirmation {
+            require!(confirmation.buyer.is_none(), EscrowError::PaymentDeposited);
+        }
+
+        // The payees of a split escrow are closed with it
+        require!(!self.escrow.split || self.payees.is_some(), EscrowError::InvalidPayees);
+
+        // A rented token is out of the vault until it gets reclaimed
+        if let Some(rental) = &self.escrow.rental {
+            require!(rental.renter.is_none(), EscrowError::AssetRented);
+        }
 
         let mint_a_key = self.mint_a.key();
         let mint_b_key = self.mint_b.key();
         let maker_key = self.maker.key();
+        let marketplace_key = self.escrow.marketplace;
 
         let seeds = &[
             "escrow".as_bytes(),
             maker_key.as_ref(),
             mint_a_key.as_ref(),
             mint_b_key.as_ref(),
+            marketplace_seed(&marketplace_key),
             &[bumps.escrow]
         ];
         let signer_seeds = &[&seeds[..]];
 
-        transfer_cpi.invoke_signed(signer_seeds)?;
+        let master_edition = self.master_edition_a.as_ref().map(|master_edition| master_edition.to_account_info());
+        let vault_token_record = self.vault_token_record_a.as_ref().map(|token_record| token_record.to_account_info());
+
+        // Give the token back to the maker
+        VaultTransfer {
+            escrow: &self.escrow.to_account_info(),
+            owner: &self.escrow.to_account_info(),
+            vault: &self.vault,
+            destination_ata: &self.maker_ata.to_account_info(),
+            destination_owner: &self.maker.to_account_info(),
+            mint: &self.mint_a.to_account_info(),
+            metadata: &self.metadata_a,
+            master_edition: master_edition.as_ref(),
+            vault_token_record: vault_token_record.as_ref(),
+            destination_token_record: &self.maker_token_record_a.to_account_info(),
+            payer: &self.maker.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+            sysvar_instructions: &self.sysvar_instructions.to_account_info(),
+            token_program: &self.token_program.to_account_info(),
+            associated_token_program: &self.associated_token_program.to_account_info(),
+            token_metadata_program: &self.token_metadata_program.to_account_info(),
+        }.invoke_signed(signer_seeds)?;
 
         Ok(())
     }
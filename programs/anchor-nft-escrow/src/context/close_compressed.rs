@@ -0,0 +1,140 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+use mpl_bubblegum::{
+    instructions::{TransferCpi, TransferCpiAccounts, TransferInstructionArgs, DelegateCpi, DelegateCpiAccounts, DelegateInstructionArgs},
+    programs::{MPL_BUBBLEGUM_ID, SPL_ACCOUNT_COMPRESSION_ID, SPL_NOOP_ID},
+    utils::get_asset_id,
+};
+
+use crate::{
+    state::{Escrow, CompressedLeaf},
+    errors::EscrowError,
+};
+
+#[derive(Accounts)]
+#[instruction(leaf: CompressedLeaf)]
+pub struct CloseCompressed<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(mut, address = escrow.payer @ EscrowError::InvalidPayer)]
+    /// CHECK: we're checking the address
+    pub payer: UncheckedAccount<'info>,
+
+    /// CHECK: Bubblegum checks this against the merkle tree
+    pub tree_config: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: Bubblegum checks this against the leaf
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        close = payer,
+        has_one = mint_b,
+        seeds = [b"escrow", maker.key().as_ref(), get_asset_id(&merkle_tree.key(), leaf.nonce).as_ref(), mint_b.key().as_ref()],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+
+    #[account(address = MPL_BUBBLEGUM_ID)]
+    /// CHECK: we're checking the address
+    pub bubblegum_program: UncheckedAccount<'info>,
+    #[account(address = SPL_NOOP_ID)]
+    /// CHECK: we're checking the address
+    pub log_wrapper: UncheckedAccount<'info>,
+    #[account(address = SPL_ACCOUNT_COMPRESSION_ID)]
+    /// CHECK: we're checking the address
+    pub compression_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> CloseCompressed<'info> {
+    // The remaining accounts are the proof of the leaf
+    pub fn close_compressed(
+        &mut self,
+        leaf: CompressedLeaf,
+        proof: &[AccountInfo<'info>],
+        bumps: CloseCompressedBumps,
+    ) -> Result<()> {
+
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+
+        let proof = proof.iter().map(|node| (node, false, false)).collect::<Vec<_>>();
+
+        let program = &self.bubblegum_program.to_account_info();
+        let tree_config = &self.tree_config.to_account_info();
+        let maker = &self.maker.to_account_info();
+        let escrow = &self.escrow.to_account_info();
+        let merkle_tree = &self.merkle_tree.to_account_info();
+        let log_wrapper = &self.log_wrapper.to_account_info();
+        let compression_program = &self.compression_program.to_account_info();
+        let system_program = &self.system_program.to_account_info();
+
+        // Delegated listings only need the maker to take the delegation back
+        if self.escrow.delegated {
+            let delegate_cpi = DelegateCpi::new(
+                program,
+                DelegateCpiAccounts {
+                    tree_config,
+                    leaf_owner: maker,
+                    previous_leaf_delegate: escrow,
+                    new_leaf_delegate: maker,
+                    merkle_tree,
+                    log_wrapper,
+                    compression_program,
+                    system_program,
+                },
+                DelegateInstructionArgs {
+                    root: leaf.root,
+                    data_hash: leaf.data_hash,
+                    creator_hash: leaf.creator_hash,
+                    nonce: leaf.nonce,
+                    index: leaf.index,
+                },
+            );
+
+            delegate_cpi.invoke_with_remaining_accounts(&proof)?;
+
+            return Ok(());
+        }
+
+        // Give the leaf back to the maker
+        let asset_id = self.escrow.mint_a;
+        let mint_b_key = self.mint_b.key();
+        let maker_key = self.maker.key();
+
+        let seeds = &[
+            "escrow".as_bytes(),
+            maker_key.as_ref(),
+            asset_id.as_ref(),
+            mint_b_key.as_ref(),
+            &[bumps.escrow]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let transfer_cpi = TransferCpi::new(
+            program,
+            TransferCpiAccounts {
+                tree_config,
+                leaf_owner: (escrow, true),
+                leaf_delegate: (escrow, false),
+                new_leaf_owner: maker,
+                merkle_tree,
+                log_wrapper,
+                compression_program,
+                system_program,
+            },
+            TransferInstructionArgs {
+                root: leaf.root,
+                data_hash: leaf.data_hash,
+                creator_hash: leaf.creator_hash,
+                nonce: leaf.nonce,
+                index: leaf.index,
+            },
+        );
+
+        transfer_cpi.invoke_signed_with_remaining_accounts(signer_seeds, &proof)?;
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,77 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+
+use crate::{
+    state::Escrow,
+    errors::EscrowError,
+    mpl_core::{MPL_CORE_ID, TransferV1Cpi, load_asset},
+};
+
+#[derive(Accounts)]
+pub struct CloseCore<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(mut, address = escrow.payer @ EscrowError::InvalidPayer)]
+    /// CHECK: we're checking the address
+    pub payer: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    /// CHECK: we're checking this against the Core program later
+    pub asset: UncheckedAccount<'info>,
+    /// CHECK: we're checking this against the asset later
+    pub collection: Option<UncheckedAccount<'info>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        close = payer,
+        has_one = mint_b,
+        seeds = [b"escrow", maker.key().as_ref(), asset.key().as_ref(), mint_b.key().as_ref()],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+
+    #[account(address = MPL_CORE_ID)]
+    /// CHECK: we're checking the address
+    pub core_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> CloseCore<'info> {
+    pub fn close_core(
+        &mut self,
+        bumps: CloseCoreBumps,
+    ) -> Result<()> {
+
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+
+        let collection = self.collection.as_ref().map(|collection| collection.to_account_info());
+        load_asset(&self.asset.to_account_info(), collection.as_ref())?;
+
+        let asset_key = self.asset.key();
+        let mint_b_key = self.mint_b.key();
+        let maker_key = self.maker.key();
+
+        let seeds = &[
+            "escrow".as_bytes(),
+            maker_key.as_ref(),
+            asset_key.as_ref(),
+            mint_b_key.as_ref(),
+            &[bumps.escrow]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        // Give the asset back to the maker
+        TransferV1Cpi {
+            core_program: &self.core_program.to_account_info(),
+            asset: &self.asset.to_account_info(),
+            collection: collection.as_ref(),
+            payer: &self.maker.to_account_info(),
+            authority: &self.escrow.to_account_info(),
+            new_owner: &self.maker.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+        }.invoke_signed(signer_seeds)?;
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,200 @@
+use solana_program::*;
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Revoke, revoke},
+    metadata::{MetadataAccount, MasterEditionAccount, Metadata, TokenRecordAccount,
+        mpl_token_metadata::{
+            instructions::{RevokeCpi, RevokeCpiAccounts, RevokeInstructionArgs, ThawDelegatedAccountCpi, ThawDelegatedAccountCpiAccounts},
+            types::{TokenStandard, RevokeArgs}}
+        },
+};
+
+use crate::{
+    state::Escrow,
+    errors::EscrowError,
+};
+
+#[derive(Accounts)]
+pub struct CloseDelegated<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(mut, address = escrow.payer @ EscrowError::InvalidPayer)]
+    /// CHECK: we're checking the address
+    pub payer: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker
+    )]
+    pub maker_ata: Box<Account<'info, TokenAccount>>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_a: Box<Account<'info, MetadataAccount>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"edition",
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub master_edition_a: Box<Account<'info, MasterEditionAccount>>,
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"token_record",
+            maker_ata.key().as_ref(),
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub maker_token_record_a: Option<Box<Account<'info, TokenRecordAccount>>>,
+
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+
+    #[account(address = sysvar::instructions::id())]
+    /// CHECK: we don't need to check this
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> CloseDelegated<'info> {
+    pub fn close_delegated(
+        &mut self,
+        bumps: CloseDelegatedBumps,
+    ) -> Result<()> {
+
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+
+        require!(self.escrow.delegated, EscrowError::InvalidListingMode);
+
+        match self.metadata_a.token_standard.as_ref().unwrap() {
+            TokenStandard::ProgrammableNonFungible => self.revoke_programmable(),
+            TokenStandard::NonFungible => self.thaw_and_revoke(bumps),
+            _ => err!(EscrowError::InvalidTokenStandard),
+        }
+    }
+
+    // Revoking the Sale delegate unlocks the token in the maker's wallet
+    fn revoke_programmable(
+        &self,
+    ) -> Result<()> {
+
+        let program = &self.token_metadata_program.to_account_info();
+        let delegate = &self.escrow.to_account_info();
+        let metadata = &self.metadata_a.to_account_info();
+        let master_edition = &self.master_edition_a.to_account_info();
+        let token_record = &self.maker_token_record_a.as_ref().ok_or(EscrowError::TokenRecordAccountDoesNotMatch)?.to_account_info();
+        let mint = &self.mint_a.to_account_info();
+        let token = &self.maker_ata.to_account_info();
+        let authority = &self.maker.to_account_info();
+        let payer = &self.maker.to_account_info();
+        let system_program = &self.system_program.to_account_info();
+        let sysvar_instructions = &self.sysvar_instructions.to_account_info();
+        let spl_token_program = &self.token_program.to_account_info();
+
+        let revoke_cpi = RevokeCpi::new(
+            program,
+            RevokeCpiAccounts {
+                delegate_record: None,
+                delegate,
+                metadata,
+                master_edition: Some(master_edition),
+                token_record: Some(token_record),
+                mint,
+                token: Some(token),
+                authority,
+                payer,
+                system_program,
+                sysvar_instructions,
+                spl_token_program: Some(spl_token_program),
+                //TODO After
+                authorization_rules_program: None,
+                authorization_rules: None,
+            },
+            RevokeInstructionArgs {
+                revoke_args: RevokeArgs::SaleV1,
+            },
+        );
+
+        revoke_cpi.invoke()?;
+
+        Ok(())
+    }
+
+    // The escrow thaws the NFT before the maker revokes its SPL delegation
+    fn thaw_and_revoke(
+        &self,
+        bumps: CloseDelegatedBumps,
+    ) -> Result<()> {
+
+        let mint_a_key = self.mint_a.key();
+        let mint_b_key = self.mint_b.key();
+        let maker_key = self.maker.key();
+
+        let seeds = &[
+            "escrow".as_bytes(),
+            maker_key.as_ref(),
+            mint_a_key.as_ref(),
+            mint_b_key.as_ref(),
+            &[bumps.escrow]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let program = &self.token_metadata_program.to_account_info();
+        let delegate = &self.escrow.to_account_info();
+        let token_account = &self.maker_ata.to_account_info();
+        let edition = &self.master_edition_a.to_account_info();
+        let mint = &self.mint_a.to_account_info();
+        let token_program = &self.token_program.to_account_info();
+
+        let thaw_cpi = ThawDelegatedAccountCpi::new(
+            program,
+            ThawDelegatedAccountCpiAccounts {
+                delegate,
+                token_account,
+                edition,
+                mint,
+                token_program,
+            },
+        );
+
+        thaw_cpi.invoke_signed(signer_seeds)?;
+
+        let cpi_accounts = Revoke {
+            source: self.maker_ata.to_account_info(),
+            authority: self.maker.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        revoke(cpi_ctx)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use solana_program::*;
+
+use anchor_lang::{prelude::*, system_program};
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, transfer, spl_token::native_mint},
+    metadata::{MetadataAccount, MasterEditionAccount, Metadata, TokenRecordAccount},
+    associated_token::AssociatedToken
+};
+
+use crate::{
+    state::{Escrow, Loan},
+    errors::EscrowError,
+    marketplace::marketplace_seed,
+    transfer::VaultTransfer,
+};
+
+#[derive(Accounts)]
+pub struct CloseLoan<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(mut, address = escrow.payer @ EscrowError::InvalidPayer)]
+    /// CHECK: we're checking the address
+    pub payer: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker
+    )]
+    pub maker_ata_a: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub lender: Option<SystemAccount<'info>>,
+    // Only needed to repay a SOL loan for a maker that can't send lamports, e.g. a PDA holding data
+    #[account(mut)]
+    pub repayer: Option<Signer<'info>>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    // Only needed to repay a loan that is not in SOL
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker
+    )]
+    pub maker_ata_b: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(mut)]
+    pub lender_ata_b: Option<Box<Account<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_a: Box<Account<'info, MetadataAccount>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"edition",
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub master_edition_a: Option<Box<Account<'info, MasterEditionAccount>>>,
+    #[account(mut)]
+    pub vault_token_record_a: Option<Box<Account<'info, TokenRecordAccount>>>,
+    #[account(mut)]
+    /// CHECK: we're checking this later
+    pub maker_token_record_a: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref(), marketplace_seed(&escrow.marketplace)],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+    #[account(
+        mut,
+        close = payer,
+        has_one = escrow,
+        seeds = [b"loan", escrow.key().as_ref()],
+        bump,
+    )]
+    pub loan: Box<Account<'info, Loan>>,
+
+    #[account(address = sysvar::instructions::id())]
+    /// CHECK: we don't need to check this
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> CloseLoan<'info> {
+    // Cancels a loan nobody funded yet, or repays a funded one before its deadline.
+    // Either way the collateral goes back to the maker
+    pub fn close_loan(
+        &mut self,
+        bumps: CloseLoanBumps,
+    ) -> Result<()> {
+
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+
+        if let Some(lender) = self.loan.lender {
+            require!(Clock::get()?.unix_timestamp < self.loan.deadline()?, EscrowError::LoanExpired);
+            self.repay(lender)?;
+        }
+
+        let mint_a_key = self.mint_a.key();
+        let mint_b_key = self.mint_b.key();
+        let maker_key = self.maker.key();
+        let marketplace_key = self.escrow.marketplace;
+
+        let seeds = &[
+            "escrow".as_bytes(),
+            maker_key.as_ref(),
+            mint_a_key.as_ref(),
+            mint_b_key.as_ref(),
+            marketplace_seed(&marketplace_key),
+            &[bumps.escrow]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let master_edition = self.master_edition_a.as_ref().map(|master_edition| master_edition.to_account_info());
+        let vault_token_record = self.vault_token_record_a.as_ref().map(|token_record| token_record.to_account_info());
+
+        VaultTransfer {
+            escrow: &self.escrow.to_account_info(),
+            owner: &self.escrow.to_account_info(),
+            vault: &self.vault,
+            destination_ata: &self.maker_ata_a.to_account_info(),
+            destination_owner: &self.maker.to_account_info(),
+            mint: &self.mint_a.to_account_info(),
+            metadata: &self.metadata_a,
+            master_edition: master_edition.as_ref(),
+            vault_token_record: vault_token_record.as_ref(),
+            destination_token_record: &self.maker_token_record_a.to_account_info(),
+            payer: &self.maker.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+            sysvar_instructions: &self.sysvar_instructions.to_account_info(),
+            token_program: &self.token_program.to_account_info(),
+            associated_token_program: &self.associated_token_program.to_account_info(),
+            token_metadata_program: &self.token_metadata_program.to_account_info(),
+        }.invoke_signed(signer_seeds)?;
+
+        Ok(())
+    }
+
+    // Pays the principal plus interest to the lender, in SOL or mint_b
+    fn repay(
+        &self,
+        lender: Pubkey,
+    ) -> Result<()> {
+
+        let lender_account = self.lender.as_ref().ok_or(EscrowError::InvalidLender)?;
+        require_keys_eq!(lender_account.key(), lender, EscrowError::InvalidLender);
+
+        let repayment = self.loan.repayment()?;
+
+        if self.mint_b.key() == native_mint::id() {
+            let repayer = self.repayer.as_ref().map_or(self.maker.to_account_info(), |repayer| repayer.to_account_info());
+
+            let cpi_accounts = system_program::Transfer {
+                from: repayer,
+                to: lender_account.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(self.system_program.to_account_info(), cpi_accounts);
+
+            system_program::transfer(cpi_ctx, repayment)?;
+        } else {
+            let maker_ata_b = self.maker_ata_b.as_ref().ok_or(EscrowError::InvalidPaymentAccounts)?;
+            let lender_ata_b = self.lender_ata_b.as_ref().ok_or(EscrowError::InvalidPaymentAccounts)?;
+            require!(
+                lender_ata_b.owner == lender && lender_ata_b.mint == self.mint_b.key(),
+                EscrowError::InvalidPaymentAccounts
+            );
+
+            let cpi_accounts = Transfer {
+                from: maker_ata_b.to_account_info(),
+                to: lender_ata_b.to_account_info(),
+                authority: self.maker.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+            transfer(cpi_ctx, repayment)?;
+        }
+
+        Ok(())
+    }
+}
\ No newline at end of file
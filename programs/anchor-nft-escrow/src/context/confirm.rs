@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+
+use crate::{
+    state::Escrow,
+    errors::EscrowError,
+    marketplace::marketplace_seed,
+};
+
+#[derive(Accounts)]
+pub struct Confirm<'info> {
+    pub party: Signer<'info>,
+    /// CHECK: we're checking the seeds
+    pub maker: UncheckedAccount<'info>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref(), marketplace_seed(&escrow.marketplace)],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+}
+
+impl<'info> Confirm<'info> {
+    // Each side confirms the off-chain conditions of the trade were met
+    pub fn confirm(
+        &mut self,
+    ) -> Result<()> {
+
+        let mut confirmation = self.escrow.confirmation.ok_or(EscrowError::NotTwoSided)?;
+        let buyer = confirmation.buyer.ok_or(EscrowError::PaymentNotDeposited)?;
+
+        if self.party.key() == self.maker.key() {
+            require!(!confirmation.maker_confirmed, EscrowError::AlreadyConfirmed);
+            confirmation.maker_confirmed = true;
+        } else if self.party.key() == buyer {
+            require!(!confirmation.buyer_confirmed, EscrowError::AlreadyConfirmed);
+            confirmation.buyer_confirmed = true;
+        } else {
+            return err!(EscrowError::InvalidParty);
+        }
+
+        self.escrow.confirmation = Some(confirmation);
+
+        Ok(())
+    }
+}
\ No newline at end of file
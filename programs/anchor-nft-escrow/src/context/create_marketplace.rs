@@ -0,0 +1,60 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    state::Marketplace,
+    errors::EscrowError,
+};
+
+#[derive(Accounts)]
+#[instruction(id: Pubkey)]
+pub struct CreateMarketplace<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"marketplace", id.as_ref()],
+        bump,
+        space = Marketplace::space()
+    )]
+    pub marketplace: Box<Account<'info, Marketplace>>,
+    // Fees are paid to the token accounts of this account
+    /// CHECK: any account can receive the fees, only its address is stored
+    pub treasury: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> CreateMarketplace<'info> {
+    // Any unused key works as the id, e.g. the address of a fresh keypair
+    pub fn create_marketplace(
+        &mut self,
+        id: Pubkey,
+        curated: bool,
+        allowed_collections: Vec<Pubkey>,
+        allowed_payment_mints: Vec<Pubkey>,
+        fee_bps: u16,
+        requires_signoff: bool,
+    ) -> Result<()> {
+
+        require!(allowed_collections.len() <= Marketplace::MAX_COLLECTIONS, EscrowError::TooManyCollections);
+        require!(allowed_payment_mints.len() <= Marketplace::MAX_PAYMENT_MINTS, EscrowError::TooManyPaymentMints);
+        require!(fee_bps <= 10_000, EscrowError::InvalidMarketplaceFee);
+
+        self.marketplace.set_inner(
+            Marketplace {
+                id,
+                authority: self.authority.key(),
+                curated,
+                allowed_collections,
+                allowed_payment_mints,
+                fee_bps,
+                treasury: self.treasury.key(),
+                requires_signoff,
+            }
+        );
+
+        Ok(())
+    }
+}
\ No newline at end of file
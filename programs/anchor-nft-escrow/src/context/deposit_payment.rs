@@ -0,0 +1,117 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, transfer},
+    metadata::{MetadataAccount, Metadata},
+    associated_token::AssociatedToken
+};
+
+use crate::{
+    state::{Escrow, Config, Marketplace},
+    errors::EscrowError,
+    denylist::check_not_denylisted,
+    marketplace::{check_marketplace, marketplace_seed},
+};
+
+#[derive(Accounts)]
+pub struct DepositPayment<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    /// CHECK: we're checking the seeds
+    pub maker: UncheckedAccount<'info>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_a: Box<Account<'info, MetadataAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = buyer
+    )]
+    pub buyer_ata_b: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = mint_b,
+        associated_token::authority = escrow
+    )]
+    pub payment_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref(), marketplace_seed(&escrow.marketplace)],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    /// CHECK: checked against mint_a later
+    pub mint_denylist: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection of mint_a later
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+    pub marketplace: Option<Box<Account<'info, Marketplace>>>,
+
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> DepositPayment<'info> {
+    // The buyer locks the payment next to the asset, from here on the escrow settles through a release, a resolution or the confirmations
+    pub fn deposit_payment(
+        &mut self,
+    ) -> Result<()> {
+
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+        let collection_denylist = self.collection_denylist.as_ref().map(|denylist| denylist.to_account_info());
+        check_not_denylisted(&self.mint_a.key(), &self.metadata_a, &self.mint_denylist.to_account_info(), collection_denylist.as_ref())?;
+        check_marketplace(&self.escrow, self.marketplace.as_deref(), &self.metadata_a)?;
+
+        require!(
+            self.escrow.arbitration.is_some() || self.escrow.confirmation.is_some(),
+            EscrowError::DepositNotAllowed
+        );
+        require_keys_neq!(self.buyer.key(), self.maker.key(), EscrowError::InvalidParty);
+
+        let now = Clock::get()?.unix_timestamp;
+        let price = self.escrow.current_price(now);
+
+        let cpi_accounts = Transfer {
+            from: self.buyer_ata_b.to_account_info(),
+            to: self.payment_vault.to_account_info(),
+            authority: self.buyer.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        transfer(cpi_ctx, price)?;
+
+        if let Some(mut arbitration) = self.escrow.arbitration {
+            require!(arbitration.buyer.is_none(), EscrowError::PaymentDeposited);
+            arbitration.buyer = Some(self.buyer.key());
+            self.escrow.arbitration = Some(arbitration);
+        }
+
+        if let Some(mut confirmation) = self.escrow.confirmation {
+            require!(confirmation.buyer.is_none(), EscrowError::PaymentDeposited);
+            confirmation.buyer = Some(self.buyer.key());
+            confirmation.deadline = now.checked_add(confirmation.window).ok_or(EscrowError::Overflow)?;
+            self.escrow.confirmation = Some(confirmation);
+        }
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,46 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+
+use crate::{
+    state::Escrow,
+    errors::EscrowError,
+    marketplace::marketplace_seed,
+};
+
+#[derive(Accounts)]
+pub struct Dispute<'info> {
+    pub party: Signer<'info>,
+    /// CHECK: we're checking the seeds
+    pub maker: UncheckedAccount<'info>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref(), marketplace_seed(&escrow.marketplace)],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+}
+
+impl<'info> Dispute<'info> {
+    // Either side can freeze the settlement once both deposits are in, leaving it to the arbiter
+    pub fn dispute(
+        &mut self,
+    ) -> Result<()> {
+
+        let mut arbitration = self.escrow.arbitration.ok_or(EscrowError::NotArbitrated)?;
+        let buyer = arbitration.buyer.ok_or(EscrowError::PaymentNotDeposited)?;
+        require!(!arbitration.disputed, EscrowError::EscrowDisputed);
+        require!(
+            self.party.key() == self.maker.key() || self.party.key() == buyer,
+            EscrowError::InvalidParty
+        );
+
+        arbitration.disputed = true;
+        self.escrow.arbitration = Some(arbitration);
+
+        Ok(())
+    }
+}
\ No newline at end of file
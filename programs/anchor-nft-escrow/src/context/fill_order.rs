@@ -0,0 +1,201 @@
+use solana_program::sysvar;
+
+use anchor_lang::{prelude::*, system_program};
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, transfer, spl_token::native_mint},
+    metadata::{MetadataAccount, Metadata},
+    associated_token::AssociatedToken
+};
+use sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
+
+use crate::{
+    state::{Order, MakerNonce, OrderStatus, OrderState, Config},
+    errors::EscrowError,
+    denylist::check_not_denylisted,
+    events::OrderFill,
+    ed25519,
+};
+
+#[derive(Accounts)]
+#[instruction(order: Order)]
+pub struct FillOrder<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    #[account(mut, address = order.maker)]
+    pub maker: SystemAccount<'info>,
+
+    #[account(address = order.mint_a)]
+    pub mint_a: Box<Account<'info, Mint>>,
+    #[account(address = order.mint_b)]
+    pub mint_b: Box<Account<'info, Mint>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_a: Box<Account<'info, MetadataAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker
+    )]
+    pub maker_ata_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker
+    )]
+    pub taker_ata_a: Box<Account<'info, TokenAccount>>,
+
+    // Only needed when the order is not paid in SOL
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker
+    )]
+    pub taker_ata_b: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker
+    )]
+    pub maker_ata_b: Option<Box<Account<'info, TokenAccount>>>,
+
+    // Makers that never cancelled in bulk don't have a nonce account yet
+    #[account(
+        seeds = [b"nonce", maker.key().as_ref()],
+        bump,
+    )]
+    /// CHECK: we're deserializing this later if it exists
+    pub maker_nonce: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        seeds = [b"order", order.hash()?.as_ref()],
+        bump,
+        space = OrderStatus::space()
+    )]
+    pub order_status: Box<Account<'info, OrderStatus>>,
+
+    // Makers approve this PDA on their token account instead of vaulting the token
+    #[account(
+        seeds = [b"order_delegate"],
+        bump,
+    )]
+    /// CHECK: this is only used as a signer
+    pub delegate: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    /// CHECK: checked against mint_a later
+    pub mint_denylist: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection of mint_a later
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+
+    #[account(address = sysvar::instructions::id())]
+    /// CHECK: we don't need to check this
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> FillOrder<'info> {
+    pub fn fill_order(
+        &mut self,
+        order: Order,
+        bumps: FillOrderBumps,
+    ) -> Result<()> {
+
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+        let collection_denylist = self.collection_denylist.as_ref().map(|denylist| denylist.to_account_info());
+        check_not_denylisted(&self.mint_a.key(), &self.metadata_a, &self.mint_denylist.to_account_info(), collection_denylist.as_ref())?;
+
+        require!(Clock::get()?.unix_timestamp < order.expiry, EscrowError::OrderExpired);
+
+        // The instruction right before this one has to be the Ed25519 verification of the order signed by the maker
+        let index = load_current_index_checked(&self.sysvar_instructions.to_account_info())?;
+        require!(index > 0, EscrowError::InvalidOrderSignature);
+        let ix = load_instruction_at_checked(index as usize - 1, &self.sysvar_instructions.to_account_info())?;
+        require!(ed25519::verify(&ix, &order.maker, &order.message()?), EscrowError::InvalidOrderSignature);
+
+        // Make sure the order wasn't cancelled, in bulk or on its own, and wasn't filled already
+        if !self.maker_nonce.data_is_empty() {
+            let maker_nonce = MakerNonce::try_deserialize(&mut &self.maker_nonce.try_borrow_data()?[..])?;
+            require_gte!(order.nonce, maker_nonce.min_nonce, EscrowError::OrderCancelled);
+        }
+
+        match self.order_status.state {
+            OrderState::Open => {},
+            OrderState::Filled => return err!(EscrowError::OrderFilled),
+            OrderState::Cancelled => return err!(EscrowError::OrderCancelled),
+        }
+
+        self.order_status.set_inner(
+            OrderStatus {
+                maker: order.maker,
+                state: OrderState::Filled,
+            }
+        );
+
+        // Pay the maker, orders in the native mint are paid in SOL
+        if self.mint_b.key() == native_mint::id() {
+            let cpi_accounts = system_program::Transfer {
+                from: self.taker.to_account_info(),
+                to: self.maker.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(self.system_program.to_account_info(), cpi_accounts);
+
+            system_program::transfer(cpi_ctx, order.taker_amount)?;
+        } else {
+            let taker_ata_b = self.taker_ata_b.as_ref().ok_or(EscrowError::InvalidPaymentAccounts)?;
+            let maker_ata_b = self.maker_ata_b.as_ref().ok_or(EscrowError::InvalidPaymentAccounts)?;
+
+            let cpi_accounts = Transfer {
+                from: taker_ata_b.to_account_info(),
+                to: maker_ata_b.to_account_info(),
+                authority: self.taker.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+            transfer(cpi_ctx, order.taker_amount)?;
+        }
+
+        // Move the maker's token with the approval they gave to the delegate
+        let seeds = &[
+            "order_delegate".as_bytes(),
+            &[bumps.delegate]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: self.maker_ata_a.to_account_info(),
+            to: self.taker_ata_a.to_account_info(),
+            authority: self.delegate.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+        transfer(cpi_ctx, order.maker_amount)?;
+
+        emit!(OrderFill {
+            maker: order.maker,
+            taker: self.taker.key(),
+            mint_a: order.mint_a,
+            mint_b: order.mint_b,
+            maker_amount: order.maker_amount,
+            price: order.taker_amount,
+            nonce: order.nonce,
+        });
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,159 @@
+use solana_program::*;
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token},
+    metadata::{MetadataAccount, MasterEditionAccount, Metadata, TokenRecordAccount},
+    associated_token::AssociatedToken
+};
+
+use crate::{
+    state::{Escrow, Loan},
+    errors::EscrowError,
+    marketplace::marketplace_seed,
+    transfer::{VaultTransfer, close_vault},
+};
+
+#[derive(Accounts)]
+pub struct Foreclose<'info> {
+    #[account(mut)]
+    pub lender: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = lender,
+        associated_token::mint = mint_a,
+        associated_token::authority = lender
+    )]
+    pub lender_ata_a: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    /// CHECK: we're checking the seeds
+    pub maker: UncheckedAccount<'info>,
+    #[account(mut, address = escrow.payer @ EscrowError::InvalidPayer)]
+    /// CHECK: we're checking the address
+    pub payer: UncheckedAccount<'info>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_a: Box<Account<'info, MetadataAccount>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"edition",
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub master_edition_a: Option<Box<Account<'info, MasterEditionAccount>>>,
+    #[account(mut)]
+    pub vault_token_record_a: Option<Box<Account<'info, TokenRecordAccount>>>,
+    #[account(mut)]
+    /// CHECK: we're checking this later
+    pub lender_token_record_a: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref(), marketplace_seed(&escrow.marketplace)],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+    #[account(
+        mut,
+        close = payer,
+        has_one = escrow,
+        seeds = [b"loan", escrow.key().as_ref()],
+        bump,
+    )]
+    pub loan: Box<Account<'info, Loan>>,
+
+    #[account(address = sysvar::instructions::id())]
+    /// CHECK: we don't need to check this
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> Foreclose<'info> {
+    // Once the deadline passed without a repayment, the lender takes the collateral
+    pub fn foreclose(
+        &mut self,
+        bumps: ForecloseBumps,
+    ) -> Result<()> {
+
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+
+        let lender = self.loan.lender.ok_or(EscrowError::LoanNotFunded)?;
+        require_keys_eq!(self.lender.key(), lender, EscrowError::InvalidLender);
+        require!(Clock::get()?.unix_timestamp >= self.loan.deadline()?, EscrowError::LoanNotExpired);
+
+        let mint_a_key = self.mint_a.key();
+        let mint_b_key = self.mint_b.key();
+        let maker_key = self.maker.key();
+        let marketplace_key = self.escrow.marketplace;
+
+        let seeds = &[
+            "escrow".as_bytes(),
+            maker_key.as_ref(),
+            mint_a_key.as_ref(),
+            mint_b_key.as_ref(),
+            marketplace_seed(&marketplace_key),
+            &[bumps.escrow]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let master_edition = self.master_edition_a.as_ref().map(|master_edition| master_edition.to_account_info());
+        let vault_token_record = self.vault_token_record_a.as_ref().map(|token_record| token_record.to_account_info());
+
+        VaultTransfer {
+            escrow: &self.escrow.to_account_info(),
+            owner: &self.escrow.to_account_info(),
+            vault: &self.vault,
+            destination_ata: &self.lender_ata_a.to_account_info(),
+            destination_owner: &self.lender.to_account_info(),
+            mint: &self.mint_a.to_account_info(),
+            metadata: &self.metadata_a,
+            master_edition: master_edition.as_ref(),
+            vault_token_record: vault_token_record.as_ref(),
+            destination_token_record: &self.lender_token_record_a.to_account_info(),
+            payer: &self.lender.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+            sysvar_instructions: &self.sysvar_instructions.to_account_info(),
+            token_program: &self.token_program.to_account_info(),
+            associated_token_program: &self.associated_token_program.to_account_info(),
+            token_metadata_program: &self.token_metadata_program.to_account_info(),
+        }.invoke_signed(signer_seeds)?;
+
+        close_vault(
+            &self.vault.to_account_info(),
+            &self.metadata_a,
+            &self.payer.to_account_info(),
+            &self.escrow.to_account_info(),
+            &self.token_program.to_account_info(),
+            signer_seeds,
+        )?;
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,95 @@
+use anchor_lang::{prelude::*, system_program};
+use anchor_spl::token::{Mint, TokenAccount, Token, Transfer, transfer, spl_token::native_mint};
+
+use crate::{
+    state::{Escrow, Loan, Config},
+    errors::EscrowError,
+    marketplace::marketplace_seed,
+};
+
+#[derive(Accounts)]
+pub struct FundLoan<'info> {
+    #[account(mut)]
+    pub lender: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: we're checking the seeds
+    pub maker: UncheckedAccount<'info>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    // Only needed when the loan is not in SOL
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = lender
+    )]
+    pub lender_ata_b: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker
+    )]
+    pub maker_ata_b: Option<Box<Account<'info, TokenAccount>>>,
+
+    #[account(
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref(), marketplace_seed(&escrow.marketplace)],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        mut,
+        has_one = escrow,
+        seeds = [b"loan", escrow.key().as_ref()],
+        bump,
+    )]
+    pub loan: Box<Account<'info, Loan>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> FundLoan<'info> {
+    pub fn fund_loan(
+        &mut self,
+    ) -> Result<()> {
+
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+
+        require!(self.loan.lender.is_none(), EscrowError::LoanFunded);
+
+        // Loans in the native mint are paid out in SOL
+        if self.mint_b.key() == native_mint::id() {
+            let cpi_accounts = system_program::Transfer {
+                from: self.lender.to_account_info(),
+                to: self.maker.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(self.system_program.to_account_info(), cpi_accounts);
+
+            system_program::transfer(cpi_ctx, self.loan.principal)?;
+        } else {
+            let lender_ata_b = self.lender_ata_b.as_ref().ok_or(EscrowError::InvalidPaymentAccounts)?;
+            let maker_ata_b = self.maker_ata_b.as_ref().ok_or(EscrowError::InvalidPaymentAccounts)?;
+
+            let cpi_accounts = Transfer {
+                from: lender_ata_b.to_account_info(),
+                to: maker_ata_b.to_account_info(),
+                authority: self.lender.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+            transfer(cpi_ctx, self.loan.principal)?;
+        }
+
+        self.loan.lender = Some(self.lender.key());
+        self.loan.start_time = Clock::get()?.unix_timestamp;
+
+        Ok(())
+    }
+}
\ No newline at end of file
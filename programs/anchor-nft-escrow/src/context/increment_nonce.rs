@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    state::MakerNonce,
+    errors::EscrowError,
+};
+
+#[derive(Accounts)]
+pub struct IncrementNonce<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = maker,
+        seeds = [b"nonce", maker.key().as_ref()],
+        bump,
+        space = MakerNonce::space()
+    )]
+    pub maker_nonce: Box<Account<'info, MakerNonce>>,
+
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> IncrementNonce<'info> {
+    // Cancels every signed order of the maker with a nonce below min_nonce
+    pub fn increment_nonce(
+        &mut self,
+        min_nonce: u64,
+    ) -> Result<()> {
+
+        require_gt!(min_nonce, self.maker_nonce.min_nonce, EscrowError::InvalidNonce);
+
+        self.maker_nonce.set_inner(
+            MakerNonce {
+                maker: self.maker.key(),
+                min_nonce,
+            }
+        );
+
+        Ok(())
+    }
+}
\ No newline at end of file
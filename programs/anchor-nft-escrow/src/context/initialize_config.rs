@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    state::Config,
+    errors::EscrowError,
+    program::NftEscrow,
+};
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"config"],
+        bump,
+        space = Config::space()
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    pub program: Program<'info, NftEscrow>,
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> InitializeConfig<'info> {
+    // Only the upgrade authority of the program can create the config and become its admin
+    pub fn initialize_config(
+        &mut self,
+    ) -> Result<()> {
+
+        require!(self.program.programdata_address()? == Some(self.program_data.key()), EscrowError::InvalidAdmin);
+        require!(self.program_data.upgrade_authority_address == Some(self.admin.key()), EscrowError::InvalidAdmin);
+
+        self.config.set_inner(
+            Config {
+                admin: self.admin.key(),
+                paused: false,
+                referral_bps: 0,
+            }
+        );
+
+        Ok(())
+    }
+}
\ No newline at end of file
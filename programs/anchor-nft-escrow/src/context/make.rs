@@ -2,25 +2,31 @@ use solana_program::*;
 
 use anchor_lang::prelude::*;
 use anchor_spl::{
-    token::{Mint, TokenAccount, Token}, 
+    token::{Mint, TokenAccount, Token, spl_token::native_mint}, 
     metadata::{MetadataAccount, MasterEditionAccount, Metadata, TokenRecordAccount, 
-        mpl_token_metadata::{
-            instructions::{TransferCpi, TransferCpiAccounts, TransferInstructionArgs}, 
-            types::TokenStandard}
+        mpl_token_metadata::types::TokenStandard
         },
     associated_token::AssociatedToken
 };
-use mpl_token_metadata::types::TransferArgs;
 
-use crate::state::Escrow;
+use crate::{
+    state::{Escrow, DutchAuction, EnglishAuction, Rental, Arbitration, Confirmation, Config, Marketplace},
+    errors::EscrowError,
+    denylist::check_not_denylisted,
+    marketplace::{check_listing, marketplace_seed},
+    transfer::{AssetTransfer, is_non_fungible},
+};
 
 #[derive(Accounts)]
 pub struct Make<'info> {
-    #[account(mut)]
     pub maker: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account can receive the proceeds, only its address is stored
+    pub proceeds_recipient: Option<UncheckedAccount<'info>>,
     #[account(
         init_if_needed,
-        payer = maker,
+        payer = payer,
         associated_token::mint = mint_a,
         associated_token::authority = maker
     )]
@@ -67,6 +73,7 @@ pub struct Make<'info> {
     pub vault_token_record_a: UncheckedAccount<'info>,
 
     pub mint_b: Box<Account<'info, Mint>>,
+    // Not needed for payments in SOL, the native mint has no metadata
     #[account(
         mut,
         seeds = [
@@ -77,24 +84,36 @@ pub struct Make<'info> {
         seeds::program = token_metadata_program.key(),
         bump,
     )]
-    pub metadata_b: Box<Account<'info, MetadataAccount>>,
+    pub metadata_b: Option<Box<Account<'info, MetadataAccount>>>,
 
 
     #[account(
         init_if_needed,
-        payer = maker,
+        payer = payer,
         associated_token::mint = mint_a,
         associated_token::authority = escrow
     )]
     pub vault: Box<Account<'info, TokenAccount>>,
     #[account(
         init,
-        payer = maker,
-        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref()],
+        payer = payer,
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref(), marketplace_seed(&marketplace.as_ref().map(|marketplace| marketplace.key()))],
         bump,
         space = Escrow::space()
     )]
     pub escrow: Box<Account<'info, Escrow>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    /// CHECK: checked against mint_a later
+    pub mint_denylist: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection of mint_a later
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+    pub marketplace: Option<Box<Account<'info, Marketplace>>>,
+    // Only needed for marketplaces that sign off their listings
+    pub marketplace_authority: Option<Signer<'info>>,
 
     #[account(address = sysvar::instructions::id())]
     /// CHECK: we don't need to check this
@@ -110,103 +129,63 @@ impl<'info> Make<'info> {
         &mut self, 
         amount: u64,
         taker_amount: u64,
+        dutch_auction: Option<DutchAuction>,
+        arbitration: Option<Arbitration>,
     ) -> Result<()> {
         
-        //All deferred errors
-        let mint_a_key = self.mint_a.key();
-        let metadata_a_token_standard = self.metadata_a.token_standard.as_ref().unwrap();
-        let token_metadata_program_key = self.token_metadata_program.key();
-        let master_edition_info: AccountInfo<'_>;
-        let token_record_info: AccountInfo<'_>;
-        let vault_token_record_info: AccountInfo<'_>;
-
-        // Set-up the Instruction based on the token standard - We set it up as a Fungible by default
-        let mut edition: Option<&AccountInfo> = None;
-        let mut token_record: Option<&AccountInfo> = None;
-        let mut destination_token_record: Option<&AccountInfo> = None;
-        let mut transfer_args = TransferArgs::V1 {
-            amount,
-            authorization_data: None,
-        };
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+        let collection_denylist = self.collection_denylist.as_ref().map(|denylist| denylist.to_account_info());
+        check_not_denylisted(&self.mint_a.key(), &self.metadata_a, &self.mint_denylist.to_account_info(), collection_denylist.as_ref())?;
+
+        if let Some(marketplace) = &self.marketplace {
+            check_listing(marketplace, &self.metadata_a, &self.mint_b.key(), self.marketplace_authority.as_ref())?;
+        }
+
         let mut taker_amount = taker_amount;
 
-        if metadata_a_token_standard == &TokenStandard::NonFungible || metadata_a_token_standard == &TokenStandard::ProgrammableNonFungible {
-            master_edition_info = self.master_edition_a.as_ref().unwrap().to_account_info();
-            edition = Some(&master_edition_info);
-            transfer_args = TransferArgs::V1 {
-                amount: 1,
-                authorization_data: None,
-            };
-        } 
-        
-        if metadata_a_token_standard == &TokenStandard::ProgrammableNonFungible {
-
-            //Check the token record
-            let token_record_seed = [
-                b"metadata",
-                token_metadata_program_key.as_ref(),
-                mint_a_key.as_ref(),
-                b"token_record",
-                self.vault.to_account_info().key.as_ref(),
-            ];
-            let (vault_token_record_a, _bump) = Pubkey::find_program_address(&token_record_seed, &token_metadata_program_key);
-            require_eq!(vault_token_record_a, self.vault_token_record_a.key());
-
-            token_record_info = self.maker_token_record_a.as_ref().unwrap().to_account_info();
-            vault_token_record_info = self.vault_token_record_a.to_account_info();
-
-            token_record = Some(&token_record_info);
-            destination_token_record = Some(&vault_token_record_info);
-        };
-
-        // Build the TransferCpi instruction to transfer the token from the maker to the escrow
-        let program = &self.token_metadata_program.to_account_info();
-        let token = &self.maker_ata.to_account_info();
-        let token_owner = &self.maker.to_account_info();
-        let destination_token = &self.vault.to_account_info();
-        let destination_owner = &self.escrow.to_account_info();
-        let mint = &self.mint_a.to_account_info();
-        let metadata = &self.metadata_a.to_account_info();
-        let authority = &self.maker.to_account_info();
-        let payer = &self.maker.to_account_info();
-        let system_program = &self.system_program.to_account_info();
-        let sysvar_instructions = &self.sysvar_instructions.to_account_info();
-        let spl_token_program = &self.token_program.to_account_info();
-        let spl_ata_program = &self.associated_token_program.to_account_info();
-        //TODO After
-        let authorization_rules_program = None;
-        let authorization_rules = None;
-
-        let transfer_cpi = TransferCpi::new(
-            program,
-            TransferCpiAccounts {
-                token,
-                token_owner,
-                destination_token,
-                destination_owner,
-                mint,
-                metadata,
-                edition,
-                token_record,
-                destination_token_record,
-                authority,
-                payer,
-                system_program,
-                sysvar_instructions,
-                spl_token_program,
-                spl_ata_program,
-                authorization_rules_program,
-                authorization_rules,              
-            },
-            TransferInstructionArgs {
-                transfer_args,
-            },
-        );
+        let master_edition = self.master_edition_a.as_ref().map(|master_edition| master_edition.to_account_info());
+        let maker_token_record = self.maker_token_record_a.as_ref().map(|token_record| token_record.to_account_info());
+        let vault_token_record = self.vault_token_record_a.to_account_info();
 
-        transfer_cpi.invoke()?;
+        // Transfer the token from the maker to the escrow
+        AssetTransfer {
+            token: &self.maker_ata.to_account_info(),
+            token_owner: &self.maker.to_account_info(),
+            destination_token: &self.vault.to_account_info(),
+            destination_owner: &self.escrow.to_account_info(),
+            mint: &self.mint_a.to_account_info(),
+            metadata: &self.metadata_a,
+            master_edition: master_edition.as_ref(),
+            token_record: maker_token_record.as_ref(),
+            destination_token_record: Some(&vault_token_record),
+            authority: &self.maker.to_account_info(),
+            payer: &self.payer.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+            sysvar_instructions: &self.sysvar_instructions.to_account_info(),
+            token_program: &self.token_program.to_account_info(),
+            associated_token_program: &self.associated_token_program.to_account_info(),
+            token_metadata_program: &self.token_metadata_program.to_account_info(),
+            amount,
+        }.invoke()?;
 
-        if self.metadata_b.token_standard.as_ref().unwrap() == &TokenStandard::NonFungible || self.metadata_b.token_standard.as_ref().unwrap() == &TokenStandard::ProgrammableNonFungible {
+        if !self.mint_b_is_fungible()? {
             taker_amount = 1;
+            // A decaying price only makes sense if the taker pays with a fungible token
+            require!(dutch_auction.is_none(), EscrowError::InvalidTokenStandard);
+            // The payment of an arbitrated escrow is deposited in a vault, so it needs to be fungible too
+            require!(arbitration.is_none(), EscrowError::InvalidTokenStandard);
+        }
+
+        if let Some(dutch_auction) = &dutch_auction {
+            require!(dutch_auction.is_valid(), EscrowError::InvalidAuctionParameters);
+        }
+
+        let marketplace_fee_bps = self.marketplace.as_ref().map_or(0, |marketplace| marketplace.fee_bps);
+
+        if let Some(arbitration) = &arbitration {
+            require!(arbitration.is_valid(), EscrowError::InvalidArbitrationParameters);
+            // The arbiter and the marketplace can both take their fee out of a released payment
+            require!(arbitration.fee_bps as u32 + marketplace_fee_bps as u32 <= 10_000, EscrowError::FeesExceedPrice);
         }
 
         self.escrow.set_inner(
@@ -214,9 +193,126 @@ impl<'info> Make<'info> {
                 mint_a: self.mint_a.key(),
                 mint_b: self.mint_b.key(),
                 mint_b_amount: taker_amount,
+                dutch_auction,
+                english_auction: None,
+                delegated: false,
+                rental: None,
+                collateral: false,
+                arbitration,
+                confirmation: None,
+                proceeds_recipient: self.proceeds_recipient.as_ref().map(|recipient| recipient.key()),
+                split: false,
+                referral_bps: None,
+                frozen: false,
+                marketplace: self.marketplace.as_ref().map(|marketplace| marketplace.key()),
+                marketplace_fee_bps,
+                payer: self.payer.key(),
+            }
+        );
+
+        Ok(())
+    }
+
+    pub fn make_auction(
+        &mut self,
+        amount: u64,
+        reserve_price: u64,
+        end_time: i64,
+        extension_window: i64,
+        min_bid_increment_bps: u16,
+    ) -> Result<()> {
+
+        // Bids are locked in a vault, so they need to be paid with a fungible token
+        require!(self.mint_b_is_fungible()?, EscrowError::InvalidTokenStandard);
+        require!(end_time > Clock::get()?.unix_timestamp, EscrowError::InvalidAuctionParameters);
+        require!(
+            (0..=EnglishAuction::MAX_EXTENSION_WINDOW).contains(&extension_window) && min_bid_increment_bps <= 10_000,
+            EscrowError::InvalidAuctionParameters
+        );
+
+        // The escrow is set-up as a regular one, with the reserve price as the minimum first bid
+        self.make(amount, reserve_price, None, None)?;
+
+        self.escrow.english_auction = Some(
+            EnglishAuction {
+                end_time,
+                extension_window,
+                min_bid_increment_bps,
+                highest_bid: 0,
+                highest_bidder: None,
+                bid_vault_payer: None,
+            }
+        );
+
+        Ok(())
+    }
+
+    pub fn make_rental(
+        &mut self,
+        fee: u64,
+        duration: i64,
+    ) -> Result<()> {
+
+        // Only NFTs can be rented, the token standard decides how the renter is kept from keeping it
+        let metadata_a_token_standard = self.metadata_a.token_standard.as_ref().unwrap();
+        require!(
+            metadata_a_token_standard == &TokenStandard::NonFungible || metadata_a_token_standard == &TokenStandard::ProgrammableNonFungible,
+            EscrowError::InvalidTokenStandard
+        );
+        // The fee is paid with a fungible token
+        require!(self.mint_b_is_fungible()?, EscrowError::InvalidTokenStandard);
+        require!((1..=Rental::MAX_DURATION).contains(&duration), EscrowError::InvalidRentalParameters);
+
+        // The escrow is set-up as a regular one, with the fee as the price of every rental
+        self.make(1, fee, None, None)?;
+
+        self.escrow.rental = Some(
+            Rental {
+                duration,
+                renter: None,
+                end_time: 0,
+            }
+        );
+
+        Ok(())
+    }
+
+    pub fn make_two_sided(
+        &mut self,
+        amount: u64,
+        taker_amount: u64,
+        confirmation_window: i64,
+    ) -> Result<()> {
+
+        // The payment is deposited in a vault, so it needs to be fungible
+        require!(self.mint_b_is_fungible()?, EscrowError::InvalidTokenStandard);
+        require!((1..=Confirmation::MAX_WINDOW).contains(&confirmation_window), EscrowError::InvalidConfirmationParameters);
+
+        self.make(amount, taker_amount, None, None)?;
+
+        // The deadline starts running once the payment is deposited
+        self.escrow.confirmation = Some(
+            Confirmation {
+                window: confirmation_window,
+                buyer: None,
+                deadline: 0,
+                maker_confirmed: false,
+                buyer_confirmed: false,
             }
         );
 
         Ok(())
     }
+
+    // SOL is fungible, every other mint_b is checked through its metadata
+    pub fn mint_b_is_fungible(&self) -> Result<bool> {
+        if self.mint_b.key() == native_mint::id() {
+            return Ok(true);
+        }
+
+        let metadata_b = self.metadata_b.as_ref().ok_or(EscrowError::MetadataAccountDoesNotMatch)?;
+        let token_standard = metadata_b.token_standard.as_ref().ok_or(EscrowError::InvalidTokenStandard)?;
+
+        Ok(!is_non_fungible(token_standard))
+    }
 }
\ No newline at end of file
@@ -0,0 +1,82 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, transfer},
+    associated_token::AssociatedToken
+};
+
+use crate::{
+    state::{Bid, Config},
+    errors::EscrowError,
+};
+
+#[derive(Accounts)]
+#[instruction(amount: u64, merkle_root: [u8; 32])]
+pub struct MakeBid<'info> {
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = bidder
+    )]
+    pub bidder_ata: Box<Account<'info, TokenAccount>>,
+
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = bidder,
+        associated_token::mint = mint_b,
+        associated_token::authority = bid
+    )]
+    pub bid_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = bidder,
+        seeds = [b"bid", bidder.key().as_ref(), mint_b.key().as_ref(), merkle_root.as_ref()],
+        bump,
+        space = Bid::space()
+    )]
+    pub bid: Box<Account<'info, Bid>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> MakeBid<'info> {
+    pub fn make_bid(
+        &mut self,
+        amount: u64,
+        merkle_root: [u8; 32],
+    ) -> Result<()> {
+
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+
+        // The payment is locked in the bid vault until a seller fills the bid or the bidder cancels it
+        let cpi_accounts = Transfer {
+            from: self.bidder_ata.to_account_info(),
+            to: self.bid_vault.to_account_info(),
+            authority: self.bidder.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        transfer(cpi_ctx, amount)?;
+
+        self.bid.set_inner(
+            Bid {
+                bidder: self.bidder.key(),
+                mint_b: self.mint_b.key(),
+                mint_b_amount: amount,
+                merkle_root,
+            }
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+use mpl_bubblegum::{
+    instructions::{TransferCpi, TransferCpiAccounts, TransferInstructionArgs, DelegateCpi, DelegateCpiAccounts, DelegateInstructionArgs},
+    programs::{MPL_BUBBLEGUM_ID, SPL_ACCOUNT_COMPRESSION_ID, SPL_NOOP_ID},
+    utils::get_asset_id,
+};
+
+use crate::{
+    state::{Escrow, CompressedLeaf, Config},
+    errors::EscrowError,
+    denylist::check_compressed_not_denylisted,
+};
+
+#[derive(Accounts)]
+#[instruction(leaf: CompressedLeaf)]
+pub struct MakeCompressed<'info> {
+    pub maker: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account can receive the proceeds, only its address is stored
+    pub proceeds_recipient: Option<UncheckedAccount<'info>>,
+    /// CHECK: Bubblegum checks this against the leaf
+    pub leaf_delegate: UncheckedAccount<'info>,
+
+    /// CHECK: Bubblegum checks this against the merkle tree
+    pub tree_config: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: Bubblegum checks this against the leaf
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"escrow", maker.key().as_ref(), get_asset_id(&merkle_tree.key(), leaf.nonce).as_ref(), mint_b.key().as_ref()],
+        bump,
+        space = Escrow::space()
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    /// CHECK: checked against the asset id later
+    pub asset_denylist: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection of the leaf later
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+
+    #[account(address = MPL_BUBBLEGUM_ID)]
+    /// CHECK: we're checking the address
+    pub bubblegum_program: UncheckedAccount<'info>,
+    #[account(address = SPL_NOOP_ID)]
+    /// CHECK: we're checking the address
+    pub log_wrapper: UncheckedAccount<'info>,
+    #[account(address = SPL_ACCOUNT_COMPRESSION_ID)]
+    /// CHECK: we're checking the address
+    pub compression_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> MakeCompressed<'info> {
+    // The metadata are the borsh-serialized MetadataArgs of the leaf, the remaining accounts are its proof
+    pub fn make_compressed(
+        &mut self,
+        leaf: CompressedLeaf,
+        metadata: &[u8],
+        taker_amount: u64,
+        delegated: bool,
+        proof: &[AccountInfo<'info>],
+    ) -> Result<()> {
+
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+        let metadata = leaf.metadata(metadata)?;
+        let collection_denylist = self.collection_denylist.as_ref().map(|denylist| denylist.to_account_info());
+        check_compressed_not_denylisted(&get_asset_id(&self.merkle_tree.key(), leaf.nonce), &metadata, &self.asset_denylist.to_account_info(), collection_denylist.as_ref())?;
+
+        let proof = proof.iter().map(|node| (node, false, false)).collect::<Vec<_>>();
+
+        let program = &self.bubblegum_program.to_account_info();
+        let tree_config = &self.tree_config.to_account_info();
+        let leaf_owner = &self.maker.to_account_info();
+        let leaf_delegate = &self.leaf_delegate.to_account_info();
+        let escrow = &self.escrow.to_account_info();
+        let merkle_tree = &self.merkle_tree.to_account_info();
+        let log_wrapper = &self.log_wrapper.to_account_info();
+        let compression_program = &self.compression_program.to_account_info();
+        let system_program = &self.system_program.to_account_info();
+
+        // Delegated listings keep the leaf with the maker and only make the escrow its delegate,
+        // otherwise the leaf is transferred to the escrow
+        if delegated {
+            let delegate_cpi = DelegateCpi::new(
+                program,
+                DelegateCpiAccounts {
+                    tree_config,
+                    leaf_owner,
+                    previous_leaf_delegate: leaf_delegate,
+                    new_leaf_delegate: escrow,
+                    merkle_tree,
+                    log_wrapper,
+                    compression_program,
+                    system_program,
+                },
+                DelegateInstructionArgs {
+                    root: leaf.root,
+                    data_hash: leaf.data_hash,
+                    creator_hash: leaf.creator_hash,
+                    nonce: leaf.nonce,
+                    index: leaf.index,
+                },
+            );
+
+            delegate_cpi.invoke_with_remaining_accounts(&proof)?;
+        } else {
+            let transfer_cpi = TransferCpi::new(
+                program,
+                TransferCpiAccounts {
+                    tree_config,
+                    leaf_owner: (leaf_owner, true),
+                    leaf_delegate: (leaf_delegate, false),
+                    new_leaf_owner: escrow,
+                    merkle_tree,
+                    log_wrapper,
+                    compression_program,
+                    system_program,
+                },
+                TransferInstructionArgs {
+                    root: leaf.root,
+                    data_hash: leaf.data_hash,
+                    creator_hash: leaf.creator_hash,
+                    nonce: leaf.nonce,
+                    index: leaf.index,
+                },
+            );
+
+            transfer_cpi.invoke_with_remaining_accounts(&proof)?;
+        }
+
+        self.escrow.set_inner(
+            Escrow {
+                mint_a: get_asset_id(&self.merkle_tree.key(), leaf.nonce),
+                mint_b: self.mint_b.key(),
+                mint_b_amount: taker_amount,
+                dutch_auction: None,
+                english_auction: None,
+                delegated,
+                rental: None,
+                collateral: false,
+                arbitration: None,
+                confirmation: None,
+                proceeds_recipient: self.proceeds_recipient.as_ref().map(|recipient| recipient.key()),
+                split: false,
+                referral_bps: None,
+                frozen: false,
+                marketplace: None,
+                marketplace_fee_bps: 0,
+                payer: self.payer.key(),
+            }
+        );
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,102 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+
+use crate::{
+    state::{Escrow, Config},
+    errors::EscrowError,
+    denylist::check_asset_not_denylisted,
+    mpl_core::{MPL_CORE_ID, TransferV1Cpi, load_asset},
+};
+
+#[derive(Accounts)]
+pub struct MakeCore<'info> {
+    pub maker: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account can receive the proceeds, only its address is stored
+    pub proceeds_recipient: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    /// CHECK: we're checking this against the Core program later
+    pub asset: UncheckedAccount<'info>,
+    /// CHECK: we're checking this against the asset later
+    pub collection: Option<UncheckedAccount<'info>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"escrow", maker.key().as_ref(), asset.key().as_ref(), mint_b.key().as_ref()],
+        bump,
+        space = Escrow::space()
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    /// CHECK: checked against the asset later
+    pub asset_denylist: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection of the asset later
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+
+    #[account(address = MPL_CORE_ID)]
+    /// CHECK: we're checking the address
+    pub core_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> MakeCore<'info> {
+    pub fn make_core(
+        &mut self,
+        taker_amount: u64,
+    ) -> Result<()> {
+
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+
+        let collection = self.collection.as_ref().map(|collection| collection.to_account_info());
+        let asset = load_asset(&self.asset.to_account_info(), collection.as_ref())?;
+        let collection_denylist = self.collection_denylist.as_ref().map(|denylist| denylist.to_account_info());
+        check_asset_not_denylisted(&self.asset.key(), asset.collection.as_ref(), &self.asset_denylist.to_account_info(), collection_denylist.as_ref())?;
+
+        require_keys_eq!(asset.owner, self.maker.key(), EscrowError::InvalidCoreAsset);
+        // Core would reject the transfer anyway, this just fails early with a clearer error
+        require!(!asset.plugins.frozen, EscrowError::AssetFrozen);
+
+        // Transfer the asset from the maker to the escrow
+        TransferV1Cpi {
+            core_program: &self.core_program.to_account_info(),
+            asset: &self.asset.to_account_info(),
+            collection: collection.as_ref(),
+            payer: &self.payer.to_account_info(),
+            authority: &self.maker.to_account_info(),
+            new_owner: &self.escrow.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+        }.invoke()?;
+
+        self.escrow.set_inner(
+            Escrow {
+                mint_a: self.asset.key(),
+                mint_b: self.mint_b.key(),
+                mint_b_amount: taker_amount,
+                dutch_auction: None,
+                english_auction: None,
+                delegated: false,
+                rental: None,
+                collateral: false,
+                arbitration: None,
+                confirmation: None,
+                proceeds_recipient: self.proceeds_recipient.as_ref().map(|recipient| recipient.key()),
+                split: false,
+                referral_bps: None,
+                frozen: false,
+                marketplace: None,
+                marketplace_fee_bps: 0,
+                payer: self.payer.key(),
+            }
+        );
+
+        Ok(())
+    }
+}
\ No newline at end of file
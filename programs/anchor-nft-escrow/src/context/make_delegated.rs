@@ -0,0 +1,260 @@
+use solana_program::*;
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Approve, approve},
+    metadata::{MetadataAccount, MasterEditionAccount, Metadata, TokenRecordAccount,
+        mpl_token_metadata::{
+            instructions::{DelegateCpi, DelegateCpiAccounts, DelegateInstructionArgs, FreezeDelegatedAccountCpi, FreezeDelegatedAccountCpiAccounts},
+            types::{TokenStandard, DelegateArgs}}
+        },
+};
+
+use crate::{
+    state::{Escrow, Config},
+    errors::EscrowError,
+    denylist::check_not_denylisted,
+};
+
+#[derive(Accounts)]
+pub struct MakeDelegated<'info> {
+    pub maker: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account can receive the proceeds, only its address is stored
+    pub proceeds_recipient: Option<UncheckedAccount<'info>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker
+    )]
+    pub maker_ata: Box<Account<'info, TokenAccount>>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_a: Box<Account<'info, MetadataAccount>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"edition",
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub master_edition_a: Box<Account<'info, MasterEditionAccount>>,
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"token_record",
+            maker_ata.key().as_ref(),
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub maker_token_record_a: Option<Box<Account<'info, TokenRecordAccount>>>,
+
+    pub mint_b: Box<Account<'info, Mint>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_b.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_b: Box<Account<'info, MetadataAccount>>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump,
+        space = Escrow::space()
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    /// CHECK: checked against mint_a later
+    pub mint_denylist: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection of mint_a later
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+
+    #[account(address = sysvar::instructions::id())]
+    /// CHECK: we don't need to check this
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> MakeDelegated<'info> {
+    pub fn make_delegated(
+        &mut self,
+        taker_amount: u64,
+        bumps: MakeDelegatedBumps,
+    ) -> Result<()> {
+
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+        let collection_denylist = self.collection_denylist.as_ref().map(|denylist| denylist.to_account_info());
+        check_not_denylisted(&self.mint_a.key(), &self.metadata_a, &self.mint_denylist.to_account_info(), collection_denylist.as_ref())?;
+
+        let metadata_a_token_standard = self.metadata_a.token_standard.as_ref().unwrap();
+        let mut taker_amount = taker_amount;
+
+        // The token stays in the maker's wallet, the escrow only gets the authority to move it
+        match metadata_a_token_standard {
+            TokenStandard::ProgrammableNonFungible => self.delegate_programmable()?,
+            TokenStandard::NonFungible => self.approve_and_freeze(bumps)?,
+            _ => return err!(EscrowError::InvalidTokenStandard),
+        }
+
+        if self.metadata_b.token_standard.as_ref().unwrap() == &TokenStandard::NonFungible || self.metadata_b.token_standard.as_ref().unwrap() == &TokenStandard::ProgrammableNonFungible {
+            taker_amount = 1;
+        }
+
+        self.escrow.set_inner(
+            Escrow {
+                mint_a: self.mint_a.key(),
+                mint_b: self.mint_b.key(),
+                mint_b_amount: taker_amount,
+                dutch_auction: None,
+                english_auction: None,
+                delegated: true,
+                rental: None,
+                collateral: false,
+                arbitration: None,
+                confirmation: None,
+                proceeds_recipient: self.proceeds_recipient.as_ref().map(|recipient| recipient.key()),
+                split: false,
+                referral_bps: None,
+                frozen: false,
+                marketplace: None,
+                marketplace_fee_bps: 0,
+                payer: self.payer.key(),
+            }
+        );
+
+        Ok(())
+    }
+
+    // The escrow becomes the Sale delegate of the pNFT, which puts the token record in the Listed state
+    // so that only the escrow can move it until the delegate is revoked
+    fn delegate_programmable(
+        &self,
+    ) -> Result<()> {
+
+        let program = &self.token_metadata_program.to_account_info();
+        let delegate = &self.escrow.to_account_info();
+        let metadata = &self.metadata_a.to_account_info();
+        let master_edition = &self.master_edition_a.to_account_info();
+        let token_record = &self.maker_token_record_a.as_ref().ok_or(EscrowError::TokenRecordAccountDoesNotMatch)?.to_account_info();
+        let mint = &self.mint_a.to_account_info();
+        let token = &self.maker_ata.to_account_info();
+        let authority = &self.maker.to_account_info();
+        let payer = &self.payer.to_account_info();
+        let system_program = &self.system_program.to_account_info();
+        let sysvar_instructions = &self.sysvar_instructions.to_account_info();
+        let spl_token_program = &self.token_program.to_account_info();
+
+        let delegate_cpi = DelegateCpi::new(
+            program,
+            DelegateCpiAccounts {
+                delegate_record: None,
+                delegate,
+                metadata,
+                master_edition: Some(master_edition),
+                token_record: Some(token_record),
+                mint,
+                token: Some(token),
+                authority,
+                payer,
+                system_program,
+                sysvar_instructions,
+                spl_token_program: Some(spl_token_program),
+                //TODO After
+                authorization_rules_program: None,
+                authorization_rules: None,
+            },
+            DelegateInstructionArgs {
+                delegate_args: DelegateArgs::SaleV1 {
+                    amount: 1,
+                    authorization_data: None,
+                },
+            },
+        );
+
+        delegate_cpi.invoke()?;
+
+        Ok(())
+    }
+
+    // The escrow becomes the SPL delegate of the NFT and freezes it through token-metadata,
+    // so the maker can't move it until the escrow thaws it
+    fn approve_and_freeze(
+        &self,
+        bumps: MakeDelegatedBumps,
+    ) -> Result<()> {
+
+        let cpi_accounts = Approve {
+            to: self.maker_ata.to_account_info(),
+            delegate: self.escrow.to_account_info(),
+            authority: self.maker.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        approve(cpi_ctx, 1)?;
+
+        let mint_a_key = self.mint_a.key();
+        let mint_b_key = self.mint_b.key();
+        let maker_key = self.maker.key();
+
+        let seeds = &[
+            "escrow".as_bytes(),
+            maker_key.as_ref(),
+            mint_a_key.as_ref(),
+            mint_b_key.as_ref(),
+            &[bumps.escrow]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let program = &self.token_metadata_program.to_account_info();
+        let delegate = &self.escrow.to_account_info();
+        let token_account = &self.maker_ata.to_account_info();
+        let edition = &self.master_edition_a.to_account_info();
+        let mint = &self.mint_a.to_account_info();
+        let token_program = &self.token_program.to_account_info();
+
+        let freeze_cpi = FreezeDelegatedAccountCpi::new(
+            program,
+            FreezeDelegatedAccountCpiAccounts {
+                delegate,
+                token_account,
+                edition,
+                mint,
+                token_program,
+            },
+        );
+
+        freeze_cpi.invoke_signed(signer_seeds)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    state::Loan,
+    errors::EscrowError,
+};
+// The glob brings in the client modules Anchor generates for Make, which the nested accounts need
+use crate::context::make::*;
+
+#[derive(Accounts)]
+pub struct MakeLoan<'info> {
+    // The collateral is vaulted through the regular make flow
+    pub make: Make<'info>,
+
+    #[account(
+        init,
+        payer = make.payer,
+        seeds = [b"loan", make.escrow.key().as_ref()],
+        bump,
+        space = Loan::space()
+    )]
+    pub loan: Box<Account<'info, Loan>>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> MakeLoan<'info> {
+    pub fn make_loan(
+        &mut self,
+        amount: u64,
+        principal: u64,
+        apr_bps: u16,
+        duration: i64,
+    ) -> Result<()> {
+
+        require!(principal > 0 && (1..=Loan::MAX_DURATION).contains(&duration), EscrowError::InvalidLoanParameters);
+        // Funding a loan isn't a sale, there is no price for a marketplace to take its fee on
+        require!(self.make.marketplace.is_none(), EscrowError::InvalidMarketplace);
+
+        self.make.make(amount, principal, None, None)?;
+        self.make.escrow.collateral = true;
+
+        self.loan.set_inner(
+            Loan {
+                escrow: self.make.escrow.key(),
+                borrower: self.make.maker.key(),
+                lender: None,
+                principal,
+                apr_bps,
+                duration,
+                start_time: 0,
+                frozen_at: None,
+                frozen_for: 0,
+            }
+        );
+
+        // A loan whose repayment doesn't fit in a u64 could never be repaid
+        self.loan.repayment()?;
+
+        Ok(())
+    }
+}
\ No newline at end of file
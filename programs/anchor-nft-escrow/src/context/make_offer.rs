@@ -0,0 +1,81 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, transfer},
+    associated_token::AssociatedToken
+};
+
+use crate::{
+    state::{Offer, Config},
+    errors::EscrowError,
+};
+
+#[derive(Accounts)]
+pub struct MakeOffer<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = buyer
+    )]
+    pub buyer_ata: Box<Account<'info, TokenAccount>>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = mint_b,
+        associated_token::authority = offer
+    )]
+    pub offer_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = buyer,
+        seeds = [b"offer", buyer.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump,
+        space = Offer::space()
+    )]
+    pub offer: Box<Account<'info, Offer>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> MakeOffer<'info> {
+    pub fn make_offer(
+        &mut self,
+        amount: u64,
+    ) -> Result<()> {
+
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+
+        // The payment is locked in the offer vault until the owner of the NFT accepts the offer or the buyer cancels it
+        let cpi_accounts = Transfer {
+            from: self.buyer_ata.to_account_info(),
+            to: self.offer_vault.to_account_info(),
+            authority: self.buyer.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        transfer(cpi_ctx, amount)?;
+
+        self.offer.set_inner(
+            Offer {
+                buyer: self.buyer.key(),
+                mint_a: self.mint_a.key(),
+                mint_b: self.mint_b.key(),
+                mint_b_amount: amount,
+            }
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    state::{Payees, Payee},
+    errors::EscrowError,
+};
+// The glob brings in the client modules Anchor generates for Make, which the nested accounts need
+use crate::context::make::*;
+
+#[derive(Accounts)]
+pub struct MakeSplit<'info> {
+    // The asset is vaulted through the regular make flow
+    pub make: Make<'info>,
+
+    #[account(
+        init,
+        payer = make.payer,
+        seeds = [b"payees", make.escrow.key().as_ref()],
+        bump,
+        space = Payees::space()
+    )]
+    pub payees: Box<Account<'info, Payees>>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> MakeSplit<'info> {
+    pub fn make_split(
+        &mut self,
+        amount: u64,
+        taker_amount: u64,
+        payees: Vec<Payee>,
+    ) -> Result<()> {
+
+        require!(Payees::is_valid(&payees), EscrowError::InvalidPayees);
+        // The payees replace the proceeds recipient
+        require!(self.make.proceeds_recipient.is_none(), EscrowError::InvalidPayees);
+
+        // The payment is split by amount, so it needs to be paid with a fungible token
+        require!(self.make.mint_b_is_fungible()?, EscrowError::InvalidTokenStandard);
+
+        self.make.make(amount, taker_amount, None, None)?;
+        self.make.escrow.split = true;
+
+        self.payees.set_inner(
+            Payees {
+                escrow: self.make.escrow.key(),
+                payees,
+            }
+        );
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -1,7 +1,89 @@
 pub mod make;
 pub mod take;
+pub mod take_atomic;
 pub mod close;
+pub mod make_delegated;
+pub mod close_delegated;
+pub mod make_bid;
+pub mod take_bid;
+pub mod cancel_bid;
+pub mod make_offer;
+pub mod accept_offer;
+pub mod cancel_offer;
+pub mod place_bid;
+pub mod settle;
+pub mod make_core;
+pub mod take_core;
+pub mod close_core;
+pub mod make_compressed;
+pub mod take_compressed;
+pub mod close_compressed;
+pub mod rent_asset;
+pub mod reclaim;
+pub mod make_loan;
+pub mod fund_loan;
+pub mod close_loan;
+pub mod foreclose;
+pub mod deposit_payment;
+pub mod dispute;
+pub mod resolve;
+pub mod confirm;
+pub mod fill_order;
+pub mod cancel_order;
+pub mod increment_nonce;
+pub mod make_split;
+pub mod take_split;
+pub mod set_referral_fee;
+pub mod set_default_referral_fee;
+pub mod initialize_config;
+pub mod set_paused;
+pub mod set_escrow_frozen;
+pub mod add_to_denylist;
+pub mod remove_from_denylist;
+pub mod create_marketplace;
+pub mod update_marketplace;
 
 pub use make::*;
 pub use take::*;
-pub use close::*;
\ No newline at end of file
+pub use take_atomic::*;
+pub use close::*;
+pub use make_delegated::*;
+pub use close_delegated::*;
+pub use make_bid::*;
+pub use take_bid::*;
+pub use cancel_bid::*;
+pub use make_offer::*;
+pub use accept_offer::*;
+pub use cancel_offer::*;
+pub use place_bid::*;
+pub use settle::*;
+pub use make_core::*;
+pub use take_core::*;
+pub use close_core::*;
+pub use make_compressed::*;
+pub use take_compressed::*;
+pub use close_compressed::*;
+pub use rent_asset::*;
+pub use reclaim::*;
+pub use make_loan::*;
+pub use fund_loan::*;
+pub use close_loan::*;
+pub use foreclose::*;
+pub use deposit_payment::*;
+pub use dispute::*;
+pub use resolve::*;
+pub use confirm::*;
+pub use fill_order::*;
+pub use cancel_order::*;
+pub use increment_nonce::*;
+pub use make_split::*;
+pub use take_split::*;
+pub use set_referral_fee::*;
+pub use set_default_referral_fee::*;
+pub use initialize_config::*;
+pub use set_paused::*;
+pub use set_escrow_frozen::*;
+pub use add_to_denylist::*;
+pub use remove_from_denylist::*;
+pub use create_marketplace::*;
+pub use update_marketplace::*;
\ No newline at end of file
@@ -0,0 +1,143 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, transfer},
+    metadata::{MetadataAccount, Metadata},
+    associated_token::{AssociatedToken, get_associated_token_address}
+};
+
+use crate::{
+    state::{Escrow, Config, Marketplace},
+    errors::EscrowError,
+    marketplace::{check_marketplace, marketplace_seed},
+};
+
+#[derive(Accounts)]
+pub struct PlaceBid<'info> {
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = bidder
+    )]
+    pub bidder_ata: Box<Account<'info, TokenAccount>>,
+    /// CHECK: we're checking the seeds
+    pub maker: UncheckedAccount<'info>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_a: Box<Account<'info, MetadataAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = bidder,
+        associated_token::mint = mint_b,
+        associated_token::authority = escrow
+    )]
+    pub bid_vault: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub previous_bidder_ata: Option<Box<Account<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref(), marketplace_seed(&escrow.marketplace)],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    pub marketplace: Option<Box<Account<'info, Marketplace>>>,
+
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> PlaceBid<'info> {
+    pub fn place_bid(
+        &mut self,
+        amount: u64,
+        bumps: PlaceBidBumps,
+    ) -> Result<()> {
+
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+        check_marketplace(&self.escrow, self.marketplace.as_deref(), &self.metadata_a)?;
+
+        let mut english_auction = self.escrow.english_auction.ok_or(EscrowError::NotAnAuction)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(now < english_auction.end_time, EscrowError::AuctionEnded);
+
+        // The first bid needs to meet the reserve price, every other one needs to beat the highest bid by the minimum increment
+        match english_auction.highest_bidder {
+            Some(_) => require_gte!(amount, english_auction.minimum_bid(), EscrowError::BidBelowMinimumIncrement),
+            None => require_gte!(amount, self.escrow.mint_b_amount, EscrowError::BidTooLow),
+        }
+
+        // Lock the new bid in the vault
+        let cpi_accounts = Transfer {
+            from: self.bidder_ata.to_account_info(),
+            to: self.bid_vault.to_account_info(),
+            authority: self.bidder.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        transfer(cpi_ctx, amount)?;
+
+        // Refund the previous highest bidder
+        if let Some(previous_bidder) = english_auction.highest_bidder {
+            let previous_bidder_ata = self.previous_bidder_ata.as_ref().ok_or(EscrowError::InvalidBidder)?;
+            require_keys_eq!(
+                previous_bidder_ata.key(),
+                get_associated_token_address(&previous_bidder, &self.mint_b.key()),
+                EscrowError::InvalidBidder
+            );
+
+            let mint_a_key = self.mint_a.key();
+            let mint_b_key = self.mint_b.key();
+            let maker_key = self.maker.key();
+            let marketplace_key = self.escrow.marketplace;
+
+            let seeds = &[
+                "escrow".as_bytes(),
+                maker_key.as_ref(),
+                mint_a_key.as_ref(),
+                mint_b_key.as_ref(),
+                marketplace_seed(&marketplace_key),
+                &[bumps.escrow]
+            ];
+            let signer_seeds = &[&seeds[..]];
+
+            let cpi_accounts = Transfer {
+                from: self.bid_vault.to_account_info(),
+                to: previous_bidder_ata.to_account_info(),
+                authority: self.escrow.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+            transfer(cpi_ctx, english_auction.highest_bid)?;
+        }
+
+        english_auction.bid_vault_payer.get_or_insert(self.bidder.key());
+        english_auction.highest_bid = amount;
+        english_auction.highest_bidder = Some(self.bidder.key());
+        english_auction.extend(now)?;
+        self.escrow.english_auction = Some(english_auction);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,262 @@
+use solana_program::*;
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, transfer},
+    metadata::{MetadataAccount, MasterEditionAccount, Metadata, TokenRecordAccount,
+        mpl_token_metadata::{
+            instructions::{UnlockCpi, UnlockCpiAccounts, UnlockInstructionArgs, ThawDelegatedAccountCpi, ThawDelegatedAccountCpiAccounts},
+            types::{TokenStandard, UnlockArgs}}
+        },
+    associated_token::AssociatedToken
+};
+
+use crate::{
+    state::Escrow,
+    errors::EscrowError,
+    marketplace::marketplace_seed,
+    transfer::AssetTransfer,
+};
+
+#[derive(Accounts)]
+pub struct Reclaim<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: we're checking the seeds
+    pub maker: UncheckedAccount<'info>,
+    /// CHECK: we're checking this against the renter of the escrow
+    pub renter: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = renter
+    )]
+    pub renter_ata_a: Box<Account<'info, TokenAccount>>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_a: Box<Account<'info, MetadataAccount>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"edition",
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub master_edition_a: Box<Account<'info, MasterEditionAccount>>,
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"token_record",
+            renter_ata_a.key().as_ref(),
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub renter_token_record_a: Option<Box<Account<'info, TokenRecordAccount>>>,
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"token_record",
+            vault.key().as_ref(),
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    /// CHECK: we're checking the seeds
+    pub vault_token_record_a: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref(), marketplace_seed(&escrow.marketplace)],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+
+    #[account(address = sysvar::instructions::id())]
+    /// CHECK: we don't need to check this
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> Reclaim<'info> {
+    // Permissionless, so the maker or a crank can bring the token back once the rental is over
+    pub fn reclaim(
+        &mut self,
+        bumps: ReclaimBumps,
+    ) -> Result<()> {
+
+        let mut rental = self.escrow.rental.ok_or(EscrowError::NotARental)?;
+        let renter = rental.renter.ok_or(EscrowError::AssetNotRented)?;
+        require_keys_eq!(self.renter.key(), renter, EscrowError::InvalidRenter);
+        require!(Clock::get()?.unix_timestamp >= rental.end_time, EscrowError::RentalNotEnded);
+
+        let mint_a_key = self.mint_a.key();
+        let mint_b_key = self.mint_b.key();
+        let maker_key = self.maker.key();
+        let marketplace_key = self.escrow.marketplace;
+
+        let seeds = &[
+            "escrow".as_bytes(),
+            maker_key.as_ref(),
+            mint_a_key.as_ref(),
+            mint_b_key.as_ref(),
+            marketplace_seed(&marketplace_key),
+            &[bumps.escrow]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        match self.metadata_a.token_standard.as_ref().unwrap() {
+            TokenStandard::ProgrammableNonFungible => self.unlock_and_transfer(signer_seeds)?,
+            TokenStandard::NonFungible => self.thaw_and_transfer(signer_seeds)?,
+            _ => return err!(EscrowError::InvalidTokenStandard),
+        }
+
+        // The token is back in the vault and can be rented again
+        rental.renter = None;
+        rental.end_time = 0;
+        self.escrow.rental = Some(rental);
+
+        Ok(())
+    }
+
+    // The escrow unlocks the pNFT and uses its LockedTransfer delegate to send it back to the vault
+    fn unlock_and_transfer(
+        &self,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+
+        let program = &self.token_metadata_program.to_account_info();
+        let escrow = &self.escrow.to_account_info();
+        let renter = &self.renter.to_account_info();
+        let token = &self.renter_ata_a.to_account_info();
+        let mint = &self.mint_a.to_account_info();
+        let metadata = &self.metadata_a.to_account_info();
+        let edition = &self.master_edition_a.to_account_info();
+        let token_record = &self.renter_token_record_a.as_ref().ok_or(EscrowError::TokenRecordAccountDoesNotMatch)?.to_account_info();
+        let vault = &self.vault.to_account_info();
+        let vault_token_record = &self.vault_token_record_a.to_account_info();
+        let payer = &self.payer.to_account_info();
+        let system_program = &self.system_program.to_account_info();
+        let sysvar_instructions = &self.sysvar_instructions.to_account_info();
+        let spl_token_program = &self.token_program.to_account_info();
+        let spl_ata_program = &self.associated_token_program.to_account_info();
+
+        let unlock_cpi = UnlockCpi::new(
+            program,
+            UnlockCpiAccounts {
+                authority: escrow,
+                token_owner: Some(renter),
+                token,
+                mint,
+                metadata,
+                edition: Some(edition),
+                token_record: Some(token_record),
+                payer,
+                system_program,
+                sysvar_instructions,
+                spl_token_program: Some(spl_token_program),
+                //TODO After
+                authorization_rules_program: None,
+                authorization_rules: None,
+            },
+            UnlockInstructionArgs {
+                unlock_args: UnlockArgs::V1 {
+                    authorization_data: None,
+                },
+            },
+        );
+
+        unlock_cpi.invoke_signed(signer_seeds)?;
+
+        AssetTransfer {
+            token,
+            token_owner: renter,
+            destination_token: vault,
+            destination_owner: escrow,
+            mint,
+            metadata: &self.metadata_a,
+            master_edition: Some(edition),
+            token_record: Some(token_record),
+            destination_token_record: Some(vault_token_record),
+            authority: escrow,
+            payer,
+            system_program,
+            sysvar_instructions,
+            token_program: spl_token_program,
+            associated_token_program: spl_ata_program,
+            token_metadata_program: program,
+            amount: 1,
+        }.invoke_signed(signer_seeds)?;
+
+        Ok(())
+    }
+
+    // The escrow thaws the NFT and moves it back to the vault as the SPL delegate
+    fn thaw_and_transfer(
+        &self,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+
+        let program = &self.token_metadata_program.to_account_info();
+        let delegate = &self.escrow.to_account_info();
+        let token_account = &self.renter_ata_a.to_account_info();
+        let edition = &self.master_edition_a.to_account_info();
+        let mint = &self.mint_a.to_account_info();
+        let token_program = &self.token_program.to_account_info();
+
+        let thaw_cpi = ThawDelegatedAccountCpi::new(
+            program,
+            ThawDelegatedAccountCpiAccounts {
+                delegate,
+                token_account,
+                edition,
+                mint,
+                token_program,
+            },
+        );
+
+        thaw_cpi.invoke_signed(signer_seeds)?;
+
+        let cpi_accounts = Transfer {
+            from: self.renter_ata_a.to_account_info(),
+            to: self.vault.to_account_info(),
+            authority: self.escrow.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+        transfer(cpi_ctx, 1)?;
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    state::{Config, DenylistEntry},
+    errors::EscrowError,
+};
+
+#[derive(Accounts)]
+pub struct RemoveFromDenylist<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        has_one = admin @ EscrowError::InvalidAdmin,
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"denylist", denylist_entry.key.as_ref()],
+        bump,
+    )]
+    pub denylist_entry: Box<Account<'info, DenylistEntry>>,
+}
+
+impl<'info> RemoveFromDenylist<'info> {
+    // Closing the entry is enough, the asset can be listed and bought again
+    pub fn remove_from_denylist(
+        &mut self,
+    ) -> Result<()> {
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,323 @@
+use solana_program::*;
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, Approve, transfer, approve},
+    metadata::{MetadataAccount, MasterEditionAccount, Metadata, TokenRecordAccount,
+        mpl_token_metadata::{
+            instructions::{DelegateCpi, DelegateCpiAccounts, DelegateInstructionArgs, LockCpi, LockCpiAccounts, LockInstructionArgs, FreezeDelegatedAccountCpi, FreezeDelegatedAccountCpiAccounts},
+            types::{TokenStandard, DelegateArgs, LockArgs}}
+        },
+    associated_token::AssociatedToken
+};
+
+use crate::{
+    state::{Escrow, Config, Marketplace},
+    errors::EscrowError,
+    denylist::check_not_denylisted,
+    marketplace::{check_marketplace, marketplace_seed},
+    transfer::{VaultTransfer, pay_marketplace_fee},
+};
+
+#[derive(Accounts)]
+pub struct RentAsset<'info> {
+    #[account(mut)]
+    pub renter: Signer<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = renter
+    )]
+    pub renter_ata_b: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = renter,
+        associated_token::mint = mint_a,
+        associated_token::authority = renter
+    )]
+    pub renter_ata_a: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    /// CHECK: we're checking the seeds
+    pub maker: UncheckedAccount<'info>,
+    #[account(address = escrow.payee(maker.key()) @ EscrowError::InvalidProceedsRecipient)]
+    /// CHECK: the maker, or the proceeds recipient set on the escrow
+    pub proceeds_recipient: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = renter,
+        associated_token::mint = mint_b,
+        associated_token::authority = proceeds_recipient
+    )]
+    pub maker_ata_b: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub treasury_ata_b: Option<Box<Account<'info, TokenAccount>>>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_a: Box<Account<'info, MetadataAccount>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"edition",
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub master_edition_a: Box<Account<'info, MasterEditionAccount>>,
+    #[account(mut)]
+    pub vault_token_record_a: Option<Box<Account<'info, TokenRecordAccount>>>,
+    #[account(mut)]
+    /// CHECK: we're checking this later
+    pub renter_token_record_a: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref(), marketplace_seed(&escrow.marketplace)],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    /// CHECK: checked against mint_a later
+    pub mint_denylist: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection of mint_a later
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+    pub marketplace: Option<Box<Account<'info, Marketplace>>>,
+
+    #[account(address = sysvar::instructions::id())]
+    /// CHECK: we don't need to check this
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> RentAsset<'info> {
+    pub fn rent_asset(
+        &mut self,
+        bumps: RentAssetBumps,
+    ) -> Result<()> {
+
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+        let collection_denylist = self.collection_denylist.as_ref().map(|denylist| denylist.to_account_info());
+        check_not_denylisted(&self.mint_a.key(), &self.metadata_a, &self.mint_denylist.to_account_info(), collection_denylist.as_ref())?;
+        check_marketplace(&self.escrow, self.marketplace.as_deref(), &self.metadata_a)?;
+
+        let mut rental = self.escrow.rental.ok_or(EscrowError::NotARental)?;
+        require!(rental.renter.is_none(), EscrowError::AssetRented);
+
+        let marketplace_fee = pay_marketplace_fee(
+            &self.escrow,
+            self.marketplace.as_deref(),
+            self.escrow.mint_b_amount,
+            self.treasury_ata_b.as_deref(),
+            &self.renter_ata_b.to_account_info(),
+            &self.renter.to_account_info(),
+            &self.token_program.to_account_info(),
+        )?;
+
+        // Pay the rest of the fee to the maker, or to the proceeds recipient
+        let cpi_accounts = Transfer {
+            from: self.renter_ata_b.to_account_info(),
+            to: self.maker_ata_b.to_account_info(),
+            authority: self.renter.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        transfer(cpi_ctx, self.escrow.mint_b_amount - marketplace_fee)?;
+
+        let mint_a_key = self.mint_a.key();
+        let mint_b_key = self.mint_b.key();
+        let maker_key = self.maker.key();
+        let marketplace_key = self.escrow.marketplace;
+
+        let seeds = &[
+            "escrow".as_bytes(),
+            maker_key.as_ref(),
+            mint_a_key.as_ref(),
+            mint_b_key.as_ref(),
+            marketplace_seed(&marketplace_key),
+            &[bumps.escrow]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        // Hand the token over to the renter
+        let master_edition = self.master_edition_a.to_account_info();
+        let vault_token_record = self.vault_token_record_a.as_ref().map(|token_record| token_record.to_account_info());
+
+        VaultTransfer {
+            escrow: &self.escrow.to_account_info(),
+            owner: &self.escrow.to_account_info(),
+            vault: &self.vault,
+            destination_ata: &self.renter_ata_a.to_account_info(),
+            destination_owner: &self.renter.to_account_info(),
+            mint: &self.mint_a.to_account_info(),
+            metadata: &self.metadata_a,
+            master_edition: Some(&master_edition),
+            vault_token_record: vault_token_record.as_ref(),
+            destination_token_record: &self.renter_token_record_a.to_account_info(),
+            payer: &self.renter.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+            sysvar_instructions: &self.sysvar_instructions.to_account_info(),
+            token_program: &self.token_program.to_account_info(),
+            associated_token_program: &self.associated_token_program.to_account_info(),
+            token_metadata_program: &self.token_metadata_program.to_account_info(),
+        }.invoke_signed(signer_seeds)?;
+
+        // The renter can use the token but the escrow keeps the authority to reclaim it
+        match self.metadata_a.token_standard.as_ref().unwrap() {
+            TokenStandard::ProgrammableNonFungible => self.delegate_and_lock(signer_seeds)?,
+            TokenStandard::NonFungible => self.approve_and_freeze(signer_seeds)?,
+            _ => return err!(EscrowError::InvalidTokenStandard),
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        rental.renter = Some(self.renter.key());
+        rental.end_time = now.checked_add(rental.duration).ok_or(EscrowError::Overflow)?;
+        self.escrow.rental = Some(rental);
+
+        Ok(())
+    }
+
+    // The escrow becomes the LockedTransfer delegate of the pNFT, which can only send it back to the escrow,
+    // and locks it so the renter can't revoke the delegate
+    fn delegate_and_lock(
+        &self,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+
+        let program = &self.token_metadata_program.to_account_info();
+        let delegate = &self.escrow.to_account_info();
+        let metadata = &self.metadata_a.to_account_info();
+        let master_edition = &self.master_edition_a.to_account_info();
+        let token_record = &self.renter_token_record_a.to_account_info();
+        let mint = &self.mint_a.to_account_info();
+        let token = &self.renter_ata_a.to_account_info();
+        let renter = &self.renter.to_account_info();
+        let system_program = &self.system_program.to_account_info();
+        let sysvar_instructions = &self.sysvar_instructions.to_account_info();
+        let spl_token_program = &self.token_program.to_account_info();
+
+        let delegate_cpi = DelegateCpi::new(
+            program,
+            DelegateCpiAccounts {
+                delegate_record: None,
+                delegate,
+                metadata,
+                master_edition: Some(master_edition),
+                token_record: Some(token_record),
+                mint,
+                token: Some(token),
+                authority: renter,
+                payer: renter,
+                system_program,
+                sysvar_instructions,
+                spl_token_program: Some(spl_token_program),
+                //TODO After
+                authorization_rules_program: None,
+                authorization_rules: None,
+            },
+            DelegateInstructionArgs {
+                delegate_args: DelegateArgs::LockedTransferV1 {
+                    amount: 1,
+                    locked_address: self.escrow.key(),
+                    authorization_data: None,
+                },
+            },
+        );
+
+        delegate_cpi.invoke()?;
+
+        let lock_cpi = LockCpi::new(
+            program,
+            LockCpiAccounts {
+                authority: delegate,
+                token_owner: Some(renter),
+                token,
+                mint,
+                metadata,
+                edition: Some(master_edition),
+                token_record: Some(token_record),
+                payer: renter,
+                system_program,
+                sysvar_instructions,
+                spl_token_program: Some(spl_token_program),
+                //TODO After
+                authorization_rules_program: None,
+                authorization_rules: None,
+            },
+            LockInstructionArgs {
+                lock_args: LockArgs::V1 {
+                    authorization_data: None,
+                },
+            },
+        );
+
+        lock_cpi.invoke_signed(signer_seeds)?;
+
+        Ok(())
+    }
+
+    // The escrow becomes the SPL delegate of the NFT and freezes it in the renter's wallet
+    fn approve_and_freeze(
+        &self,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+
+        let cpi_accounts = Approve {
+            to: self.renter_ata_a.to_account_info(),
+            delegate: self.escrow.to_account_info(),
+            authority: self.renter.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        approve(cpi_ctx, 1)?;
+
+        let program = &self.token_metadata_program.to_account_info();
+        let delegate = &self.escrow.to_account_info();
+        let token_account = &self.renter_ata_a.to_account_info();
+        let edition = &self.master_edition_a.to_account_info();
+        let mint = &self.mint_a.to_account_info();
+        let token_program = &self.token_program.to_account_info();
+
+        let freeze_cpi = FreezeDelegatedAccountCpi::new(
+            program,
+            FreezeDelegatedAccountCpiAccounts {
+                delegate,
+                token_account,
+                edition,
+                mint,
+                token_program,
+            },
+        );
+
+        freeze_cpi.invoke_signed(signer_seeds)?;
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,302 @@
+use solana_program::*;
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, CloseAccount, transfer, close_account},
+    metadata::{MetadataAccount, MasterEditionAccount, Metadata, TokenRecordAccount},
+    associated_token::AssociatedToken
+};
+
+use crate::{
+    state::{Escrow, Resolution, Marketplace},
+    errors::EscrowError,
+    denylist::check_not_denylisted,
+    marketplace::{match_marketplace, marketplace_seed},
+    transfer::{VaultTransfer, close_vault, marketplace_fee},
+};
+
+#[derive(Accounts)]
+pub struct Resolve<'info> {
+    // The buyer on a release, the arbiter on a resolution, anyone once a two-sided escrow is confirmed
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: we're checking the seeds
+    pub maker: UncheckedAccount<'info>,
+    #[account(mut, address = escrow.payer @ EscrowError::InvalidPayer)]
+    /// CHECK: we're checking the address
+    pub payer: UncheckedAccount<'info>,
+    #[account(address = escrow.payee(maker.key()) @ EscrowError::InvalidProceedsRecipient)]
+    /// CHECK: the maker, or the proceeds recipient set on the escrow
+    pub proceeds_recipient: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub buyer: SystemAccount<'info>,
+    /// CHECK: we're checking this against the resolution later
+    pub recipient: UncheckedAccount<'info>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_a: Box<Account<'info, MetadataAccount>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"edition",
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub master_edition_a: Option<Box<Account<'info, MasterEditionAccount>>>,
+    #[account(mut)]
+    pub vault_token_record_a: Option<Box<Account<'info, TokenRecordAccount>>>,
+    #[account(mut)]
+    /// CHECK: we're checking this later
+    pub recipient_token_record_a: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint_a,
+        associated_token::authority = recipient
+    )]
+    pub recipient_ata_a: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = escrow
+    )]
+    pub payment_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint_b,
+        associated_token::authority = proceeds_recipient
+    )]
+    pub maker_ata_b: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = buyer
+    )]
+    pub buyer_ata_b: Box<Account<'info, TokenAccount>>,
+    // Only needed when the arbiter takes a fee
+    #[account(mut)]
+    pub arbiter_ata_b: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(mut)]
+    pub treasury_ata_b: Option<Box<Account<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref(), marketplace_seed(&escrow.marketplace)],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+    /// CHECK: checked against mint_a later
+    pub mint_denylist: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection of mint_a later
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+    pub marketplace: Option<Box<Account<'info, Marketplace>>>,
+
+    #[account(address = sysvar::instructions::id())]
+    /// CHECK: we don't need to check this
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> Resolve<'info> {
+    // Without a dispute the buyer confirms the deal and releases both sides
+    pub fn release(
+        &mut self,
+        bumps: ResolveBumps,
+    ) -> Result<()> {
+
+        let arbitration = self.escrow.arbitration.ok_or(EscrowError::NotArbitrated)?;
+        let buyer = arbitration.buyer.ok_or(EscrowError::PaymentNotDeposited)?;
+        require!(!arbitration.disputed, EscrowError::EscrowDisputed);
+        require_keys_eq!(self.authority.key(), buyer, EscrowError::InvalidParty);
+
+        self.settle(buyer, Resolution::Release, 0, bumps)
+    }
+
+    // On a dispute the arbiter decides who gets what, and takes its fee out of the payment
+    pub fn resolve(
+        &mut self,
+        resolution: Resolution,
+        bumps: ResolveBumps,
+    ) -> Result<()> {
+
+        let arbitration = self.escrow.arbitration.ok_or(EscrowError::NotArbitrated)?;
+        require!(arbitration.disputed, EscrowError::EscrowNotDisputed);
+        require_keys_eq!(self.authority.key(), arbitration.arbiter, EscrowError::InvalidArbiter);
+
+        let buyer = arbitration.buyer.ok_or(EscrowError::PaymentNotDeposited)?;
+        let fee = arbitration.fee(self.payment_vault.amount);
+
+        self.settle(buyer, resolution, fee, bumps)
+    }
+
+    // A two-sided escrow releases both sides once the parties confirmed, or once the deadline passed
+    pub fn settle_confirmed(
+        &mut self,
+        bumps: ResolveBumps,
+    ) -> Result<()> {
+
+        let confirmation = self.escrow.confirmation.ok_or(EscrowError::NotTwoSided)?;
+        let buyer = confirmation.buyer.ok_or(EscrowError::PaymentNotDeposited)?;
+        require!(confirmation.is_settleable(Clock::get()?.unix_timestamp), EscrowError::ConfirmationPending);
+
+        self.settle(buyer, Resolution::Release, 0, bumps)
+    }
+
+    fn settle(
+        &mut self,
+        buyer: Pubkey,
+        resolution: Resolution,
+        fee: u64,
+        bumps: ResolveBumps,
+    ) -> Result<()> {
+
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+        require_keys_eq!(self.buyer.key(), buyer, EscrowError::InvalidParty);
+
+        let (recipient, payment_destination) = match resolution {
+            Resolution::Release => (buyer, self.maker_ata_b.to_account_info()),
+            Resolution::Refund => (self.maker.key(), self.buyer_ata_b.to_account_info()),
+        };
+        require_keys_eq!(self.recipient.key(), recipient, EscrowError::InvalidParty);
+
+        // A flagged asset can still be refunded to the maker, it just can't reach the buyer.
+        // Only a release is a sale, so only a release pays the marketplace fee
+        let marketplace_fee = match resolution {
+            Resolution::Release => {
+                let collection_denylist = self.collection_denylist.as_ref().map(|denylist| denylist.to_account_info());
+                check_not_denylisted(&self.mint_a.key(), &self.metadata_a, &self.mint_denylist.to_account_info(), collection_denylist.as_ref())?;
+                // The deal was checked against the marketplace when the payment was deposited
+                match_marketplace(&self.escrow, self.marketplace.as_deref())?;
+
+                marketplace_fee(&self.escrow, self.marketplace.as_deref(), self.payment_vault.amount, self.treasury_ata_b.as_deref())?
+            },
+            Resolution::Refund => 0,
+        };
+
+        let mint_a_key = self.mint_a.key();
+        let mint_b_key = self.mint_b.key();
+        let maker_key = self.maker.key();
+        let marketplace_key = self.escrow.marketplace;
+
+        let seeds = &[
+            "escrow".as_bytes(),
+            maker_key.as_ref(),
+            mint_a_key.as_ref(),
+            mint_b_key.as_ref(),
+            marketplace_seed(&marketplace_key),
+            &[bumps.escrow]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let master_edition = self.master_edition_a.as_ref().map(|master_edition| master_edition.to_account_info());
+        let vault_token_record = self.vault_token_record_a.as_ref().map(|token_record| token_record.to_account_info());
+
+        VaultTransfer {
+            escrow: &self.escrow.to_account_info(),
+            owner: &self.escrow.to_account_info(),
+            vault: &self.vault,
+            destination_ata: &self.recipient_ata_a.to_account_info(),
+            destination_owner: &self.recipient.to_account_info(),
+            mint: &self.mint_a.to_account_info(),
+            metadata: &self.metadata_a,
+            master_edition: master_edition.as_ref(),
+            vault_token_record: vault_token_record.as_ref(),
+            destination_token_record: &self.recipient_token_record_a.to_account_info(),
+            payer: &self.authority.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+            sysvar_instructions: &self.sysvar_instructions.to_account_info(),
+            token_program: &self.token_program.to_account_info(),
+            associated_token_program: &self.associated_token_program.to_account_info(),
+            token_metadata_program: &self.token_metadata_program.to_account_info(),
+        }.invoke_signed(signer_seeds)?;
+
+        close_vault(
+            &self.vault.to_account_info(),
+            &self.metadata_a,
+            &self.payer.to_account_info(),
+            &self.escrow.to_account_info(),
+            &self.token_program.to_account_info(),
+            signer_seeds,
+        )?;
+
+        // Pay the arbiter and the marketplace first, the rest of the payment goes to whoever the resolution favours
+        if fee > 0 {
+            let arbitration = self.escrow.arbitration.ok_or(EscrowError::NotArbitrated)?;
+            let arbiter_ata_b = self.arbiter_ata_b.as_ref().ok_or(EscrowError::InvalidPaymentAccounts)?;
+            require!(
+                arbiter_ata_b.owner == arbitration.arbiter && arbiter_ata_b.mint == mint_b_key,
+                EscrowError::InvalidPaymentAccounts
+            );
+
+            let cpi_accounts = Transfer {
+                from: self.payment_vault.to_account_info(),
+                to: arbiter_ata_b.to_account_info(),
+                authority: self.escrow.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+            transfer(cpi_ctx, fee)?;
+        }
+
+        if marketplace_fee > 0 {
+            let cpi_accounts = Transfer {
+                from: self.payment_vault.to_account_info(),
+                to: self.treasury_ata_b.as_ref().unwrap().to_account_info(),
+                authority: self.escrow.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+            transfer(cpi_ctx, marketplace_fee)?;
+        }
+
+        let cpi_accounts = Transfer {
+            from: self.payment_vault.to_account_info(),
+            to: payment_destination,
+            authority: self.escrow.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+        transfer(cpi_ctx, self.payment_vault.amount - fee - marketplace_fee)?;
+
+        // The buyer funded the payment vault, so the rent goes back to them
+        let cpi_accounts = CloseAccount {
+            account: self.payment_vault.to_account_info(),
+            destination: self.buyer.to_account_info(),
+            authority: self.escrow.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+        close_account(cpi_ctx)?;
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    state::Config,
+    errors::EscrowError,
+};
+
+#[derive(Accounts)]
+pub struct SetDefaultReferralFee<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = admin @ EscrowError::InvalidAdmin,
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+}
+
+impl<'info> SetDefaultReferralFee<'info> {
+    // The referral cut of every listing that doesn't set its own, fills check it against the other fees
+    pub fn set_default_referral_fee(
+        &mut self,
+        referral_bps: u16,
+    ) -> Result<()> {
+
+        require!(referral_bps <= 10_000, EscrowError::FeesExceedPrice);
+
+        self.config.referral_bps = referral_bps;
+
+        Ok(())
+    }
+}
\ No newline at end of file
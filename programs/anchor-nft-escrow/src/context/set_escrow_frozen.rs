@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    state::{Config, Escrow, Loan},
+    errors::EscrowError,
+};
+
+#[derive(Accounts)]
+pub struct SetEscrowFrozen<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        has_one = admin @ EscrowError::InvalidAdmin,
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    #[account(mut)]
+    pub escrow: Box<Account<'info, Escrow>>,
+    // Only needed for collateral
+    #[account(
+        mut,
+        has_one = escrow,
+        seeds = [b"loan", escrow.key().as_ref()],
+        bump,
+    )]
+    pub loan: Option<Box<Account<'info, Loan>>>,
+}
+
+impl<'info> SetEscrowFrozen<'info> {
+    // A frozen escrow keeps its asset until the admin thaws it, it can't be filled, settled or closed.
+    // Used for listings of assets flagged as stolen
+    pub fn set_escrow_frozen(
+        &mut self,
+        frozen: bool,
+    ) -> Result<()> {
+
+        if self.escrow.collateral {
+            let loan = self.loan.as_mut().ok_or(EscrowError::MissingLoan)?;
+            loan.set_frozen(frozen, Clock::get()?.unix_timestamp)?;
+        }
+
+        self.escrow.frozen = frozen;
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    state::Config,
+    errors::EscrowError,
+};
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = admin @ EscrowError::InvalidAdmin,
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+}
+
+impl<'info> SetPaused<'info> {
+    // While paused no listing can be made or filled. Closing a listing and settling an ended auction are
+    // always allowed so users can withdraw
+    pub fn set_paused(
+        &mut self,
+        paused: bool,
+    ) -> Result<()> {
+
+        self.config.paused = paused;
+
+        Ok(())
+    }
+}
\ No newline at end of file
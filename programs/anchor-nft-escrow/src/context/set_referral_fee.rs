@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    state::Escrow,
+    errors::EscrowError,
+    marketplace::marketplace_seed,
+};
+
+#[derive(Accounts)]
+pub struct SetReferralFee<'info> {
+    pub maker: Signer<'info>,
+
+    // Works for every kind of listing, the escrow seeds are taken from the escrow itself
+    #[account(
+        mut,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.mint_a.as_ref(), escrow.mint_b.as_ref(), marketplace_seed(&escrow.marketplace)],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+}
+
+impl<'info> SetReferralFee<'info> {
+    // The referrer of a fill gets referral_bps of the price, out of what the maker receives.
+    // None goes back to the referral cut of the config
+    pub fn set_referral_fee(
+        &mut self,
+        referral_bps: Option<u16>,
+    ) -> Result<()> {
+
+        // Together with the marketplace fee of the listing, the referral cut can't take more than the price
+        if let Some(referral_bps) = referral_bps {
+            require!(referral_bps as u32 + self.escrow.marketplace_fee_bps as u32 <= 10_000, EscrowError::FeesExceedPrice);
+        }
+
+        self.escrow.referral_bps = referral_bps;
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,225 @@
+use solana_program::*;
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, CloseAccount, transfer, close_account},
+    metadata::{MetadataAccount, MasterEditionAccount, Metadata, TokenRecordAccount},
+    associated_token::AssociatedToken
+};
+
+use crate::{
+    state::{Escrow, Marketplace},
+    errors::EscrowError,
+    denylist::check_not_denylisted,
+    marketplace::{match_marketplace, marketplace_seed},
+    transfer::{VaultTransfer, close_vault, marketplace_fee},
+};
+
+#[derive(Accounts)]
+pub struct Settle<'info> {
+    // Anyone can settle an ended auction
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: we're checking the seeds
+    pub maker: UncheckedAccount<'info>,
+    #[account(mut, address = escrow.payer @ EscrowError::InvalidPayer)]
+    /// CHECK: we're checking the address
+    pub payer: UncheckedAccount<'info>,
+    #[account(address = escrow.payee(maker.key()) @ EscrowError::InvalidProceedsRecipient)]
+    /// CHECK: the maker, or the proceeds recipient set on the escrow
+    pub proceeds_recipient: UncheckedAccount<'info>,
+    /// CHECK: we're checking this against the highest bidder later
+    pub winner: UncheckedAccount<'info>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_a: Box<Account<'info, MetadataAccount>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"edition",
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub master_edition_a: Option<Box<Account<'info, MasterEditionAccount>>>,
+    #[account(mut)]
+    pub vault_token_record_a: Option<Box<Account<'info, TokenRecordAccount>>>,
+    #[account(mut)]
+    /// CHECK: we're checking this later
+    pub winner_token_record_a: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = cranker,
+        associated_token::mint = mint_a,
+        associated_token::authority = winner
+    )]
+    pub winner_ata_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = escrow
+    )]
+    pub bid_vault: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(mut)]
+    /// CHECK: we're checking this against the first bidder later
+    pub bid_vault_payer: Option<UncheckedAccount<'info>>,
+    #[account(
+        init_if_needed,
+        payer = cranker,
+        associated_token::mint = mint_b,
+        associated_token::authority = proceeds_recipient
+    )]
+    pub maker_ata_b: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub treasury_ata_b: Option<Box<Account<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref(), marketplace_seed(&escrow.marketplace)],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+    /// CHECK: checked against mint_a later
+    pub mint_denylist: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection of mint_a later
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+    pub marketplace: Option<Box<Account<'info, Marketplace>>>,
+
+    #[account(address = sysvar::instructions::id())]
+    /// CHECK: we don't need to check this
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> Settle<'info> {
+    pub fn settle(
+        &mut self,
+        bumps: SettleBumps,
+    ) -> Result<()> {
+
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+        let collection_denylist = self.collection_denylist.as_ref().map(|denylist| denylist.to_account_info());
+        check_not_denylisted(&self.mint_a.key(), &self.metadata_a, &self.mint_denylist.to_account_info(), collection_denylist.as_ref())?;
+        // The bids were checked against the marketplace when they were placed
+        match_marketplace(&self.escrow, self.marketplace.as_deref())?;
+
+        let english_auction = self.escrow.english_auction.ok_or(EscrowError::NotAnAuction)?;
+
+        require!(Clock::get()?.unix_timestamp >= english_auction.end_time, EscrowError::AuctionNotEnded);
+
+        // Without bids the asset goes back to the maker
+        let winner = english_auction.highest_bidder.unwrap_or(self.maker.key());
+        require_keys_eq!(self.winner.key(), winner, EscrowError::InvalidBidder);
+
+        let mint_a_key = self.mint_a.key();
+        let mint_b_key = self.mint_b.key();
+        let maker_key = self.maker.key();
+        let marketplace_key = self.escrow.marketplace;
+
+        let seeds = &[
+            "escrow".as_bytes(),
+            maker_key.as_ref(),
+            mint_a_key.as_ref(),
+            mint_b_key.as_ref(),
+            marketplace_seed(&marketplace_key),
+            &[bumps.escrow]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let master_edition = self.master_edition_a.as_ref().map(|master_edition| master_edition.to_account_info());
+        let vault_token_record = self.vault_token_record_a.as_ref().map(|token_record| token_record.to_account_info());
+
+        VaultTransfer {
+            escrow: &self.escrow.to_account_info(),
+            owner: &self.escrow.to_account_info(),
+            vault: &self.vault,
+            destination_ata: &self.winner_ata_a.to_account_info(),
+            destination_owner: &self.winner.to_account_info(),
+            mint: &self.mint_a.to_account_info(),
+            metadata: &self.metadata_a,
+            master_edition: master_edition.as_ref(),
+            vault_token_record: vault_token_record.as_ref(),
+            destination_token_record: &self.winner_token_record_a.to_account_info(),
+            payer: &self.cranker.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+            sysvar_instructions: &self.sysvar_instructions.to_account_info(),
+            token_program: &self.token_program.to_account_info(),
+            associated_token_program: &self.associated_token_program.to_account_info(),
+            token_metadata_program: &self.token_metadata_program.to_account_info(),
+        }.invoke_signed(signer_seeds)?;
+
+        close_vault(
+            &self.vault.to_account_info(),
+            &self.metadata_a,
+            &self.payer.to_account_info(),
+            &self.escrow.to_account_info(),
+            &self.token_program.to_account_info(),
+            signer_seeds,
+        )?;
+
+        // Release the winning bid to the maker, minus the marketplace fee, and close the bid vault
+        if english_auction.highest_bidder.is_some() {
+            let bid_vault = self.bid_vault.as_ref().ok_or(EscrowError::InvalidBidder)?;
+            let bid_vault_payer = self.bid_vault_payer.as_ref().ok_or(EscrowError::InvalidPayer)?;
+            require!(english_auction.bid_vault_payer == Some(bid_vault_payer.key()), EscrowError::InvalidPayer);
+
+            let fee = marketplace_fee(&self.escrow, self.marketplace.as_deref(), english_auction.highest_bid, self.treasury_ata_b.as_deref())?;
+            if fee > 0 {
+                let cpi_accounts = Transfer {
+                    from: bid_vault.to_account_info(),
+                    to: self.treasury_ata_b.as_ref().unwrap().to_account_info(),
+                    authority: self.escrow.to_account_info(),
+                };
+                let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+                transfer(cpi_ctx, fee)?;
+            }
+
+            let cpi_accounts = Transfer {
+                from: bid_vault.to_account_info(),
+                to: self.maker_ata_b.to_account_info(),
+                authority: self.escrow.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+            transfer(cpi_ctx, english_auction.highest_bid - fee)?;
+
+            let cpi_accounts = CloseAccount {
+                account: bid_vault.to_account_info(),
+                destination: bid_vault_payer.to_account_info(),
+                authority: self.escrow.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+            close_account(cpi_ctx)?;
+        }
+
+        Ok(())
+    }
+}
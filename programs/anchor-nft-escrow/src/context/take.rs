@@ -5,23 +5,30 @@ use anchor_spl::{
     token::{Mint, TokenAccount, Token}, 
     metadata::{MetadataAccount, MasterEditionAccount, Metadata, TokenRecordAccount, 
         mpl_token_metadata::{
-            instructions::{TransferCpi, TransferCpiAccounts, TransferInstructionArgs}, 
+            instructions::{ThawDelegatedAccountCpi, ThawDelegatedAccountCpiAccounts}, 
             types::TokenStandard}
         },
     associated_token::{AssociatedToken, get_associated_token_address}
 };
-use mpl_token_metadata::types::TransferArgs;
 use sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
 
 use crate::{
-    state::Escrow,
+    state::{Escrow, Config, Marketplace},
     errors::{EscrowError, IntrospectionError},
+    denylist::check_not_denylisted,
+    marketplace::{check_marketplace, marketplace_seed},
+    events::Fill,
+    transfer::{AssetTransfer, VaultTransfer},
 };
 
 #[derive(Accounts)]
 pub struct Take<'info> {
     #[account(mut)]
-    pub maker: SystemAccount<'info>,
+    /// CHECK: we're checking the seeds
+    pub maker: UncheckedAccount<'info>,
+    #[account(address = escrow.payee(maker.key()) @ EscrowError::InvalidProceedsRecipient)]
+    /// CHECK: the maker, or the proceeds recipient set on the escrow
+    pub proceeds_recipient: UncheckedAccount<'info>,
     #[account(mut)]
     pub taker: Signer<'info>,
 
@@ -44,10 +51,20 @@ pub struct Take<'info> {
     pub destination_ata: Box<Account<'info, TokenAccount>>, //Start: Taker_ata; End: Maker_ata
 
     #[account(
-        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref()],
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref(), marketplace_seed(&escrow.marketplace)],
         bump,
     )]
     pub escrow: Box<Account<'info, Escrow>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    /// CHECK: checked against mint_a later
+    pub mint_denylist: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection of mint_a later
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+    pub marketplace: Option<Box<Account<'info, Marketplace>>>,
 
     #[account(address = sysvar::instructions::id())]
     /// CHECK: we don't need to check this
@@ -59,144 +76,92 @@ pub struct Take<'info> {
 }
 
 impl<'info> Take<'info> {
+    // Releases the asset against a taker_to_maker instruction that has to come right after it at the top level.
+    // That check can't hold under CPI, so this instruction rejects it and take_atomic replaces it for composing programs
     pub fn take_from_escrow(
         &mut self,
         bumps: TakeBumps,
     ) -> Result<()> {
 
-        //All deferred errors
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+        let collection_denylist = self.collection_denylist.as_ref().map(|denylist| denylist.to_account_info());
+        check_not_denylisted(&self.mint_a.key(), &self.metadata, &self.mint_denylist.to_account_info(), collection_denylist.as_ref())?;
+        check_marketplace(&self.escrow, self.marketplace.as_deref(), &self.metadata)?;
+        // Only the payment to the maker is introspected, listings that owe a marketplace fee are taken with take_atomic
+        require!(self.escrow.marketplace_fee_bps == 0, EscrowError::InvalidMarketplaceFee);
+
         let mint_a_key = self.mint_a.key();
         let mint_b_key = self.mint_b.key();
         let maker_key = self.maker.key();
-        let metadata_token_standard = self.metadata.token_standard.as_ref().unwrap();
-        let token_metadata_program_key = self.token_metadata_program.key();
-        let master_edition_info: AccountInfo<'_>;
-        let token_record_info: AccountInfo<'_>;
-        let destination_token_record_info: AccountInfo<'_>;
-
-        // Set-up the Instruction based on the token standard - We set it up as a Fungible by default
-        let mut edition: Option<&AccountInfo> = None;
-        let mut token_record: Option<&AccountInfo> = None;
-        let mut destination_token_record: Option<&AccountInfo> = None;
-        let mut transfer_args = TransferArgs::V1 {
-            amount: self.origin_ata.amount,
-            authorization_data: None,
-        };
+        let marketplace_key = self.escrow.marketplace;
 
-        // Check the Metadata account
-        let metadata_seed = [
-            b"metadata",
-            token_metadata_program_key.as_ref(),
-            mint_a_key.as_ref(),
-        ];
-        let (metadata, _) = Pubkey::find_program_address(&metadata_seed, &self.token_metadata_program.key());
-        require_keys_eq!(metadata, self.metadata.key(), EscrowError::MetadataAccountDoesNotMatch);
-
-
-        if metadata_token_standard == &TokenStandard::NonFungible || metadata_token_standard == &TokenStandard::ProgrammableNonFungible {
-            
-            //Check the Master Edition account
-            let master_edition_seed = [
-                b"metadata",
-                token_metadata_program_key.as_ref(),
-                mint_a_key.as_ref(),
-                b"edition",
-            ];
-            let (master_edition, _) = Pubkey::find_program_address(&master_edition_seed, &self.token_metadata_program.key());
-            require_keys_eq!(master_edition, self.master_edition.as_ref().unwrap().key(), EscrowError::MasterEditionAccountDoesNotMatch);
-            
-            master_edition_info = self.master_edition.as_ref().unwrap().to_account_info();
-            edition = Some(&master_edition_info);
-            transfer_args = TransferArgs::V1 {
-                amount: 1,
-                authorization_data: None,
-            };
-        } 
-        
-        if metadata_token_standard == &TokenStandard::ProgrammableNonFungible {
-
-            //Check the token record
-            let token_record_account_seed = [
-                b"metadata",
-                token_metadata_program_key.as_ref(),
-                mint_a_key.as_ref(),
-                b"token_record",
-                self.origin_ata.to_account_info().key.as_ref(),
-            ];
-            let (origin_token_record_pda, _) = Pubkey::find_program_address(&token_record_account_seed, &self.token_metadata_program.key());
-            require_keys_eq!(origin_token_record_pda, self.origin_token_record.as_ref().unwrap().key(), EscrowError::TokenRecordAccountDoesNotMatch);
-
-            let token_record_account_seed = [
-                b"metadata",
-                token_metadata_program_key.as_ref(),
-                mint_a_key.as_ref(),
-                b"token_record",
-                self.destination_ata.to_account_info().key.as_ref(),
-            ];
-            let (destination_token_record_pda, _) = Pubkey::find_program_address(&token_record_account_seed, &self.token_metadata_program.key());
-            require_keys_eq!(destination_token_record_pda, self.destination_token_record.key(), EscrowError::TokenRecordAccountDoesNotMatch);
-
-            token_record_info = self.origin_token_record.as_ref().unwrap().to_account_info();
-            destination_token_record_info = self.destination_token_record.to_account_info();
-
-            token_record = Some(&token_record_info);
-            destination_token_record = Some(&destination_token_record_info);
-        };
-
-        // Build the TransferCpi instruction to transfer the token from the maker to the escrow
-        let program = &self.token_metadata_program.to_account_info();
-        let token = &self.origin_ata.to_account_info();
-        let token_owner = &self.escrow.to_account_info();
-        let destination_token = &self.destination_ata.to_account_info();
-        let destination_owner = &self.taker.to_account_info();
-        let mint = &self.mint_a.to_account_info();
-        let metadata = &self.metadata.to_account_info();
-        let authority = &self.escrow.to_account_info();
-        let payer = &self.taker.to_account_info();
-        let system_program = &self.system_program.to_account_info();
-        let sysvar_instructions = &self.sysvar_instructions.to_account_info();
-        let spl_token_program = &self.token_program.to_account_info();
-        let spl_ata_program = &self.associated_token_program.to_account_info();
-        //TODO After
-        let authorization_rules_program = None;
-        let authorization_rules = None;
-
-        let transfer_cpi = TransferCpi::new(
-            program,
-            TransferCpiAccounts {
-                token,
-                token_owner,
-                destination_token,
-                destination_owner,
-                mint,
-                metadata,
-                edition,
-                token_record,
-                destination_token_record,
-                authority,
-                payer,
-                system_program,
-                sysvar_instructions,
-                spl_token_program,
-                spl_ata_program,
-                authorization_rules_program,
-                authorization_rules,              
-            },
-            TransferInstructionArgs {
-                transfer_args,
-            },
-        );
+        require!(self.escrow.english_auction.is_none(), EscrowError::AuctionInProgress);
+        require!(self.escrow.rental.is_none() && !self.escrow.collateral && self.escrow.arbitration.is_none() && self.escrow.confirmation.is_none() && !self.escrow.split, EscrowError::InvalidListingMode);
 
         let seeds = &[
             "escrow".as_bytes(),
             maker_key.as_ref(),
             mint_a_key.as_ref(),
             mint_b_key.as_ref(),
+            marketplace_seed(&marketplace_key),
             &[bumps.escrow]
         ];
         let signer_seeds = &[&seeds[..]];
 
-        transfer_cpi.invoke_signed(signer_seeds)?;
+        let master_edition = self.master_edition.as_ref().map(|master_edition| master_edition.to_account_info());
+        let origin_token_record = self.origin_token_record.as_ref().map(|token_record| token_record.to_account_info());
+
+        // Delegated NFTs are frozen in the maker's wallet and need to be thawed before they can move
+        if self.escrow.delegated && self.metadata.token_standard.as_ref().unwrap() == &TokenStandard::NonFungible {
+            let program = &self.token_metadata_program.to_account_info();
+            let delegate = &self.escrow.to_account_info();
+            let token_account = &self.origin_ata.to_account_info();
+            let edition = &self.master_edition.as_ref().ok_or(EscrowError::MasterEditionAccountDoesNotMatch)?.to_account_info();
+            let mint = &self.mint_a.to_account_info();
+            let token_program = &self.token_program.to_account_info();
+
+            let thaw_cpi = ThawDelegatedAccountCpi::new(
+                program,
+                ThawDelegatedAccountCpiAccounts {
+                    delegate,
+                    token_account,
+                    edition,
+                    mint,
+                    token_program,
+                },
+            );
+
+            thaw_cpi.invoke_signed(signer_seeds)?;
+        }
+
+        // Delegated listings are transferred straight from the maker's token account
+        let owner = match self.escrow.delegated {
+            true => self.maker.to_account_info(),
+            false => self.escrow.to_account_info(),
+        };
+
+        VaultTransfer {
+            escrow: &self.escrow.to_account_info(),
+            owner: &owner,
+            vault: &self.origin_ata,
+            destination_ata: &self.destination_ata.to_account_info(),
+            destination_owner: &self.taker.to_account_info(),
+            mint: &self.mint_a.to_account_info(),
+            metadata: &self.metadata,
+            master_edition: master_edition.as_ref(),
+            vault_token_record: origin_token_record.as_ref(),
+            destination_token_record: &self.destination_token_record.to_account_info(),
+            payer: &self.taker.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+            sysvar_instructions: &self.sysvar_instructions.to_account_info(),
+            token_program: &self.token_program.to_account_info(),
+            associated_token_program: &self.associated_token_program.to_account_info(),
+            token_metadata_program: &self.token_metadata_program.to_account_info(),
+        }.invoke_signed(signer_seeds)?;
+
+        // The introspection below only sees top-level instructions, programs composing with the escrow use take_atomic
+        require_eq!(instruction::get_stack_height(), instruction::TRANSACTION_LEVEL_STACK_HEIGHT, EscrowError::CpiNotSupported);
 
         // Set up Instruction Introspection to make sure that:
         // 1. The token was transferred from the taker to the maker after this transaction
@@ -216,12 +181,25 @@ impl<'info> Take<'info> {
         require_keys_eq!(ix.program_id, crate::ID, IntrospectionError::InvalidProgram);
         require!(ix.data[0..8].eq(crate::instruction::TakerToMaker::DISCRIMINATOR.as_slice()), IntrospectionError::InvalidDiscriminator);
 
-        // Testing 2: We know that the 10th account is going to be the destination_ata, and we know that we want that as the maker_ata
-        let maker_ata = get_associated_token_address(&self.maker.key(), &self.mint_b.key());
-        require_keys_eq!(ix.accounts.get(9).unwrap().pubkey, maker_ata, IntrospectionError::InvalidMakerATA);
+        // Testing 2: We know that the 11th account is going to be the destination_ata, and we know that we want that as the ATA of the maker, or of the proceeds recipient
+        let maker_ata = get_associated_token_address(&self.escrow.payee(self.maker.key()), &self.mint_b.key());
+        require_keys_eq!(ix.accounts.get(10).unwrap().pubkey, maker_ata, IntrospectionError::InvalidMakerATA);
         
         // Testing 3: We know that 8 bites (u8) after the discriminator will be the amount since it's the only variable we are passing
-        require!(ix.data[8..16].eq(&self.escrow.mint_b_amount.to_le_bytes()), IntrospectionError::InvalidAmount);
+        let price = self.escrow.current_price(Clock::get()?.unix_timestamp);
+        require!(ix.data[8..16].eq(&price.to_le_bytes()), IntrospectionError::InvalidAmount);
+
+        emit!(Fill {
+            escrow: self.escrow.key(),
+            maker: self.maker.key(),
+            taker: self.taker.key(),
+            mint_a: self.mint_a.key(),
+            mint_b: self.mint_b.key(),
+            price,
+            referrer: None,
+            referral_fee: 0,
+            marketplace_fee: 0,
+        });
 
         Ok(())
     }
@@ -231,126 +209,37 @@ impl<'info> Take<'info> {
         amount: u64,
     ) -> Result<()> {
 
-        require_eq!(amount, self.escrow.mint_b_amount, EscrowError::InvalidAmount);
-
-        //All deferred errors
-        let mint_b_key = self.mint_b.key();
-        let metadata_token_standard = self.metadata.token_standard.as_ref().unwrap();
-        let token_metadata_program_key = self.token_metadata_program.key();
-        let master_edition_info: AccountInfo<'_>;
-        let token_record_info: AccountInfo<'_>;
-        let destination_token_record_info: AccountInfo<'_>;
-
-        // Set-up the Instruction based on the token standard - We set it up as a Fungible by default
-        let mut edition: Option<&AccountInfo> = None;
-        let mut token_record: Option<&AccountInfo> = None;
-        let mut destination_token_record: Option<&AccountInfo> = None;
-        let transfer_args = TransferArgs::V1 {
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+
+        require!(self.escrow.english_auction.is_none(), EscrowError::AuctionInProgress);
+        require!(self.escrow.rental.is_none() && !self.escrow.collateral && self.escrow.arbitration.is_none() && self.escrow.confirmation.is_none() && !self.escrow.split, EscrowError::InvalidListingMode);
+        require_eq!(amount, self.escrow.current_price(Clock::get()?.unix_timestamp), EscrowError::InvalidAmount);
+
+        let master_edition = self.master_edition.as_ref().map(|master_edition| master_edition.to_account_info());
+        let origin_token_record = self.origin_token_record.as_ref().map(|token_record| token_record.to_account_info());
+        let destination_token_record = self.destination_token_record.to_account_info();
+
+        // Transfer the payment from the taker to the maker, or to the proceeds recipient
+        AssetTransfer {
+            token: &self.origin_ata.to_account_info(),
+            token_owner: &self.taker.to_account_info(),
+            destination_token: &self.destination_ata.to_account_info(),
+            destination_owner: &self.proceeds_recipient.to_account_info(),
+            mint: &self.mint_b.to_account_info(),
+            metadata: &self.metadata,
+            master_edition: master_edition.as_ref(),
+            token_record: origin_token_record.as_ref(),
+            destination_token_record: Some(&destination_token_record),
+            authority: &self.taker.to_account_info(),
+            payer: &self.taker.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+            sysvar_instructions: &self.sysvar_instructions.to_account_info(),
+            token_program: &self.token_program.to_account_info(),
+            associated_token_program: &self.associated_token_program.to_account_info(),
+            token_metadata_program: &self.token_metadata_program.to_account_info(),
             amount,
-            authorization_data: None,
-        };
-
-        // Check the Metadata account
-        let metadata_seed = [
-            b"metadata",
-            token_metadata_program_key.as_ref(),
-            mint_b_key.as_ref(),
-        ];
-        let (metadata, _) = Pubkey::find_program_address(&metadata_seed, &self.token_metadata_program.key());
-        require_keys_eq!(metadata, self.metadata.key(), EscrowError::MetadataAccountDoesNotMatch);
-
-
-        if metadata_token_standard == &TokenStandard::NonFungible || metadata_token_standard == &TokenStandard::ProgrammableNonFungible {
-            
-            //Check the Master Edition account
-            let master_edition_seed = [
-                b"metadata",
-                token_metadata_program_key.as_ref(),
-                mint_b_key.as_ref(),
-                b"edition",
-            ];
-            let (master_edition, _) = Pubkey::find_program_address(&master_edition_seed, &self.token_metadata_program.key());
-            require_keys_eq!(master_edition, self.master_edition.as_ref().unwrap().key(), EscrowError::MasterEditionAccountDoesNotMatch);
-            
-            master_edition_info = self.master_edition.as_ref().unwrap().to_account_info();
-            edition = Some(&master_edition_info);
-        } 
-        
-        if metadata_token_standard == &TokenStandard::ProgrammableNonFungible {
-
-            //Check the token record
-            let token_record_account_seed = [
-                b"metadata",
-                token_metadata_program_key.as_ref(),
-                mint_b_key.as_ref(),
-                b"token_record",
-                self.origin_ata.to_account_info().key.as_ref(),
-            ];
-            let (origin_token_record_pda, _) = Pubkey::find_program_address(&token_record_account_seed, &self.token_metadata_program.key());
-            require_keys_eq!(origin_token_record_pda, self.origin_token_record.as_ref().unwrap().key(), EscrowError::TokenRecordAccountDoesNotMatch);
-
-            let token_record_account_seed = [
-                b"metadata",
-                token_metadata_program_key.as_ref(),
-                mint_b_key.as_ref(),
-                b"token_record",
-                self.destination_ata.to_account_info().key.as_ref(),
-            ];
-            let (destination_token_record_pda, _) = Pubkey::find_program_address(&token_record_account_seed, &self.token_metadata_program.key());
-            require_keys_eq!(destination_token_record_pda, self.destination_token_record.key(), EscrowError::TokenRecordAccountDoesNotMatch);
-
-            token_record_info = self.origin_token_record.as_ref().unwrap().to_account_info();
-            destination_token_record_info = self.destination_token_record.to_account_info();
-
-            token_record = Some(&token_record_info);
-            destination_token_record = Some(&destination_token_record_info);
-        };
-
-        // Build the TransferCpi instruction to transfer the token from the maker to the escrow
-        let program = &self.token_metadata_program.to_account_info();
-        let token = &self.origin_ata.to_account_info();
-        let token_owner = &self.taker.to_account_info();
-        let destination_token = &self.destination_ata.to_account_info();
-        let destination_owner = &self.maker.to_account_info();
-        let mint = &self.mint_b.to_account_info();
-        let metadata = &self.metadata.to_account_info();
-        let authority = &self.taker.to_account_info();
-        let payer = &self.taker.to_account_info();
-        let system_program = &self.system_program.to_account_info();
-        let sysvar_instructions = &self.sysvar_instructions.to_account_info();
-        let spl_token_program = &self.token_program.to_account_info();
-        let spl_ata_program = &self.associated_token_program.to_account_info();
-        //TODO After
-        let authorization_rules_program = None;
-        let authorization_rules = None;
-
-        let transfer_cpi = TransferCpi::new(
-            program,
-            TransferCpiAccounts {
-                token,
-                token_owner,
-                destination_token,
-                destination_owner,
-                mint,
-                metadata,
-                edition,
-                token_record,
-                destination_token_record,
-                authority,
-                payer,
-                system_program,
-                sysvar_instructions,
-                spl_token_program,
-                spl_ata_program,
-                authorization_rules_program,
-                authorization_rules,              
-            },
-            TransferInstructionArgs {
-                transfer_args,
-            },
-        );
-
-        transfer_cpi.invoke()?;
+        }.invoke()?;
 
         Ok(())
     }
@@ -0,0 +1,313 @@
+use solana_program::*;
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token},
+    metadata::{MetadataAccount, MasterEditionAccount, Metadata, TokenRecordAccount,
+        mpl_token_metadata::{
+            instructions::{ThawDelegatedAccountCpi, ThawDelegatedAccountCpiAccounts},
+            types::TokenStandard}
+        },
+    associated_token::AssociatedToken
+};
+
+use crate::{
+    state::{Escrow, Config, Marketplace},
+    errors::EscrowError,
+    denylist::check_not_denylisted,
+    marketplace::{check_marketplace, marketplace_seed},
+    events::Fill,
+    transfer::{AssetTransfer, VaultTransfer, close_vault, pay_referral, pay_marketplace_fee},
+};
+
+#[derive(Accounts)]
+pub struct TakeAtomic<'info> {
+    #[account(mut)]
+    /// CHECK: we're checking the seeds
+    pub maker: UncheckedAccount<'info>,
+    #[account(mut, address = escrow.payer @ EscrowError::InvalidPayer)]
+    /// CHECK: we're checking the address
+    pub payer: UncheckedAccount<'info>,
+    #[account(address = escrow.payee(maker.key()) @ EscrowError::InvalidProceedsRecipient)]
+    /// CHECK: the maker, or the proceeds recipient set on the escrow
+    pub proceeds_recipient: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_a: Box<Account<'info, MetadataAccount>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"edition",
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub master_edition_a: Option<Box<Account<'info, MasterEditionAccount>>>,
+    #[account(mut)]
+    pub origin_token_record_a: Option<Box<Account<'info, TokenRecordAccount>>>,
+    #[account(mut)]
+    /// CHECK: we're checking this later
+    pub taker_token_record_a: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_b.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_b: Box<Account<'info, MetadataAccount>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_b.key().as_ref(),
+            b"edition",
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub master_edition_b: Option<Box<Account<'info, MasterEditionAccount>>>,
+    #[account(mut)]
+    pub taker_token_record_b: Option<Box<Account<'info, TokenRecordAccount>>>,
+    #[account(mut)]
+    /// CHECK: we're checking this later
+    pub maker_token_record_b: UncheckedAccount<'info>,
+
+    // The vault, or the maker's token account for delegated listings
+    #[account(
+        mut,
+        token::mint = mint_a,
+    )]
+    pub origin_ata_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker
+    )]
+    pub taker_ata_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker
+    )]
+    pub taker_ata_b: Box<Account<'info, TokenAccount>>,
+    // Only needed for referred fills, the referrer is the owner of this token account
+    #[account(mut)]
+    pub referrer_ata_b: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(mut)]
+    pub treasury_ata_b: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = proceeds_recipient
+    )]
+    pub maker_ata_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref(), marketplace_seed(&escrow.marketplace)],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    /// CHECK: checked against mint_a later
+    pub mint_denylist: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection of mint_a later
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+    pub marketplace: Option<Box<Account<'info, Marketplace>>>,
+
+    #[account(address = sysvar::instructions::id())]
+    /// CHECK: we don't need to check this
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> TakeAtomic<'info> {
+    // Pays the maker and releases the asset in the same instruction, so unlike take_from_escrow
+    // it doesn't depend on the order of the top-level instructions and can be called through CPI
+    pub fn take_atomic(
+        &mut self,
+        bumps: TakeAtomicBumps,
+    ) -> Result<()> {
+
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+        let collection_denylist = self.collection_denylist.as_ref().map(|denylist| denylist.to_account_info());
+        check_not_denylisted(&self.mint_a.key(), &self.metadata_a, &self.mint_denylist.to_account_info(), collection_denylist.as_ref())?;
+        check_marketplace(&self.escrow, self.marketplace.as_deref(), &self.metadata_a)?;
+
+        require!(self.escrow.english_auction.is_none(), EscrowError::AuctionInProgress);
+        require!(self.escrow.rental.is_none() && !self.escrow.collateral && self.escrow.arbitration.is_none() && self.escrow.confirmation.is_none() && !self.escrow.split, EscrowError::InvalidListingMode);
+
+        // The token is in the vault, or still in the maker's wallet for delegated listings
+        let owner = match self.escrow.delegated {
+            true => self.maker.to_account_info(),
+            false => self.escrow.to_account_info(),
+        };
+        require_keys_eq!(self.origin_ata_a.owner, owner.key(), EscrowError::InvalidListingMode);
+
+        // Pay the referrer, the marketplace and the maker first
+        let price = self.escrow.current_price(Clock::get()?.unix_timestamp);
+
+        let (referrer, referral_fee) = pay_referral(
+            &self.escrow,
+            &self.config,
+            self.metadata_a.seller_fee_basis_points,
+            price,
+            self.referrer_ata_b.as_deref(),
+            &self.taker_ata_b.to_account_info(),
+            &self.taker.to_account_info(),
+            &self.token_program.to_account_info(),
+        )?;
+
+        let marketplace_fee = pay_marketplace_fee(
+            &self.escrow,
+            self.marketplace.as_deref(),
+            price,
+            self.treasury_ata_b.as_deref(),
+            &self.taker_ata_b.to_account_info(),
+            &self.taker.to_account_info(),
+            &self.token_program.to_account_info(),
+        )?;
+
+        let master_edition_b = self.master_edition_b.as_ref().map(|master_edition| master_edition.to_account_info());
+        let taker_token_record_b = self.taker_token_record_b.as_ref().map(|token_record| token_record.to_account_info());
+        let maker_token_record_b = self.maker_token_record_b.to_account_info();
+
+        AssetTransfer {
+            token: &self.taker_ata_b.to_account_info(),
+            token_owner: &self.taker.to_account_info(),
+            destination_token: &self.maker_ata_b.to_account_info(),
+            destination_owner: &self.proceeds_recipient.to_account_info(),
+            mint: &self.mint_b.to_account_info(),
+            metadata: &self.metadata_b,
+            master_edition: master_edition_b.as_ref(),
+            token_record: taker_token_record_b.as_ref(),
+            destination_token_record: Some(&maker_token_record_b),
+            authority: &self.taker.to_account_info(),
+            payer: &self.taker.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+            sysvar_instructions: &self.sysvar_instructions.to_account_info(),
+            token_program: &self.token_program.to_account_info(),
+            associated_token_program: &self.associated_token_program.to_account_info(),
+            token_metadata_program: &self.token_metadata_program.to_account_info(),
+            amount: price - referral_fee - marketplace_fee,
+        }.invoke()?;
+
+        let mint_a_key = self.mint_a.key();
+        let mint_b_key = self.mint_b.key();
+        let maker_key = self.maker.key();
+        let marketplace_key = self.escrow.marketplace;
+
+        let seeds = &[
+            "escrow".as_bytes(),
+            maker_key.as_ref(),
+            mint_a_key.as_ref(),
+            mint_b_key.as_ref(),
+            marketplace_seed(&marketplace_key),
+            &[bumps.escrow]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let master_edition_a = self.master_edition_a.as_ref().map(|master_edition| master_edition.to_account_info());
+        let origin_token_record_a = self.origin_token_record_a.as_ref().map(|token_record| token_record.to_account_info());
+
+        // Delegated NFTs are frozen in the maker's wallet and need to be thawed before they can move
+        if self.escrow.delegated && self.metadata_a.token_standard.as_ref().unwrap() == &TokenStandard::NonFungible {
+            let program = &self.token_metadata_program.to_account_info();
+            let delegate = &self.escrow.to_account_info();
+            let token_account = &self.origin_ata_a.to_account_info();
+            let edition = master_edition_a.as_ref().ok_or(EscrowError::MasterEditionAccountDoesNotMatch)?;
+            let mint = &self.mint_a.to_account_info();
+            let token_program = &self.token_program.to_account_info();
+
+            let thaw_cpi = ThawDelegatedAccountCpi::new(
+                program,
+                ThawDelegatedAccountCpiAccounts {
+                    delegate,
+                    token_account,
+                    edition,
+                    mint,
+                    token_program,
+                },
+            );
+
+            thaw_cpi.invoke_signed(signer_seeds)?;
+        }
+
+        VaultTransfer {
+            escrow: &self.escrow.to_account_info(),
+            owner: &owner,
+            vault: &self.origin_ata_a,
+            destination_ata: &self.taker_ata_a.to_account_info(),
+            destination_owner: &self.taker.to_account_info(),
+            mint: &self.mint_a.to_account_info(),
+            metadata: &self.metadata_a,
+            master_edition: master_edition_a.as_ref(),
+            vault_token_record: origin_token_record_a.as_ref(),
+            destination_token_record: &self.taker_token_record_a.to_account_info(),
+            payer: &self.taker.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+            sysvar_instructions: &self.sysvar_instructions.to_account_info(),
+            token_program: &self.token_program.to_account_info(),
+            associated_token_program: &self.associated_token_program.to_account_info(),
+            token_metadata_program: &self.token_metadata_program.to_account_info(),
+        }.invoke_signed(signer_seeds)?;
+
+        // Delegated listings were taken from the maker's own token account, which stays open
+        if !self.escrow.delegated {
+            close_vault(
+                &self.origin_ata_a.to_account_info(),
+                &self.metadata_a,
+                &self.payer.to_account_info(),
+                &self.escrow.to_account_info(),
+                &self.token_program.to_account_info(),
+                signer_seeds,
+            )?;
+        }
+
+        emit!(Fill {
+            escrow: self.escrow.key(),
+            maker: self.maker.key(),
+            taker: self.taker.key(),
+            mint_a: self.mint_a.key(),
+            mint_b: self.mint_b.key(),
+            price,
+            referrer,
+            referral_fee,
+            marketplace_fee,
+        });
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,203 @@
+use solana_program::*;
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, CloseAccount, transfer, close_account},
+    metadata::{MetadataAccount, MasterEditionAccount, Metadata, TokenRecordAccount},
+    associated_token::AssociatedToken
+};
+
+use crate::{
+    state::{Bid, Config},
+    errors::EscrowError,
+    denylist::check_not_denylisted,
+    transfer::{AssetTransfer, is_non_fungible},
+    merkle,
+};
+
+#[derive(Accounts)]
+pub struct TakeBid<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(mut)]
+    pub bidder: SystemAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = seller
+    )]
+    pub seller_ata_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = mint_b,
+        associated_token::authority = seller
+    )]
+    pub seller_ata_b: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = mint_a,
+        associated_token::authority = bidder
+    )]
+    pub bidder_ata_a: Box<Account<'info, TokenAccount>>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_a: Box<Account<'info, MetadataAccount>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"edition",
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub master_edition_a: Option<Box<Account<'info, MasterEditionAccount>>>,
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"token_record",
+            seller_ata_a.key().as_ref(),
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub seller_token_record_a: Option<Box<Account<'info, TokenRecordAccount>>>,
+    #[account(mut)]
+    /// CHECK: we're checking this later
+    pub bidder_token_record_a: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = bid
+    )]
+    pub bid_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        close = bidder,
+        has_one = bidder,
+        has_one = mint_b,
+        seeds = [b"bid", bidder.key().as_ref(), mint_b.key().as_ref(), bid.merkle_root.as_ref()],
+        bump,
+    )]
+    pub bid: Box<Account<'info, Bid>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    /// CHECK: checked against mint_a later
+    pub mint_denylist: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection of mint_a later
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+
+    #[account(address = sysvar::instructions::id())]
+    /// CHECK: we don't need to check this
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> TakeBid<'info> {
+    pub fn take_bid(
+        &mut self,
+        proof: Vec<[u8; 32]>,
+        bumps: TakeBidBumps,
+    ) -> Result<()> {
+
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+        let collection_denylist = self.collection_denylist.as_ref().map(|denylist| denylist.to_account_info());
+        check_not_denylisted(&self.mint_a.key(), &self.metadata_a, &self.mint_denylist.to_account_info(), collection_denylist.as_ref())?;
+
+        //All deferred errors
+        let mint_a_key = self.mint_a.key();
+        let mint_b_key = self.mint_b.key();
+        let bidder_key = self.bidder.key();
+        let metadata_a_token_standard = self.metadata_a.token_standard.as_ref().unwrap();
+
+        // Bids can only be filled with NFTs
+        require!(is_non_fungible(metadata_a_token_standard), EscrowError::InvalidTokenStandard);
+
+        // Check that the mint is part of the list committed by the bidder
+        require!(
+            merkle::verify(&proof, self.bid.merkle_root, merkle::mint_leaf(mint_a_key.as_ref())),
+            EscrowError::InvalidMerkleProof
+        );
+
+        let master_edition = self.master_edition_a.as_ref().map(|master_edition| master_edition.to_account_info());
+        let seller_token_record = self.seller_token_record_a.as_ref().map(|token_record| token_record.to_account_info());
+        let bidder_token_record = self.bidder_token_record_a.to_account_info();
+
+        // Transfer the token from the seller to the bidder
+        AssetTransfer {
+            token: &self.seller_ata_a.to_account_info(),
+            token_owner: &self.seller.to_account_info(),
+            destination_token: &self.bidder_ata_a.to_account_info(),
+            destination_owner: &self.bidder.to_account_info(),
+            mint: &self.mint_a.to_account_info(),
+            metadata: &self.metadata_a,
+            master_edition: master_edition.as_ref(),
+            token_record: seller_token_record.as_ref(),
+            destination_token_record: Some(&bidder_token_record),
+            authority: &self.seller.to_account_info(),
+            payer: &self.seller.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+            sysvar_instructions: &self.sysvar_instructions.to_account_info(),
+            token_program: &self.token_program.to_account_info(),
+            associated_token_program: &self.associated_token_program.to_account_info(),
+            token_metadata_program: &self.token_metadata_program.to_account_info(),
+            amount: 1,
+        }.invoke()?;
+
+        // Release the payment to the seller and give the rent of the vault back to the bidder
+        let merkle_root = self.bid.merkle_root;
+        let seeds = &[
+            "bid".as_bytes(),
+            bidder_key.as_ref(),
+            mint_b_key.as_ref(),
+            merkle_root.as_ref(),
+            &[bumps.bid]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: self.bid_vault.to_account_info(),
+            to: self.seller_ata_b.to_account_info(),
+            authority: self.bid.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+        transfer(cpi_ctx, self.bid_vault.amount)?;
+
+        let cpi_accounts = CloseAccount {
+            account: self.bid_vault.to_account_info(),
+            destination: self.bidder.to_account_info(),
+            authority: self.bid.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+
+        close_account(cpi_ctx)?;
+
+        Ok(())
+    }
+}
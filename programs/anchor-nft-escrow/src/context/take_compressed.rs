@@ -0,0 +1,197 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, transfer},
+    associated_token::AssociatedToken
+};
+use mpl_bubblegum::{
+    instructions::{TransferCpi, TransferCpiAccounts, TransferInstructionArgs},
+    programs::{MPL_BUBBLEGUM_ID, SPL_ACCOUNT_COMPRESSION_ID, SPL_NOOP_ID},
+    utils::get_asset_id,
+};
+
+use crate::{
+    state::{Escrow, CompressedLeaf, Config},
+    errors::EscrowError,
+    denylist::check_compressed_not_denylisted,
+    events::Fill,
+    transfer::pay_referral,
+};
+
+#[derive(Accounts)]
+#[instruction(leaf: CompressedLeaf)]
+pub struct TakeCompressed<'info> {
+    #[account(mut)]
+    /// CHECK: we're checking the seeds
+    pub maker: UncheckedAccount<'info>,
+    #[account(mut, address = escrow.payer @ EscrowError::InvalidPayer)]
+    /// CHECK: we're checking the address
+    pub payer: UncheckedAccount<'info>,
+    #[account(address = escrow.payee(maker.key()) @ EscrowError::InvalidProceedsRecipient)]
+    /// CHECK: the maker, or the proceeds recipient set on the escrow
+    pub proceeds_recipient: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    /// CHECK: Bubblegum checks this against the merkle tree
+    pub tree_config: UncheckedAccount<'info>,
+    #[account(mut)]
+    /// CHECK: Bubblegum checks this against the leaf
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker
+    )]
+    pub taker_ata_b: Box<Account<'info, TokenAccount>>,
+    // Only needed for referred fills, the referrer is the owner of this token account
+    #[account(mut)]
+    pub referrer_ata_b: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = proceeds_recipient
+    )]
+    pub maker_ata_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        close = payer,
+        has_one = mint_b,
+        seeds = [b"escrow", maker.key().as_ref(), get_asset_id(&merkle_tree.key(), leaf.nonce).as_ref(), mint_b.key().as_ref()],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    /// CHECK: checked against the asset id later
+    pub asset_denylist: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection of the leaf later
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+
+    #[account(address = MPL_BUBBLEGUM_ID)]
+    /// CHECK: we're checking the address
+    pub bubblegum_program: UncheckedAccount<'info>,
+    #[account(address = SPL_NOOP_ID)]
+    /// CHECK: we're checking the address
+    pub log_wrapper: UncheckedAccount<'info>,
+    #[account(address = SPL_ACCOUNT_COMPRESSION_ID)]
+    /// CHECK: we're checking the address
+    pub compression_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> TakeCompressed<'info> {
+    // The metadata are the borsh-serialized MetadataArgs of the leaf, the remaining accounts are its proof
+    pub fn take_compressed(
+        &mut self,
+        leaf: CompressedLeaf,
+        metadata: &[u8],
+        proof: &[AccountInfo<'info>],
+        bumps: TakeCompressedBumps,
+    ) -> Result<()> {
+
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+        let metadata = leaf.metadata(metadata)?;
+        let collection_denylist = self.collection_denylist.as_ref().map(|denylist| denylist.to_account_info());
+        check_compressed_not_denylisted(&get_asset_id(&self.merkle_tree.key(), leaf.nonce), &metadata, &self.asset_denylist.to_account_info(), collection_denylist.as_ref())?;
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+
+        // Compressed NFTs are paid for in the same instruction, so there is no need for introspection
+        let price = self.escrow.current_price(Clock::get()?.unix_timestamp);
+
+        // The referral cut comes out of what the maker receives, next to the royalties of the leaf
+        let (referrer, referral_fee) = pay_referral(
+            &self.escrow,
+            &self.config,
+            metadata.seller_fee_basis_points,
+            price,
+            self.referrer_ata_b.as_deref(),
+            &self.taker_ata_b.to_account_info(),
+            &self.taker.to_account_info(),
+            &self.token_program.to_account_info(),
+        )?;
+
+        let cpi_accounts = Transfer {
+            from: self.taker_ata_b.to_account_info(),
+            to: self.maker_ata_b.to_account_info(),
+            authority: self.taker.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        transfer(cpi_ctx, price - referral_fee)?;
+
+        // Release the leaf to the taker, the escrow is its delegate in both listing modes
+        let asset_id = self.escrow.mint_a;
+        let mint_b_key = self.mint_b.key();
+        let maker_key = self.maker.key();
+
+        let seeds = &[
+            "escrow".as_bytes(),
+            maker_key.as_ref(),
+            asset_id.as_ref(),
+            mint_b_key.as_ref(),
+            &[bumps.escrow]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let proof = proof.iter().map(|node| (node, false, false)).collect::<Vec<_>>();
+
+        let program = &self.bubblegum_program.to_account_info();
+        let tree_config = &self.tree_config.to_account_info();
+        let escrow = &self.escrow.to_account_info();
+        let leaf_owner = match self.escrow.delegated {
+            true => self.maker.to_account_info(),
+            false => self.escrow.to_account_info(),
+        };
+        let new_leaf_owner = &self.taker.to_account_info();
+        let merkle_tree = &self.merkle_tree.to_account_info();
+        let log_wrapper = &self.log_wrapper.to_account_info();
+        let compression_program = &self.compression_program.to_account_info();
+        let system_program = &self.system_program.to_account_info();
+
+        let transfer_cpi = TransferCpi::new(
+            program,
+            TransferCpiAccounts {
+                tree_config,
+                leaf_owner: (&leaf_owner, false),
+                leaf_delegate: (escrow, true),
+                new_leaf_owner,
+                merkle_tree,
+                log_wrapper,
+                compression_program,
+                system_program,
+            },
+            TransferInstructionArgs {
+                root: leaf.root,
+                data_hash: leaf.data_hash,
+                creator_hash: leaf.creator_hash,
+                nonce: leaf.nonce,
+                index: leaf.index,
+            },
+        );
+
+        transfer_cpi.invoke_signed_with_remaining_accounts(signer_seeds, &proof)?;
+
+        emit!(Fill {
+            escrow: self.escrow.key(),
+            maker: self.maker.key(),
+            taker: self.taker.key(),
+            mint_a: asset_id,
+            mint_b: self.mint_b.key(),
+            price,
+            referrer,
+            referral_fee,
+            marketplace_fee: 0,
+        });
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,193 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, transfer},
+    associated_token::{AssociatedToken, get_associated_token_address}
+};
+
+use crate::{
+    state::{Escrow, Config},
+    errors::EscrowError,
+    denylist::check_asset_not_denylisted,
+    events::Fill,
+    mpl_core::{MPL_CORE_ID, TransferV1Cpi, load_asset},
+    transfer::pay_referral,
+};
+
+#[derive(Accounts)]
+pub struct TakeCore<'info> {
+    #[account(mut)]
+    /// CHECK: we're checking the seeds
+    pub maker: UncheckedAccount<'info>,
+    #[account(mut, address = escrow.payer @ EscrowError::InvalidPayer)]
+    /// CHECK: we're checking the address
+    pub payer: UncheckedAccount<'info>,
+    #[account(address = escrow.payee(maker.key()) @ EscrowError::InvalidProceedsRecipient)]
+    /// CHECK: the maker, or the proceeds recipient set on the escrow
+    pub proceeds_recipient: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    #[account(mut)]
+    /// CHECK: we're checking this against the Core program later
+    pub asset: UncheckedAccount<'info>,
+    /// CHECK: we're checking this against the asset later
+    pub collection: Option<UncheckedAccount<'info>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker
+    )]
+    pub taker_ata_b: Box<Account<'info, TokenAccount>>,
+    // Only needed for referred fills, the referrer is the owner of this token account
+    #[account(mut)]
+    pub referrer_ata_b: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = proceeds_recipient
+    )]
+    pub maker_ata_b: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        close = payer,
+        has_one = mint_b,
+        seeds = [b"escrow", maker.key().as_ref(), asset.key().as_ref(), mint_b.key().as_ref()],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    /// CHECK: checked against the asset later
+    pub asset_denylist: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection of the asset later
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+
+    #[account(address = MPL_CORE_ID)]
+    /// CHECK: we're checking the address
+    pub core_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> TakeCore<'info> {
+    // The remaining accounts are the mint_b token accounts of the royalty creators, in the order of the Royalties plugin
+    pub fn take_core(
+        &mut self,
+        creator_atas: &[AccountInfo<'info>],
+        bumps: TakeCoreBumps,
+    ) -> Result<()> {
+
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+
+        let collection = self.collection.as_ref().map(|collection| collection.to_account_info());
+        let asset = load_asset(&self.asset.to_account_info(), collection.as_ref())?;
+        let collection_denylist = self.collection_denylist.as_ref().map(|denylist| denylist.to_account_info());
+        check_asset_not_denylisted(&self.asset.key(), asset.collection.as_ref(), &self.asset_denylist.to_account_info(), collection_denylist.as_ref())?;
+
+        // Core assets are paid for in the same instruction, so there is no need for introspection
+        let price = self.escrow.current_price(Clock::get()?.unix_timestamp);
+        let mut maker_proceeds = price;
+
+        // Royalties and the referral cut both come out of what the maker receives
+        let royalty_bps = asset.plugins.royalties.as_ref().map_or(0, |royalties| royalties.basis_points);
+
+        let (referrer, referral_fee) = pay_referral(
+            &self.escrow,
+            &self.config,
+            royalty_bps,
+            price,
+            self.referrer_ata_b.as_deref(),
+            &self.taker_ata_b.to_account_info(),
+            &self.taker.to_account_info(),
+            &self.token_program.to_account_info(),
+        )?;
+        maker_proceeds -= referral_fee;
+
+        // Royalties come out of the price and are split between the creators by their percentage
+        if let Some(royalties) = asset.plugins.royalties {
+            require_eq!(creator_atas.len(), royalties.creators.len(), EscrowError::InvalidCreator);
+
+            let royalty = (price as u128 * royalties.basis_points as u128 / 10_000) as u64;
+
+            for (creator, creator_ata) in royalties.creators.iter().zip(creator_atas.iter()) {
+                require_keys_eq!(
+                    creator_ata.key(),
+                    get_associated_token_address(&creator.address, &self.mint_b.key()),
+                    EscrowError::InvalidCreator
+                );
+
+                let amount = (royalty as u128 * creator.percentage as u128 / 100) as u64;
+                if amount == 0 {
+                    continue;
+                }
+
+                let cpi_accounts = Transfer {
+                    from: self.taker_ata_b.to_account_info(),
+                    to: creator_ata.clone(),
+                    authority: self.taker.to_account_info(),
+                };
+                let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+                transfer(cpi_ctx, amount)?;
+
+                maker_proceeds -= amount;
+            }
+        }
+
+        let cpi_accounts = Transfer {
+            from: self.taker_ata_b.to_account_info(),
+            to: self.maker_ata_b.to_account_info(),
+            authority: self.taker.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        transfer(cpi_ctx, maker_proceeds)?;
+
+        // Release the asset to the taker
+        let asset_key = self.asset.key();
+        let mint_b_key = self.mint_b.key();
+        let maker_key = self.maker.key();
+
+        let seeds = &[
+            "escrow".as_bytes(),
+            maker_key.as_ref(),
+            asset_key.as_ref(),
+            mint_b_key.as_ref(),
+            &[bumps.escrow]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        TransferV1Cpi {
+            core_program: &self.core_program.to_account_info(),
+            asset: &self.asset.to_account_info(),
+            collection: collection.as_ref(),
+            payer: &self.taker.to_account_info(),
+            authority: &self.escrow.to_account_info(),
+            new_owner: &self.taker.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+        }.invoke_signed(signer_seeds)?;
+
+        emit!(Fill {
+            escrow: self.escrow.key(),
+            maker: self.maker.key(),
+            taker: self.taker.key(),
+            mint_a: self.asset.key(),
+            mint_b: self.mint_b.key(),
+            price,
+            referrer,
+            referral_fee,
+            marketplace_fee: 0,
+        });
+
+        Ok(())
+    }
+}
\ No newline at end of file
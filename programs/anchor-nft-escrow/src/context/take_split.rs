@@ -0,0 +1,258 @@
+use solana_program::*;
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, TokenAccount, Token, Transfer, transfer},
+    metadata::{MetadataAccount, MasterEditionAccount, Metadata, TokenRecordAccount},
+    associated_token::{AssociatedToken, Create, create_idempotent, get_associated_token_address}
+};
+
+use crate::{
+    state::{Escrow, Payees, Config, Marketplace},
+    errors::EscrowError,
+    denylist::check_not_denylisted,
+    marketplace::{check_marketplace, marketplace_seed},
+    events::Fill,
+    transfer::{VaultTransfer, close_vault, pay_referral, pay_marketplace_fee},
+};
+
+#[derive(Accounts)]
+pub struct TakeSplit<'info> {
+    #[account(mut)]
+    /// CHECK: we're checking the seeds
+    pub maker: UncheckedAccount<'info>,
+    #[account(mut, address = escrow.payer @ EscrowError::InvalidPayer)]
+    /// CHECK: we're checking the address
+    pub payer: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    pub mint_a: Box<Account<'info, Mint>>,
+    pub mint_b: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref()
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub metadata_a: Box<Account<'info, MetadataAccount>>,
+    #[account(
+        seeds = [
+            b"metadata",
+            token_metadata_program.key().as_ref(),
+            mint_a.key().as_ref(),
+            b"edition",
+            ],
+        seeds::program = token_metadata_program.key(),
+        bump,
+    )]
+    pub master_edition_a: Option<Box<Account<'info, MasterEditionAccount>>>,
+    #[account(mut)]
+    pub vault_token_record_a: Option<Box<Account<'info, TokenRecordAccount>>>,
+    #[account(mut)]
+    /// CHECK: we're checking this later
+    pub taker_token_record_a: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker
+    )]
+    pub taker_ata_a: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker
+    )]
+    pub taker_ata_b: Box<Account<'info, TokenAccount>>,
+    // Only needed for referred fills, the referrer is the owner of this token account
+    #[account(mut)]
+    pub referrer_ata_b: Option<Box<Account<'info, TokenAccount>>>,
+    #[account(mut)]
+    pub treasury_ata_b: Option<Box<Account<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        close = payer,
+        has_one = escrow,
+        seeds = [b"payees", escrow.key().as_ref()],
+        bump,
+    )]
+    pub payees: Box<Account<'info, Payees>>,
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"escrow", maker.key().as_ref(), mint_a.key().as_ref(), mint_b.key().as_ref(), marketplace_seed(&escrow.marketplace)],
+        bump,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, Config>>,
+    /// CHECK: checked against mint_a later
+    pub mint_denylist: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection of mint_a later
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+    pub marketplace: Option<Box<Account<'info, Marketplace>>>,
+
+    #[account(address = sysvar::instructions::id())]
+    /// CHECK: we don't need to check this
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> TakeSplit<'info> {
+    // The remaining accounts are the wallet and the mint_b token account of every payee, in the order of the Payees account.
+    // The token accounts are created for the payees that don't have one yet
+    pub fn take_split(
+        &mut self,
+        payee_accounts: &[AccountInfo<'info>],
+        bumps: TakeSplitBumps,
+    ) -> Result<()> {
+
+        require!(!self.config.paused, EscrowError::ProgramPaused);
+        require!(!self.escrow.frozen, EscrowError::EscrowFrozen);
+        let collection_denylist = self.collection_denylist.as_ref().map(|denylist| denylist.to_account_info());
+        check_not_denylisted(&self.mint_a.key(), &self.metadata_a, &self.mint_denylist.to_account_info(), collection_denylist.as_ref())?;
+        check_marketplace(&self.escrow, self.marketplace.as_deref(), &self.metadata_a)?;
+
+        require!(self.escrow.split, EscrowError::InvalidListingMode);
+        require_eq!(payee_accounts.len(), self.payees.payees.len() * 2, EscrowError::InvalidPayee);
+
+        let price = self.escrow.current_price(Clock::get()?.unix_timestamp);
+
+        // The referral and marketplace cuts come out of what the payees receive
+        let (referrer, referral_fee) = pay_referral(
+            &self.escrow,
+            &self.config,
+            self.metadata_a.seller_fee_basis_points,
+            price,
+            self.referrer_ata_b.as_deref(),
+            &self.taker_ata_b.to_account_info(),
+            &self.taker.to_account_info(),
+            &self.token_program.to_account_info(),
+        )?;
+
+        let marketplace_fee = pay_marketplace_fee(
+            &self.escrow,
+            self.marketplace.as_deref(),
+            price,
+            self.treasury_ata_b.as_deref(),
+            &self.taker_ata_b.to_account_info(),
+            &self.taker.to_account_info(),
+            &self.token_program.to_account_info(),
+        )?;
+
+        // Pay every payee its share of the rest of the price
+        let amounts = self.payees.amounts(price - referral_fee - marketplace_fee);
+
+        for ((payee, accounts), amount) in self.payees.payees.iter().zip(payee_accounts.chunks(2)).zip(amounts) {
+            let (wallet, payee_ata) = (&accounts[0], &accounts[1]);
+            require_keys_eq!(wallet.key(), payee.address, EscrowError::InvalidPayee);
+            require_keys_eq!(payee_ata.key(), get_associated_token_address(&payee.address, &self.mint_b.key()), EscrowError::InvalidPayee);
+
+            let cpi_accounts = Create {
+                payer: self.taker.to_account_info(),
+                associated_token: payee_ata.clone(),
+                authority: wallet.clone(),
+                mint: self.mint_b.to_account_info(),
+                system_program: self.system_program.to_account_info(),
+                token_program: self.token_program.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(self.associated_token_program.to_account_info(), cpi_accounts);
+
+            create_idempotent(cpi_ctx)?;
+
+            if amount == 0 {
+                continue;
+            }
+
+            let cpi_accounts = Transfer {
+                from: self.taker_ata_b.to_account_info(),
+                to: payee_ata.clone(),
+                authority: self.taker.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+            transfer(cpi_ctx, amount)?;
+        }
+
+        // Release the asset to the taker
+        let mint_a_key = self.mint_a.key();
+        let mint_b_key = self.mint_b.key();
+        let maker_key = self.maker.key();
+        let marketplace_key = self.escrow.marketplace;
+
+        let seeds = &[
+            "escrow".as_bytes(),
+            maker_key.as_ref(),
+            mint_a_key.as_ref(),
+            mint_b_key.as_ref(),
+            marketplace_seed(&marketplace_key),
+            &[bumps.escrow]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let master_edition_a = self.master_edition_a.as_ref().map(|master_edition| master_edition.to_account_info());
+        let vault_token_record_a = self.vault_token_record_a.as_ref().map(|token_record| token_record.to_account_info());
+
+        VaultTransfer {
+            escrow: &self.escrow.to_account_info(),
+            owner: &self.escrow.to_account_info(),
+            vault: &self.vault,
+            destination_ata: &self.taker_ata_a.to_account_info(),
+            destination_owner: &self.taker.to_account_info(),
+            mint: &self.mint_a.to_account_info(),
+            metadata: &self.metadata_a,
+            master_edition: master_edition_a.as_ref(),
+            vault_token_record: vault_token_record_a.as_ref(),
+            destination_token_record: &self.taker_token_record_a.to_account_info(),
+            payer: &self.taker.to_account_info(),
+            system_program: &self.system_program.to_account_info(),
+            sysvar_instructions: &self.sysvar_instructions.to_account_info(),
+            token_program: &self.token_program.to_account_info(),
+            associated_token_program: &self.associated_token_program.to_account_info(),
+            token_metadata_program: &self.token_metadata_program.to_account_info(),
+        }.invoke_signed(signer_seeds)?;
+
+        close_vault(
+            &self.vault.to_account_info(),
+            &self.metadata_a,
+            &self.payer.to_account_info(),
+            &self.escrow.to_account_info(),
+            &self.token_program.to_account_info(),
+            signer_seeds,
+        )?;
+
+        emit!(Fill {
+            escrow: self.escrow.key(),
+            maker: self.maker.key(),
+            taker: self.taker.key(),
+            mint_a: self.mint_a.key(),
+            mint_b: self.mint_b.key(),
+            price,
+            referrer,
+            referral_fee,
+            marketplace_fee,
+        });
+
+        Ok(())
+    }
+}
\ No newline at end of file
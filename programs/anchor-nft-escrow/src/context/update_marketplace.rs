@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    state::Marketplace,
+    errors::EscrowError,
+};
+
+#[derive(Accounts)]
+pub struct UpdateMarketplace<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"marketplace", marketplace.id.as_ref()],
+        bump,
+    )]
+    pub marketplace: Box<Account<'info, Marketplace>>,
+    // Fees are paid to the token accounts of this account
+    /// CHECK: any account can receive the fees, only its address is stored
+    pub treasury: UncheckedAccount<'info>,
+    // Only needed to hand the marketplace over, the new authority signs so a mistyped key can't lock it
+    pub new_authority: Option<Signer<'info>>,
+}
+
+impl<'info> UpdateMarketplace<'info> {
+    // Existing listings follow the new rules too, a collection that gets removed can't be taken anymore.
+    // The fee is the exception, listings keep paying the fee they were made with
+    pub fn update_marketplace(
+        &mut self,
+        curated: bool,
+        allowed_collections: Vec<Pubkey>,
+        allowed_payment_mints: Vec<Pubkey>,
+        fee_bps: u16,
+        requires_signoff: bool,
+    ) -> Result<()> {
+
+        require!(allowed_collections.len() <= Marketplace::MAX_COLLECTIONS, EscrowError::TooManyCollections);
+        require!(allowed_payment_mints.len() <= Marketplace::MAX_PAYMENT_MINTS, EscrowError::TooManyPaymentMints);
+        require!(fee_bps <= 10_000, EscrowError::InvalidMarketplaceFee);
+
+        self.marketplace.curated = curated;
+        self.marketplace.allowed_collections = allowed_collections;
+        self.marketplace.allowed_payment_mints = allowed_payment_mints;
+        self.marketplace.fee_bps = fee_bps;
+        self.marketplace.treasury = self.treasury.key();
+        self.marketplace.requires_signoff = requires_signoff;
+
+        if let Some(new_authority) = &self.new_authority {
+            self.marketplace.authority = new_authority.key();
+        }
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,63 @@
+use anchor_lang::prelude::*;
+use anchor_spl::metadata::MetadataAccount;
+use mpl_bubblegum::types::MetadataArgs;
+
+use crate::{
+    errors::EscrowError,
+    marketplace::verified_collection,
+};
+
+pub fn denylist_address(key: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"denylist", key.as_ref()], &crate::ID).0
+}
+
+fn check_entry(key: &Pubkey, denylist: &AccountInfo) -> Result<()> {
+    require_keys_eq!(denylist.key(), denylist_address(key), EscrowError::InvalidDenylistEntry);
+    require!(denylist.data_is_empty(), EscrowError::AssetDenylisted);
+
+    Ok(())
+}
+
+// An asset is flagged when the admin created a denylist entry for its mint, or for its verified collection.
+// Entries are program accounts, so an empty account at the right address means the asset isn't flagged.
+// Every make and fill passes the entry of the asset, and the one of its collection when it has a verified one
+pub fn check_not_denylisted(
+    mint: &Pubkey,
+    metadata: &MetadataAccount,
+    mint_denylist: &AccountInfo,
+    collection_denylist: Option<&AccountInfo>,
+) -> Result<()> {
+
+    check_asset_not_denylisted(mint, verified_collection(metadata).as_ref(), mint_denylist, collection_denylist)
+}
+
+// Compressed assets are keyed on the asset id and on the verified collection of the leaf's metadata
+pub fn check_compressed_not_denylisted(
+    asset: &Pubkey,
+    metadata: &MetadataArgs,
+    asset_denylist: &AccountInfo,
+    collection_denylist: Option<&AccountInfo>,
+) -> Result<()> {
+
+    let collection = metadata.collection.as_ref().filter(|collection| collection.verified).map(|collection| collection.key);
+
+    check_asset_not_denylisted(asset, collection.as_ref(), asset_denylist, collection_denylist)
+}
+
+// Core and compressed assets have no metadata account, their entries are keyed on the asset id and on the
+// collection the asset program vouches for
+pub fn check_asset_not_denylisted(
+    asset: &Pubkey,
+    collection: Option<&Pubkey>,
+    asset_denylist: &AccountInfo,
+    collection_denylist: Option<&AccountInfo>,
+) -> Result<()> {
+
+    check_entry(asset, asset_denylist)?;
+
+    if let Some(collection) = collection {
+        check_entry(collection, collection_denylist.ok_or(EscrowError::InvalidDenylistEntry)?)?;
+    }
+
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,41 @@
+use solana_program::{ed25519_program, instruction::Instruction, pubkey::Pubkey};
+
+// Header of the Ed25519 program data: the signature count and a padding byte
+const HEADER_LEN: usize = 2;
+// Seven u16 offsets describe where the signature, the public key and the message are
+const OFFSETS_LEN: usize = 14;
+const PUBKEY_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+
+fn read_u16(data: &[u8], offset: usize) -> Option<usize> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+}
+
+// The Ed25519 program already rejected the transaction if the signature was wrong,
+// so we only need to make sure that it checked a single signature of `message` by `signer`
+// and that everything it checked lives inside its own instruction data
+pub fn verify(ix: &Instruction, signer: &Pubkey, message: &[u8]) -> bool {
+    if ix.program_id != ed25519_program::id() || ix.data.len() < HEADER_LEN + OFFSETS_LEN || ix.data[0] != 1 {
+        return false;
+    }
+
+    let data = &ix.data;
+    let offsets: Option<Vec<usize>> = (0..7).map(|i| read_u16(data, HEADER_LEN + i * 2)).collect();
+    let Some(offsets) = offsets else {
+        return false;
+    };
+    let [signature_offset, signature_ix, pubkey_offset, pubkey_ix, message_offset, message_size, message_ix] = offsets[..] else {
+        return false;
+    };
+
+    // u16::MAX means the data is part of the Ed25519 instruction itself
+    let current = u16::MAX as usize;
+    if signature_ix != current || pubkey_ix != current || message_ix != current {
+        return false;
+    }
+
+    data.get(signature_offset..signature_offset + SIGNATURE_LEN).is_some()
+        && data.get(pubkey_offset..pubkey_offset + PUBKEY_LEN) == Some(signer.as_ref())
+        && data.get(message_offset..message_offset + message_size) == Some(message)
+}
\ No newline at end of file
@@ -9,7 +9,147 @@ pub enum EscrowError {
     #[msg("The Token Record Account doesn't match.")]
     TokenRecordAccountDoesNotMatch,
     #[msg("The Amount of Token sent to the Maker doesn't match.")]
-    InvalidAmount
+    InvalidAmount,
+    #[msg("The Token Standard is not supported for this instruction.")]
+    InvalidTokenStandard,
+    #[msg("The Merkle Proof doesn't match the Merkle Root.")]
+    InvalidMerkleProof,
+    #[msg("The Auction parameters are invalid.")]
+    InvalidAuctionParameters,
+    #[msg("The Escrow is not an Auction.")]
+    NotAnAuction,
+    #[msg("The Escrow is an Auction and can only be settled.")]
+    AuctionInProgress,
+    #[msg("The Auction has already ended.")]
+    AuctionEnded,
+    #[msg("The Auction has not ended yet.")]
+    AuctionNotEnded,
+    #[msg("The Auction already has bids.")]
+    AuctionHasBids,
+    #[msg("The Bid is too low.")]
+    BidTooLow,
+    #[msg("The Bid doesn't beat the highest one by the minimum increment.")]
+    BidBelowMinimumIncrement,
+    #[msg("The Bidder doesn't match the Auction.")]
+    InvalidBidder,
+    #[msg("The listing mode of the Escrow doesn't support this instruction.")]
+    InvalidListingMode,
+    #[msg("The Core Asset is invalid.")]
+    InvalidCoreAsset,
+    #[msg("The Core Collection doesn't match the Asset.")]
+    InvalidCoreCollection,
+    #[msg("The Core Asset is frozen.")]
+    AssetFrozen,
+    #[msg("The Creator accounts don't match the Royalties.")]
+    InvalidCreator,
+    #[msg("The Rental parameters are invalid.")]
+    InvalidRentalParameters,
+    #[msg("The Escrow is not a Rental.")]
+    NotARental,
+    #[msg("The Asset is already rented.")]
+    AssetRented,
+    #[msg("The Asset is not rented.")]
+    AssetNotRented,
+    #[msg("The Rental has not ended yet.")]
+    RentalNotEnded,
+    #[msg("The Renter doesn't match the Rental.")]
+    InvalidRenter,
+    #[msg("The Loan parameters are invalid.")]
+    InvalidLoanParameters,
+    #[msg("The Loan is already funded.")]
+    LoanFunded,
+    #[msg("The Loan is not funded.")]
+    LoanNotFunded,
+    #[msg("The Loan is past its deadline.")]
+    LoanExpired,
+    #[msg("The Loan has not reached its deadline yet.")]
+    LoanNotExpired,
+    #[msg("The Lender doesn't match the Loan.")]
+    InvalidLender,
+    #[msg("The payment accounts don't match the Loan currency.")]
+    InvalidPaymentAccounts,
+    #[msg("The Arbitration parameters are invalid.")]
+    InvalidArbitrationParameters,
+    #[msg("The Escrow is not arbitrated.")]
+    NotArbitrated,
+    #[msg("The Payment is already deposited.")]
+    PaymentDeposited,
+    #[msg("The Payment is not deposited.")]
+    PaymentNotDeposited,
+    #[msg("The Escrow is disputed.")]
+    EscrowDisputed,
+    #[msg("The Escrow is not disputed.")]
+    EscrowNotDisputed,
+    #[msg("The Arbiter doesn't match the Escrow.")]
+    InvalidArbiter,
+    #[msg("The Signer is not a party of the Escrow.")]
+    InvalidParty,
+    #[msg("The Escrow doesn't take payment deposits.")]
+    DepositNotAllowed,
+    #[msg("The Confirmation parameters are invalid.")]
+    InvalidConfirmationParameters,
+    #[msg("The Escrow is not two-sided.")]
+    NotTwoSided,
+    #[msg("The Party already confirmed.")]
+    AlreadyConfirmed,
+    #[msg("The Escrow is still waiting for a confirmation.")]
+    ConfirmationPending,
+    #[msg("The Order is expired.")]
+    OrderExpired,
+    #[msg("The Order is not signed by the Maker.")]
+    InvalidOrderSignature,
+    #[msg("The Order is already filled.")]
+    OrderFilled,
+    #[msg("The Order is cancelled.")]
+    OrderCancelled,
+    #[msg("The Nonce is invalid.")]
+    InvalidNonce,
+    #[msg("This instruction can't be called through CPI.")]
+    CpiNotSupported,
+    #[msg("The Proceeds Recipient doesn't match the Escrow.")]
+    InvalidProceedsRecipient,
+    #[msg("The Payees are invalid.")]
+    InvalidPayees,
+    #[msg("The Payee doesn't match the Escrow.")]
+    InvalidPayee,
+    #[msg("The Referrer is invalid.")]
+    InvalidReferrer,
+    #[msg("The fees exceed the price.")]
+    FeesExceedPrice,
+    #[msg("Trading is paused.")]
+    ProgramPaused,
+    #[msg("The Escrow is frozen.")]
+    EscrowFrozen,
+    #[msg("The Admin is invalid.")]
+    InvalidAdmin,
+    #[msg("The Asset is denylisted.")]
+    AssetDenylisted,
+    #[msg("The Denylist Entry doesn't match the Asset.")]
+    InvalidDenylistEntry,
+    #[msg("The Marketplace doesn't match the Escrow.")]
+    InvalidMarketplace,
+    #[msg("The Marketplace can't hold that many Collections.")]
+    TooManyCollections,
+    #[msg("The Collection isn't allowed on this Marketplace.")]
+    CollectionNotAllowed,
+    #[msg("The Marketplace can't hold that many Payment Mints.")]
+    TooManyPaymentMints,
+    #[msg("The Payment Mint isn't allowed on this Marketplace.")]
+    PaymentMintNotAllowed,
+    #[msg("The Marketplace Fee is invalid.")]
+    InvalidMarketplaceFee,
+    #[msg("The Treasury doesn't match the Marketplace.")]
+    InvalidTreasury,
+    #[msg("The Marketplace Authority has to sign off the listing.")]
+    MarketplaceSignoffRequired,
+    #[msg("The computation overflowed.")]
+    Overflow,
+    #[msg("The Payer doesn't match the Escrow.")]
+    InvalidPayer,
+    #[msg("The Loan of the Escrow is missing.")]
+    MissingLoan,
+    #[msg("The Metadata doesn't match the Leaf.")]
+    InvalidLeafMetadata
 }
 
 #[error_code]
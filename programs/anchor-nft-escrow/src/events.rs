@@ -0,0 +1,25 @@
+use anchor_lang::prelude::*;
+
+#[event]
+pub struct Fill {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub price: u64,
+    pub referrer: Option<Pubkey>,
+    pub referral_fee: u64,
+    pub marketplace_fee: u64,
+}
+
+#[event]
+pub struct OrderFill {
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub maker_amount: u64,
+    pub price: u64,
+    pub nonce: u64,
+}
\ No newline at end of file
@@ -2,9 +2,17 @@ use anchor_lang::prelude::*;
 
 pub mod errors;
 pub mod state;
+pub mod events;
+pub mod merkle;
+pub mod mpl_core;
+pub mod ed25519;
+pub mod transfer;
+pub mod denylist;
+pub mod marketplace;
 
 pub mod context;
 pub use context::*;
+use state::{DutchAuction, Arbitration, Resolution, Order, CompressedLeaf, Payee};
 
 declare_id!("2VYDrwoKRKNgvmQo3DHfcLfQFAuijjNEAY9ZoXfv8GfZ");
 
@@ -12,8 +20,28 @@ declare_id!("2VYDrwoKRKNgvmQo3DHfcLfQFAuijjNEAY9ZoXfv8GfZ");
 pub mod nft_escrow {
     use super::*;
 
-    pub fn make(ctx: Context<Make>, maker_amount: u64, taker_amount: u64) -> Result<()> {
-        ctx.accounts.make(maker_amount, taker_amount)
+    pub fn make(ctx: Context<Make>, maker_amount: u64, taker_amount: u64, dutch_auction: Option<DutchAuction>, arbitration: Option<Arbitration>) -> Result<()> {
+        ctx.accounts.make(maker_amount, taker_amount, dutch_auction, arbitration)
+    }
+
+    pub fn make_auction(ctx: Context<Make>, maker_amount: u64, reserve_price: u64, end_time: i64, extension_window: i64, min_bid_increment_bps: u16) -> Result<()> {
+        ctx.accounts.make_auction(maker_amount, reserve_price, end_time, extension_window, min_bid_increment_bps)
+    }
+
+    pub fn make_rental(ctx: Context<Make>, fee: u64, duration: i64) -> Result<()> {
+        ctx.accounts.make_rental(fee, duration)
+    }
+
+    pub fn make_two_sided(ctx: Context<Make>, maker_amount: u64, taker_amount: u64, confirmation_window: i64) -> Result<()> {
+        ctx.accounts.make_two_sided(maker_amount, taker_amount, confirmation_window)
+    }
+
+    pub fn make_split(ctx: Context<MakeSplit>, maker_amount: u64, taker_amount: u64, payees: Vec<Payee>) -> Result<()> {
+        ctx.accounts.make_split(maker_amount, taker_amount, payees)
+    }
+
+    pub fn make_delegated(ctx: Context<MakeDelegated>, taker_amount: u64) -> Result<()> {
+        ctx.accounts.make_delegated(taker_amount, ctx.bumps)
     }
 
     pub fn take_from_escrow(ctx: Context<Take>) -> Result<()> {
@@ -24,7 +52,171 @@ pub mod nft_escrow {
         ctx.accounts.taker_to_maker(amount)
     }
 
+    pub fn take_atomic(ctx: Context<TakeAtomic>) -> Result<()> {
+        ctx.accounts.take_atomic(ctx.bumps)
+    }
+
+    pub fn take_split<'info>(ctx: Context<'_, '_, '_, 'info, TakeSplit<'info>>) -> Result<()> {
+        ctx.accounts.take_split(ctx.remaining_accounts, ctx.bumps)
+    }
+
+    pub fn set_referral_fee(ctx: Context<SetReferralFee>, referral_bps: Option<u16>) -> Result<()> {
+        ctx.accounts.set_referral_fee(referral_bps)
+    }
+
+    pub fn set_default_referral_fee(ctx: Context<SetDefaultReferralFee>, referral_bps: u16) -> Result<()> {
+        ctx.accounts.set_default_referral_fee(referral_bps)
+    }
+
+    pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+        ctx.accounts.initialize_config()
+    }
+
+    pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
+        ctx.accounts.set_paused(paused)
+    }
+
+    pub fn set_escrow_frozen(ctx: Context<SetEscrowFrozen>, frozen: bool) -> Result<()> {
+        ctx.accounts.set_escrow_frozen(frozen)
+    }
+
+    pub fn add_to_denylist(ctx: Context<AddToDenylist>, key: Pubkey) -> Result<()> {
+        ctx.accounts.add_to_denylist(key)
+    }
+
+    pub fn remove_from_denylist(ctx: Context<RemoveFromDenylist>) -> Result<()> {
+        ctx.accounts.remove_from_denylist()
+    }
+
+    pub fn create_marketplace(ctx: Context<CreateMarketplace>, id: Pubkey, curated: bool, allowed_collections: Vec<Pubkey>, allowed_payment_mints: Vec<Pubkey>, fee_bps: u16, requires_signoff: bool) -> Result<()> {
+        ctx.accounts.create_marketplace(id, curated, allowed_collections, allowed_payment_mints, fee_bps, requires_signoff)
+    }
+
+    pub fn update_marketplace(ctx: Context<UpdateMarketplace>, curated: bool, allowed_collections: Vec<Pubkey>, allowed_payment_mints: Vec<Pubkey>, fee_bps: u16, requires_signoff: bool) -> Result<()> {
+        ctx.accounts.update_marketplace(curated, allowed_collections, allowed_payment_mints, fee_bps, requires_signoff)
+    }
+
     pub fn close(ctx: Context<Close>) -> Result<()> {
         ctx.accounts.close(ctx.bumps)
     }
+
+    pub fn close_delegated(ctx: Context<CloseDelegated>) -> Result<()> {
+        ctx.accounts.close_delegated(ctx.bumps)
+    }
+
+    pub fn place_bid(ctx: Context<PlaceBid>, amount: u64) -> Result<()> {
+        ctx.accounts.place_bid(amount, ctx.bumps)
+    }
+
+    pub fn settle(ctx: Context<Settle>) -> Result<()> {
+        ctx.accounts.settle(ctx.bumps)
+    }
+
+    pub fn rent_asset(ctx: Context<RentAsset>) -> Result<()> {
+        ctx.accounts.rent_asset(ctx.bumps)
+    }
+
+    pub fn reclaim(ctx: Context<Reclaim>) -> Result<()> {
+        ctx.accounts.reclaim(ctx.bumps)
+    }
+
+    pub fn make_loan(ctx: Context<MakeLoan>, maker_amount: u64, principal: u64, apr_bps: u16, duration: i64) -> Result<()> {
+        ctx.accounts.make_loan(maker_amount, principal, apr_bps, duration)
+    }
+
+    pub fn fund_loan(ctx: Context<FundLoan>) -> Result<()> {
+        ctx.accounts.fund_loan()
+    }
+
+    pub fn close_loan(ctx: Context<CloseLoan>) -> Result<()> {
+        ctx.accounts.close_loan(ctx.bumps)
+    }
+
+    pub fn foreclose(ctx: Context<Foreclose>) -> Result<()> {
+        ctx.accounts.foreclose(ctx.bumps)
+    }
+
+    pub fn deposit_payment(ctx: Context<DepositPayment>) -> Result<()> {
+        ctx.accounts.deposit_payment()
+    }
+
+    pub fn dispute(ctx: Context<Dispute>) -> Result<()> {
+        ctx.accounts.dispute()
+    }
+
+    pub fn release(ctx: Context<Resolve>) -> Result<()> {
+        ctx.accounts.release(ctx.bumps)
+    }
+
+    pub fn resolve(ctx: Context<Resolve>, resolution: Resolution) -> Result<()> {
+        ctx.accounts.resolve(resolution, ctx.bumps)
+    }
+
+    pub fn confirm(ctx: Context<Confirm>) -> Result<()> {
+        ctx.accounts.confirm()
+    }
+
+    pub fn settle_confirmed(ctx: Context<Resolve>) -> Result<()> {
+        ctx.accounts.settle_confirmed(ctx.bumps)
+    }
+
+    pub fn fill_order(ctx: Context<FillOrder>, order: Order) -> Result<()> {
+        ctx.accounts.fill_order(order, ctx.bumps)
+    }
+
+    pub fn cancel_order(ctx: Context<CancelOrder>, _order: Order) -> Result<()> {
+        ctx.accounts.cancel_order()
+    }
+
+    pub fn increment_nonce(ctx: Context<IncrementNonce>, min_nonce: u64) -> Result<()> {
+        ctx.accounts.increment_nonce(min_nonce)
+    }
+
+    pub fn make_bid(ctx: Context<MakeBid>, amount: u64, merkle_root: [u8; 32]) -> Result<()> {
+        ctx.accounts.make_bid(amount, merkle_root)
+    }
+
+    pub fn take_bid(ctx: Context<TakeBid>, proof: Vec<[u8; 32]>) -> Result<()> {
+        ctx.accounts.take_bid(proof, ctx.bumps)
+    }
+
+    pub fn cancel_bid(ctx: Context<CancelBid>) -> Result<()> {
+        ctx.accounts.cancel_bid(ctx.bumps)
+    }
+
+    pub fn make_offer(ctx: Context<MakeOffer>, amount: u64) -> Result<()> {
+        ctx.accounts.make_offer(amount)
+    }
+
+    pub fn accept_offer(ctx: Context<AcceptOffer>) -> Result<()> {
+        ctx.accounts.accept_offer(ctx.bumps)
+    }
+
+    pub fn cancel_offer(ctx: Context<CancelOffer>) -> Result<()> {
+        ctx.accounts.cancel_offer(ctx.bumps)
+    }
+
+    pub fn make_core(ctx: Context<MakeCore>, taker_amount: u64) -> Result<()> {
+        ctx.accounts.make_core(taker_amount)
+    }
+
+    pub fn take_core<'info>(ctx: Context<'_, '_, '_, 'info, TakeCore<'info>>) -> Result<()> {
+        ctx.accounts.take_core(ctx.remaining_accounts, ctx.bumps)
+    }
+
+    pub fn close_core(ctx: Context<CloseCore>) -> Result<()> {
+        ctx.accounts.close_core(ctx.bumps)
+    }
+
+    pub fn make_compressed<'info>(ctx: Context<'_, '_, '_, 'info, MakeCompressed<'info>>, leaf: CompressedLeaf, metadata: Vec<u8>, taker_amount: u64, delegated: bool) -> Result<()> {
+        ctx.accounts.make_compressed(leaf, &metadata, taker_amount, delegated, ctx.remaining_accounts)
+    }
+
+    pub fn take_compressed<'info>(ctx: Context<'_, '_, '_, 'info, TakeCompressed<'info>>, leaf: CompressedLeaf, metadata: Vec<u8>) -> Result<()> {
+        ctx.accounts.take_compressed(leaf, &metadata, ctx.remaining_accounts, ctx.bumps)
+    }
+
+    pub fn close_compressed<'info>(ctx: Context<'_, '_, '_, 'info, CloseCompressed<'info>>, leaf: CompressedLeaf) -> Result<()> {
+        ctx.accounts.close_compressed(leaf, ctx.remaining_accounts, ctx.bumps)
+    }
 }
@@ -0,0 +1,86 @@
+use anchor_lang::prelude::*;
+use anchor_spl::metadata::MetadataAccount;
+
+use crate::{
+    state::{Escrow, Marketplace},
+    errors::EscrowError,
+};
+
+// Listings made on a marketplace have its key as the last seed of their escrow, so every tenant
+// has its own listing of an asset. The seed is empty for the others, which keeps their address.
+// Their fills pass the marketplace, and the token account of its treasury when it charges a fee,
+// the other listings leave both out
+pub fn marketplace_seed(marketplace: &Option<Pubkey>) -> &[u8] {
+    marketplace.as_ref().map_or(&[], |marketplace| marketplace.as_ref())
+}
+
+// Anyone can claim a collection, it only counts once it's verified by the collection authority
+pub fn verified_collection(metadata: &MetadataAccount) -> Option<Pubkey> {
+    metadata.collection.as_ref().filter(|collection| collection.verified).map(|collection| collection.key)
+}
+
+pub fn check_collection_allowed(marketplace: &Marketplace, metadata: &MetadataAccount) -> Result<()> {
+    if !marketplace.curated {
+        return Ok(());
+    }
+
+    let collection = verified_collection(metadata).ok_or(EscrowError::CollectionNotAllowed)?;
+    require!(marketplace.allowed_collections.contains(&collection), EscrowError::CollectionNotAllowed);
+
+    Ok(())
+}
+
+// Runs at make time, on top of the collection check that also runs on every take
+pub fn check_listing(
+    marketplace: &Marketplace,
+    metadata: &MetadataAccount,
+    mint_b: &Pubkey,
+    authority: Option<&Signer>,
+) -> Result<()> {
+
+    check_collection_allowed(marketplace, metadata)?;
+
+    require!(
+        marketplace.allowed_payment_mints.is_empty() || marketplace.allowed_payment_mints.contains(mint_b),
+        EscrowError::PaymentMintNotAllowed
+    );
+
+    if marketplace.requires_signoff {
+        let authority = authority.ok_or(EscrowError::MarketplaceSignoffRequired)?;
+        require_keys_eq!(authority.key(), marketplace.authority, EscrowError::MarketplaceSignoffRequired);
+    }
+
+    Ok(())
+}
+
+// A listing made on a marketplace can only be taken through it, under the rules it has at take time
+pub fn check_marketplace(
+    escrow: &Escrow,
+    marketplace: Option<&Account<Marketplace>>,
+    metadata: &MetadataAccount,
+) -> Result<()> {
+
+    match_marketplace(escrow, marketplace)?;
+
+    match marketplace {
+        Some(marketplace) if escrow.marketplace.is_some() => check_collection_allowed(marketplace, metadata),
+        _ => Ok(()),
+    }
+}
+
+// Settling a deal that was already checked when the payment came in only needs the marketplace to pay its fee,
+// the rules it has now don't apply so that a change can't lock the payment
+pub fn match_marketplace(
+    escrow: &Escrow,
+    marketplace: Option<&Account<Marketplace>>,
+) -> Result<()> {
+
+    let Some(escrow_marketplace) = escrow.marketplace else {
+        return Ok(());
+    };
+
+    let marketplace = marketplace.ok_or(EscrowError::InvalidMarketplace)?;
+    require_keys_eq!(marketplace.key(), escrow_marketplace, EscrowError::InvalidMarketplace);
+
+    Ok(())
+}
\ No newline at end of file
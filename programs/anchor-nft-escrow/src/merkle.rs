@@ -0,0 +1,21 @@
+use solana_program::keccak::hashv;
+
+// The leaf of a mint is the keccak hash of its address
+pub fn mint_leaf(mint: &[u8]) -> [u8; 32] {
+    hashv(&[mint]).0
+}
+
+// Pairs are hashed in sorted order so the proof doesn't need to carry the position of each node
+pub fn verify(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let mut computed_hash = leaf;
+
+    for node in proof.iter() {
+        computed_hash = if computed_hash <= *node {
+            hashv(&[&computed_hash, node]).0
+        } else {
+            hashv(&[node, &computed_hash]).0
+        };
+    }
+
+    computed_hash == root
+}
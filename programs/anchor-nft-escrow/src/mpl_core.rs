@@ -0,0 +1,258 @@
+use anchor_lang::prelude::*;
+use solana_program::{instruction::{AccountMeta, Instruction}, program::invoke_signed, pubkey};
+
+use crate::errors::EscrowError;
+
+// The Core program is not vendored, so we mirror the parts of its account layout and
+// the TransferV1 instruction that the escrow needs
+pub const MPL_CORE_ID: Pubkey = pubkey!("CoREENxT6tW1HoK8ypY1SxRMZTcVPm7R94rH4PZNhX7d");
+
+const ASSET_V1_KEY: u8 = 1;
+const PLUGIN_HEADER_V1_KEY: u8 = 3;
+const PLUGIN_REGISTRY_V1_KEY: u8 = 4;
+const COLLECTION_V1_KEY: u8 = 5;
+
+const TRANSFER_V1_DISCRIMINATOR: u8 = 14;
+
+#[derive(AnchorDeserialize)]
+pub enum UpdateAuthority {
+    None,
+    Address(Pubkey),
+    Collection(Pubkey),
+}
+
+#[derive(AnchorDeserialize)]
+struct BaseAssetV1 {
+    key: u8,
+    owner: Pubkey,
+    update_authority: UpdateAuthority,
+    _name: String,
+    _uri: String,
+    _seq: Option<u64>,
+}
+
+#[derive(AnchorDeserialize)]
+struct BaseCollectionV1 {
+    key: u8,
+    _update_authority: Pubkey,
+    _name: String,
+    _uri: String,
+    _num_minted: u32,
+    _current_size: u32,
+}
+
+#[derive(AnchorDeserialize)]
+struct PluginHeaderV1 {
+    key: u8,
+    plugin_registry_offset: u64,
+}
+
+#[derive(AnchorDeserialize)]
+pub enum PluginAuthority {
+    None,
+    Owner,
+    UpdateAuthority,
+    Address { address: Pubkey },
+}
+
+#[derive(AnchorDeserialize)]
+struct RegistryRecord {
+    plugin_type: u8,
+    _authority: PluginAuthority,
+    offset: u64,
+}
+
+#[derive(AnchorDeserialize)]
+struct PluginRegistryV1 {
+    key: u8,
+    registry: Vec<RegistryRecord>,
+}
+
+#[derive(AnchorDeserialize, Clone)]
+pub struct Creator {
+    pub address: Pubkey,
+    pub percentage: u8,
+}
+
+#[derive(AnchorDeserialize, Clone)]
+pub enum RuleSet {
+    None,
+    ProgramAllowList(Vec<Pubkey>),
+    ProgramDenyList(Vec<Pubkey>),
+}
+
+#[derive(AnchorDeserialize, Clone)]
+pub struct Royalties {
+    pub basis_points: u16,
+    pub creators: Vec<Creator>,
+    pub rule_set: RuleSet,
+}
+
+// Only the plugins the escrow cares about, the discriminant is the Core PluginType
+const ROYALTIES: u8 = 0;
+const FREEZE_DELEGATE: u8 = 1;
+const PERMANENT_FREEZE_DELEGATE: u8 = 5;
+
+#[derive(Default)]
+pub struct Plugins {
+    pub royalties: Option<Royalties>,
+    pub frozen: bool,
+}
+
+impl Plugins {
+    // Walks the plugin registry that follows the base account, if there is one
+    fn load(data: &[u8], base_len: usize) -> Result<Self> {
+        let mut plugins = Plugins::default();
+
+        if data.len() <= base_len {
+            return Ok(plugins);
+        }
+
+        let header = PluginHeaderV1::deserialize(&mut &data[base_len..])?;
+        require_eq!(header.key, PLUGIN_HEADER_V1_KEY, EscrowError::InvalidCoreAsset);
+
+        let registry = PluginRegistryV1::deserialize(&mut &data[header.plugin_registry_offset as usize..])?;
+        require_eq!(registry.key, PLUGIN_REGISTRY_V1_KEY, EscrowError::InvalidCoreAsset);
+
+        for record in registry.registry.iter() {
+            // Every plugin is stored as the Plugin enum, so the data starts with its discriminant
+            let plugin_data = &mut &data[record.offset as usize + 1..];
+
+            match record.plugin_type {
+                ROYALTIES => plugins.royalties = Some(Royalties::deserialize(plugin_data)?),
+                FREEZE_DELEGATE | PERMANENT_FREEZE_DELEGATE => plugins.frozen |= bool::deserialize(plugin_data)?,
+                _ => {}
+            }
+        }
+
+        Ok(plugins)
+    }
+}
+
+pub struct CoreAsset {
+    pub owner: Pubkey,
+    pub collection: Option<Pubkey>,
+    pub plugins: Plugins,
+}
+
+impl CoreAsset {
+    pub fn load(asset: &AccountInfo) -> Result<Self> {
+        require_keys_eq!(*asset.owner, MPL_CORE_ID, EscrowError::InvalidCoreAsset);
+
+        let data = asset.try_borrow_data()?;
+        let cursor = &mut &data[..];
+        let base = BaseAssetV1::deserialize(cursor)?;
+        require_eq!(base.key, ASSET_V1_KEY, EscrowError::InvalidCoreAsset);
+        let base_len = data.len() - cursor.len();
+
+        let collection = match base.update_authority {
+            UpdateAuthority::Collection(collection) => Some(collection),
+            _ => None,
+        };
+
+        Ok(CoreAsset {
+            owner: base.owner,
+            collection,
+            plugins: Plugins::load(&data, base_len)?,
+        })
+    }
+}
+
+pub struct CoreCollection {
+    pub plugins: Plugins,
+}
+
+impl CoreCollection {
+    pub fn load(collection: &AccountInfo) -> Result<Self> {
+        require_keys_eq!(*collection.owner, MPL_CORE_ID, EscrowError::InvalidCoreCollection);
+
+        let data = collection.try_borrow_data()?;
+        let cursor = &mut &data[..];
+        let base = BaseCollectionV1::deserialize(cursor)?;
+        require_eq!(base.key, COLLECTION_V1_KEY, EscrowError::InvalidCoreCollection);
+        let base_len = data.len() - cursor.len();
+
+        Ok(CoreCollection {
+            plugins: Plugins::load(&data, base_len)?,
+        })
+    }
+}
+
+// Loads the asset together with its collection, checking that the collection passed in is the asset's one.
+// Plugins set on the asset take precedence over the ones inherited from the collection
+pub fn load_asset(asset: &AccountInfo, collection: Option<&AccountInfo>) -> Result<CoreAsset> {
+    let mut core_asset = CoreAsset::load(asset)?;
+
+    require!(
+        core_asset.collection == collection.map(|collection| collection.key()),
+        EscrowError::InvalidCoreCollection
+    );
+
+    if let Some(collection) = collection {
+        let core_collection = CoreCollection::load(collection)?;
+
+        if core_asset.plugins.royalties.is_none() {
+            core_asset.plugins.royalties = core_collection.plugins.royalties;
+        }
+        core_asset.plugins.frozen |= core_collection.plugins.frozen;
+    }
+
+    Ok(core_asset)
+}
+
+// Moves a Core asset through the Core program, so every plugin of the asset gets to validate the transfer
+pub struct TransferV1Cpi<'a, 'info> {
+    pub core_program: &'a AccountInfo<'info>,
+    pub asset: &'a AccountInfo<'info>,
+    pub collection: Option<&'a AccountInfo<'info>>,
+    pub payer: &'a AccountInfo<'info>,
+    pub authority: &'a AccountInfo<'info>,
+    pub new_owner: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> TransferV1Cpi<'a, 'info> {
+    pub fn invoke(self) -> Result<()> {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(
+        self,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+
+        // Missing optional accounts are passed as the Core program itself
+        let collection = self.collection.unwrap_or(self.core_program);
+
+        let instruction = Instruction {
+            program_id: MPL_CORE_ID,
+            accounts: vec![
+                AccountMeta::new(self.asset.key(), false),
+                AccountMeta::new_readonly(collection.key(), false),
+                AccountMeta::new(self.payer.key(), true),
+                AccountMeta::new_readonly(self.authority.key(), true),
+                AccountMeta::new_readonly(self.new_owner.key(), false),
+                AccountMeta::new_readonly(self.system_program.key(), false),
+                AccountMeta::new_readonly(MPL_CORE_ID, false),
+            ],
+            // TransferV1 without a compression proof
+            data: vec![TRANSFER_V1_DISCRIMINATOR, 0],
+        };
+
+        invoke_signed(
+            &instruction,
+            &[
+                self.asset.clone(),
+                collection.clone(),
+                self.payer.clone(),
+                self.authority.clone(),
+                self.new_owner.clone(),
+                self.system_program.clone(),
+                self.core_program.clone(),
+            ],
+            signer_seeds,
+        )?;
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -1,10 +1,33 @@
 use anchor_lang::prelude::*;
+use mpl_bubblegum::{types::MetadataArgs, hash::hash_metadata};
 
+use crate::errors::EscrowError;
+
+// The maker isn't stored, it's the first seed of the escrow, so any account can be one,
+// including PDAs of other programs that sign the make through CPI
 #[account]
 pub struct Escrow {
     pub mint_a: Pubkey,
     pub mint_b: Pubkey,
     pub mint_b_amount: u64,
+    pub dutch_auction: Option<DutchAuction>,
+    pub english_auction: Option<EnglishAuction>,
+    pub delegated: bool,
+    pub rental: Option<Rental>,
+    pub collateral: bool,
+    pub arbitration: Option<Arbitration>,
+    pub confirmation: Option<Confirmation>,
+    // Sales pay this account instead of the maker when it's set, e.g. a cold wallet or a treasury
+    pub proceeds_recipient: Option<Pubkey>,
+    pub split: bool,
+    // Overrides the referral cut of the config for this listing
+    pub referral_bps: Option<u16>,
+    pub frozen: bool,
+    pub marketplace: Option<Pubkey>,
+    // The marketplace fee the listing was made with, later fee changes only apply to new listings
+    pub marketplace_fee_bps: u16,
+    // The account that paid the rent of the escrow, every path that closes it refunds this one rather than the maker
+    pub payer: Pubkey,
 }
 
 impl Escrow {
@@ -12,6 +35,719 @@ impl Escrow {
         8 +     // Discriminator
         32 +    // mint_a
         32 +    // mint_b
+        8 +     // mint_b_amount
+        1 + DutchAuction::space() +  // dutch_auction
+        1 + EnglishAuction::space() +  // english_auction
+        1 +     // delegated
+        1 + Rental::space() +  // rental
+        1 +     // collateral
+        1 + Arbitration::space() +  // arbitration
+        1 + Confirmation::space() +  // confirmation
+        1 + 32 +    // proceeds_recipient
+        1 +     // split
+        1 + 2 + // referral_bps
+        1 +     // frozen
+        1 + 32 +    // marketplace
+        2 +     // marketplace_fee_bps
+        32      // payer
+    }
+
+    // The referral cut of the listing, the program-wide one unless the maker set its own
+    pub fn referral_bps(&self, config: &Config) -> u16 {
+        self.referral_bps.unwrap_or(config.referral_bps)
+    }
+
+    // The cut of the price that goes to the referrer of a fill, rounded down in favour of the maker
+    pub fn referral_fee(&self, config: &Config, price: u64) -> u64 {
+        (price as u128 * self.referral_bps(config) as u128 / 10_000) as u64
+    }
+
+    // The cut of the price that goes to the marketplace treasury, rounded down in favour of the maker
+    pub fn marketplace_fee(&self, price: u64) -> u64 {
+        (price as u128 * self.marketplace_fee_bps as u128 / 10_000) as u64
+    }
+
+    // The account sales are paid to, the maker unless another one was set at make time
+    pub fn payee(&self, maker: Pubkey) -> Pubkey {
+        self.proceeds_recipient.unwrap_or(maker)
+    }
+
+    // The price the taker has to pay at the given time
+    pub fn current_price(&self, now: i64) -> u64 {
+        match &self.dutch_auction {
+            Some(dutch_auction) => dutch_auction.price_at(now),
+            None => self.mint_b_amount,
+        }
+    }
+}
+
+// The program-wide settings, the admin starts as the upgrade authority of the program
+#[account]
+pub struct Config {
+    pub admin: Pubkey,
+    pub paused: bool,
+    // Referral cut of every fill, in basis points of the price, escrows can override it
+    pub referral_bps: u16,
+}
+
+impl Config {
+    pub fn space() -> usize {
+        8 +     // Discriminator
+        32 +    // admin
+        1 +     // paused
+        2       // referral_bps
+    }
+}
+
+// A storefront hosted by the program, every authority can run one.
+// Curated marketplaces only accept listings of assets in one of their allowed verified collections
+#[account]
+pub struct Marketplace {
+    // The key the marketplace address is derived from, picked at creation so that the authority can change
+    pub id: Pubkey,
+    pub authority: Pubkey,
+    pub curated: bool,
+    pub allowed_collections: Vec<Pubkey>,
+    // Listings can only ask for these mints, any mint is allowed when it's empty
+    pub allowed_payment_mints: Vec<Pubkey>,
+    // Cut of every fill paid to the treasury, in basis points of the price
+    pub fee_bps: u16,
+    pub treasury: Pubkey,
+    // Listings have to be co-signed by the authority
+    pub requires_signoff: bool,
+}
+
+impl Marketplace {
+    pub const MAX_COLLECTIONS: usize = 16;
+    pub const MAX_PAYMENT_MINTS: usize = 8;
+
+    pub fn space() -> usize {
+        8 +     // Discriminator
+        32 +    // id
+        32 +    // authority
+        1 +     // curated
+        4 + Self::MAX_COLLECTIONS * 32 +    // allowed_collections
+        4 + Self::MAX_PAYMENT_MINTS * 32 +  // allowed_payment_mints
+        2 +     // fee_bps
+        32 +    // treasury
+        1       // requires_signoff
+    }
+}
+
+// Flags a mint or a collection, it can't be listed or bought while the entry exists
+#[account]
+pub struct DenylistEntry {
+    pub key: Pubkey,
+}
+
+impl DenylistEntry {
+    pub fn space() -> usize {
+        8 +     // Discriminator
+        32      // key
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DutchAuction {
+    pub start_price: u64,
+    pub end_price: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+impl DutchAuction {
+    pub fn space() -> usize {
+        8 +     // start_price
+        8 +     // end_price
+        8 +     // start_time
+        8       // end_time
+    }
+
+    // The duration has to fit in an i64 too, price_at divides by it
+    pub fn is_valid(&self) -> bool {
+        self.start_time < self.end_time
+            && self.end_time.checked_sub(self.start_time).is_some()
+            && self.start_price >= self.end_price
+    }
+
+    // The price decays linearly from start_price to end_price, rounding in favour of the maker
+    pub fn price_at(&self, now: i64) -> u64 {
+        if now <= self.start_time {
+            return self.start_price;
+        }
+        if now >= self.end_time {
+            return self.end_price;
+        }
+
+        let elapsed = (now - self.start_time) as u128;
+        let duration = (self.end_time - self.start_time) as u128;
+        let decay = (self.start_price - self.end_price) as u128 * elapsed / duration;
+
+        self.start_price - decay as u64
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct EnglishAuction {
+    pub end_time: i64,
+    pub extension_window: i64,
+    pub min_bid_increment_bps: u16,
+    pub highest_bid: u64,
+    pub highest_bidder: Option<Pubkey>,
+    // The first bidder paid the bid vault's rent, it gets it back when the auction settles
+    pub bid_vault_payer: Option<Pubkey>,
+}
+
+impl EnglishAuction {
+    pub const MAX_EXTENSION_WINDOW: i64 = 24 * 60 * 60;
+
+    pub fn space() -> usize {
+        8 +     // end_time
+        8 +     // extension_window
+        2 +     // min_bid_increment_bps
+        8 +     // highest_bid
+        1 + 32 + // highest_bidder
+        1 + 32  // bid_vault_payer
+    }
+
+    // Every bid needs to beat the highest one by at least the increment, rounded up and never less than 1
+    pub fn minimum_bid(&self) -> u64 {
+        let product = self.highest_bid as u128 * self.min_bid_increment_bps as u128;
+        let floor = product / 10_000;
+        let increment = if floor * 10_000 < product { floor + 1 } else { floor } as u64;
+
+        self.highest_bid.saturating_add(increment.max(1))
+    }
+
+    // A bid placed in the last extension_window seconds pushes the end of the auction back
+    pub fn extend(&mut self, now: i64) -> Result<()> {
+        if self.end_time - now < self.extension_window {
+            self.end_time = now.checked_add(self.extension_window).ok_or(EscrowError::Overflow)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rental {
+    pub duration: i64,
+    pub renter: Option<Pubkey>,
+    pub end_time: i64,
+}
+
+impl Rental {
+    pub const MAX_DURATION: i64 = 365 * 24 * 60 * 60;
+
+    pub fn space() -> usize {
+        8 +     // duration
+        1 + 32 +    // renter
+        8       // end_time
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Arbitration {
+    pub arbiter: Pubkey,
+    pub fee_bps: u16,
+    pub buyer: Option<Pubkey>,
+    pub disputed: bool,
+}
+
+impl Arbitration {
+    pub fn space() -> usize {
+        32 +    // arbiter
+        2 +     // fee_bps
+        1 + 32 +    // buyer
+        1       // disputed
+    }
+
+    // A new arbitration can't start with a deposit or a dispute
+    pub fn is_valid(&self) -> bool {
+        self.fee_bps <= 10_000 && self.buyer.is_none() && !self.disputed
+    }
+
+    pub fn fee(&self, amount: u64) -> u64 {
+        (amount as u128 * self.fee_bps as u128 / 10_000) as u64
+    }
+}
+
+// A two-sided escrow only settles once both parties confirmed, or once the deadline passed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Confirmation {
+    pub window: i64,
+    pub buyer: Option<Pubkey>,
+    pub deadline: i64,
+    pub maker_confirmed: bool,
+    pub buyer_confirmed: bool,
+}
+
+impl Confirmation {
+    pub const MAX_WINDOW: i64 = 90 * 24 * 60 * 60;
+
+    pub fn space() -> usize {
+        8 +     // window
+        1 + 32 +    // buyer
+        8 +     // deadline
+        1 +     // maker_confirmed
+        1       // buyer_confirmed
+    }
+
+    pub fn is_settleable(&self, now: i64) -> bool {
+        (self.maker_confirmed && self.buyer_confirmed) || now >= self.deadline
+    }
+}
+
+// An order signed off-chain by the maker, the signed message is the Borsh encoding of it
+// behind a domain tag and the program id, so a signature is only valid for this deployment
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Order {
+    pub maker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub maker_amount: u64,
+    pub taker_amount: u64,
+    pub expiry: i64,
+    pub nonce: u64,
+}
+
+impl Order {
+    pub const DOMAIN: &[u8] = b"anchor-nft-escrow:order:v1";
+
+    pub fn message(&self) -> Result<Vec<u8>> {
+        Ok([Self::DOMAIN, crate::ID.as_ref(), &self.try_to_vec()?].concat())
+    }
+
+    // Orders are tracked on-chain by the hash of their signed message
+    pub fn hash(&self) -> Result<[u8; 32]> {
+        Ok(solana_program::keccak::hash(&self.message()?).0)
+    }
+}
+
+// Every order of a maker with a nonce below min_nonce is cancelled
+#[account]
+pub struct MakerNonce {
+    pub maker: Pubkey,
+    pub min_nonce: u64,
+}
+
+impl MakerNonce {
+    pub fn space() -> usize {
+        8 +     // Discriminator
+        32 +    // maker
+        8       // min_nonce
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OrderState {
+    Open,
+    Filled,
+    Cancelled,
+}
+
+// Keeps a single order from being filled twice, or filled after it was cancelled
+#[account]
+pub struct OrderStatus {
+    pub maker: Pubkey,
+    pub state: OrderState,
+}
+
+impl OrderStatus {
+    pub fn space() -> usize {
+        8 +     // Discriminator
+        32 +    // maker
+        1       // state
+    }
+}
+
+// How the arbiter settles a disputed escrow
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Resolution {
+    // The asset goes to the buyer and the payment to the maker
+    Release,
+    // The asset goes back to the maker and the payment back to the buyer
+    Refund,
+}
+
+// The terms of a loan backed by the asset vaulted in the escrow
+#[account]
+pub struct Loan {
+    pub escrow: Pubkey,
+    pub borrower: Pubkey,
+    pub lender: Option<Pubkey>,
+    pub principal: u64,
+    pub apr_bps: u16,
+    pub duration: i64,
+    pub start_time: i64,
+    // While the escrow is frozen the borrower can't repay, so that time doesn't count towards the deadline
+    pub frozen_at: Option<i64>,
+    pub frozen_for: i64,
+}
+
+impl Loan {
+    pub const MAX_DURATION: i64 = 5 * 365 * 24 * 60 * 60;
+
+    pub fn space() -> usize {
+        8 +     // Discriminator
+        32 +    // escrow
+        32 +    // borrower
+        1 + 32 +    // lender
+        8 +     // principal
+        2 +     // apr_bps
+        8 +     // duration
+        8 +     // start_time
+        1 + 8 + // frozen_at
+        8       // frozen_for
+    }
+
+    pub fn deadline(&self) -> Result<i64> {
+        self.start_time
+            .checked_add(self.duration)
+            .and_then(|deadline| deadline.checked_add(self.frozen_for))
+            .ok_or(error!(EscrowError::Overflow))
+    }
+
+    // Called whenever the escrow gets frozen or thawed
+    pub fn set_frozen(&mut self, frozen: bool, now: i64) -> Result<()> {
+        match (frozen, self.frozen_at) {
+            (true, None) if self.lender.is_some() => self.frozen_at = Some(now),
+            (false, Some(frozen_at)) => {
+                self.frozen_for = now
+                    .checked_sub(frozen_at)
+                    .and_then(|frozen_for| self.frozen_for.checked_add(frozen_for))
+                    .ok_or(EscrowError::Overflow)?;
+                self.frozen_at = None;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    // Interest accrues over the whole duration of the loan, rounded up in favour of the lender
+    pub fn repayment(&self) -> Result<u64> {
+        let numerator = self.principal as u128 * self.apr_bps as u128 * self.duration as u128;
+        let denominator = 10_000u128 * 365 * 24 * 60 * 60;
+        let floor = numerator / denominator;
+        let interest = u64::try_from(if floor * denominator < numerator { floor + 1 } else { floor })
+            .map_err(|_| error!(EscrowError::Overflow))?;
+
+        self.principal.checked_add(interest).ok_or(error!(EscrowError::Overflow))
+    }
+}
+
+// The payees of a split escrow, created next to it by make_split
+#[account]
+pub struct Payees {
+    pub escrow: Pubkey,
+    pub payees: Vec<Payee>,
+}
+
+impl Payees {
+    pub const MAX_PAYEES: usize = 5;
+
+    pub fn space() -> usize {
+        8 +     // Discriminator
+        32 +    // escrow
+        4 + Self::MAX_PAYEES * Payee::space()   // payees
+    }
+
+    // Between one and MAX_PAYEES distinct payees, each with a share, and the shares add up to 100%
+    pub fn is_valid(payees: &[Payee]) -> bool {
+        if payees.is_empty() || payees.len() > Self::MAX_PAYEES {
+            return false;
+        }
+        if payees.iter().any(|payee| payee.share_bps == 0) {
+            return false;
+        }
+        if payees.iter().enumerate().any(|(i, payee)| payees[..i].iter().any(|other| other.address == payee.address)) {
+            return false;
+        }
+
+        payees.iter().map(|payee| payee.share_bps as u32).sum::<u32>() == 10_000
+    }
+
+    // Every payee gets its share rounded down, the rounding dust goes to the first payee
+    // so that the amounts always add up to exactly the price
+    pub fn amounts(&self, price: u64) -> Vec<u64> {
+        let mut amounts: Vec<u64> = self.payees.iter()
+            .map(|payee| (price as u128 * payee.share_bps as u128 / 10_000) as u64)
+            .collect();
+        let dust = price - amounts.iter().sum::<u64>();
+        amounts[0] += dust;
+
+        amounts
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Payee {
+    pub address: Pubkey,
+    pub share_bps: u16,
+}
+
+impl Payee {
+    pub fn space() -> usize {
+        32 +    // address
+        2       // share_bps
+    }
+}
+
+#[account]
+pub struct Bid {
+    pub bidder: Pubkey,
+    pub mint_b: Pubkey,
+    pub mint_b_amount: u64,
+    pub merkle_root: [u8; 32],
+}
+
+impl Bid {
+    pub fn space() -> usize {
+        8 +     // Discriminator
+        32 +    // bidder
+        32 +    // mint_b
+        8 +     // mint_b_amount
+        32      // merkle_root
+    }
+}
+
+#[account]
+pub struct Offer {
+    pub buyer: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub mint_b_amount: u64,
+}
+
+impl Offer {
+    pub fn space() -> usize {
+        8 +     // Discriminator
+        32 +    // buyer
+        32 +    // mint_a
+        32 +    // mint_b
         8       // mint_b_amount
     }
-}
\ No newline at end of file
+}
+
+// The current state of a compressed NFT leaf, Bubblegum verifies it against the proof
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CompressedLeaf {
+    pub root: [u8; 32],
+    pub data_hash: [u8; 32],
+    pub creator_hash: [u8; 32],
+    pub nonce: u64,
+    pub index: u32,
+}
+
+impl CompressedLeaf {
+    // The leaf only commits to a hash of its metadata, so the borsh-serialized MetadataArgs have to hash to
+    // data_hash. Bubblegum checks data_hash against the proof in the same instruction
+    pub fn metadata(&self, metadata: &[u8]) -> Result<MetadataArgs> {
+        let metadata = MetadataArgs::try_from_slice(metadata).map_err(|_| error!(EscrowError::InvalidLeafMetadata))?;
+        let data_hash = hash_metadata(&metadata).map_err(|_| error!(EscrowError::InvalidLeafMetadata))?;
+        require!(data_hash == self.data_hash, EscrowError::InvalidLeafMetadata);
+
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mpl_bubblegum::types::{Collection, TokenProgramVersion, TokenStandard};
+
+    fn payees(shares: &[u16]) -> Vec<Payee> {
+        shares.iter().map(|&share_bps| Payee { address: Pubkey::new_unique(), share_bps }).collect()
+    }
+
+    #[test]
+    fn payee_shares_add_up_to_exactly_100_percent() {
+        assert!(Payees::is_valid(&payees(&[5_000, 3_000, 2_000])));
+        assert!(Payees::is_valid(&payees(&[10_000])));
+        assert!(!Payees::is_valid(&payees(&[5_000, 4_999])));
+        assert!(!Payees::is_valid(&payees(&[5_000, 5_001])));
+    }
+
+    #[test]
+    fn payees_are_distinct() {
+        let mut duplicated = payees(&[2_500, 2_500, 5_000]);
+        duplicated[2].address = duplicated[0].address;
+
+        assert!(!Payees::is_valid(&duplicated));
+    }
+
+    #[test]
+    fn payees_have_a_share() {
+        assert!(!Payees::is_valid(&payees(&[0, 10_000])));
+    }
+
+    #[test]
+    fn there_are_one_to_max_payees() {
+        assert!(!Payees::is_valid(&[]));
+        assert!(Payees::is_valid(&payees(&[2_000; Payees::MAX_PAYEES])));
+        assert!(!Payees::is_valid(&payees(&[1_000, 1_500, 1_500, 2_000, 2_000, 2_000])));
+    }
+
+    #[test]
+    fn amounts_add_up_to_the_price() {
+        let payees = Payees { escrow: Pubkey::new_unique(), payees: payees(&[3_333, 3_333, 3_334]) };
+
+        for price in [0, 1, 2, 7, 100, 9_999, 1_000_001, u64::MAX] {
+            assert_eq!(payees.amounts(price).iter().map(|&amount| amount as u128).sum::<u128>(), price as u128);
+        }
+        // The rounding dust goes to the first payee
+        assert_eq!(payees.amounts(100), vec![34, 33, 33]);
+        assert_eq!(payees.amounts(7), vec![3, 2, 2]);
+    }
+
+    fn dutch_auction(start_price: u64, end_price: u64, start_time: i64, end_time: i64) -> DutchAuction {
+        DutchAuction { start_price, end_price, start_time, end_time }
+    }
+
+    #[test]
+    fn dutch_auction_price_holds_outside_the_range() {
+        let auction = dutch_auction(1_000, 100, 1_000, 2_000);
+
+        assert_eq!(auction.price_at(0), 1_000);
+        assert_eq!(auction.price_at(999), 1_000);
+        assert_eq!(auction.price_at(2_001), 100);
+        assert_eq!(auction.price_at(i64::MAX), 100);
+    }
+
+    #[test]
+    fn dutch_auction_price_matches_the_bounds() {
+        let auction = dutch_auction(1_000, 100, 1_000, 2_000);
+
+        assert_eq!(auction.price_at(1_000), 1_000);
+        assert_eq!(auction.price_at(2_000), 100);
+    }
+
+    #[test]
+    fn dutch_auction_price_decays_linearly_rounding_up() {
+        assert_eq!(dutch_auction(1_000, 100, 1_000, 2_000).price_at(1_500), 550);
+
+        // 10 * 1 / 3 decays by 3.33, the price rounds up in favour of the maker
+        let auction = dutch_auction(10, 0, 0, 3);
+        assert_eq!(auction.price_at(1), 7);
+        assert_eq!(auction.price_at(2), 4);
+
+        // Prices near u64::MAX don't overflow the decay
+        let auction = dutch_auction(u64::MAX, 0, 0, 2);
+        assert_eq!(auction.price_at(1), u64::MAX / 2 + 1);
+    }
+
+    #[test]
+    fn dutch_auction_needs_a_decaying_range() {
+        assert!(dutch_auction(1_000, 100, 1_000, 2_000).is_valid());
+        assert!(dutch_auction(100, 100, 1_000, 2_000).is_valid());
+        assert!(!dutch_auction(1_000, 100, 1_000, 1_000).is_valid());
+        assert!(!dutch_auction(1_000, 100, 2_000, 1_000).is_valid());
+        assert!(!dutch_auction(100, 1_000, 1_000, 2_000).is_valid());
+    }
+
+    #[test]
+    fn dutch_auction_duration_fits_in_an_i64() {
+        assert!(!dutch_auction(1_000, 100, i64::MIN, i64::MAX).is_valid());
+        assert!(!dutch_auction(1_000, 100, -1, i64::MAX).is_valid());
+        assert!(dutch_auction(1_000, 100, 0, i64::MAX).is_valid());
+        assert_eq!(dutch_auction(1_000, 100, 0, i64::MAX).price_at(i64::MAX / 2), 551);
+    }
+
+    fn loan(principal: u64, apr_bps: u16, duration: i64) -> Loan {
+        Loan { escrow: Pubkey::new_unique(), borrower: Pubkey::new_unique(), lender: None, principal, apr_bps, duration, start_time: 0, frozen_at: None, frozen_for: 0 }
+    }
+
+    const YEAR: i64 = 365 * 24 * 60 * 60;
+
+    #[test]
+    fn loan_interest_accrues_over_the_duration() {
+        assert_eq!(loan(10_000, 0, Loan::MAX_DURATION).repayment().unwrap(), 10_000);
+        assert_eq!(loan(10_000, 1_000, YEAR).repayment().unwrap(), 11_000);
+        assert_eq!(loan(10_000, 1_000, YEAR / 2).repayment().unwrap(), 10_500);
+        assert_eq!(loan(10_000, u16::MAX, Loan::MAX_DURATION).repayment().unwrap(), 10_000 + 327_675);
+    }
+
+    #[test]
+    fn loan_interest_rounds_up() {
+        // 10 * 1% over one second is far below a unit, the lender still gets 1
+        assert_eq!(loan(10, 100, 1).repayment().unwrap(), 11);
+        assert_eq!(loan(1, 1, 1).repayment().unwrap(), 2);
+    }
+
+    #[test]
+    fn loan_repayment_fits_in_a_u64() {
+        // The largest principal whose interest at the highest rate over the longest duration still fits
+        let principal = u64::MAX / (1 + u16::MAX as u64 * 5 / 10_000 + 1);
+        assert!(loan(principal, u16::MAX, Loan::MAX_DURATION).repayment().is_ok());
+
+        assert!(loan(u64::MAX, 0, Loan::MAX_DURATION).repayment().is_ok());
+        assert!(loan(u64::MAX, 1, 1).repayment().is_err());
+        assert!(loan(u64::MAX / 2, u16::MAX, Loan::MAX_DURATION).repayment().is_err());
+        assert!(loan(u64::MAX, u16::MAX, Loan::MAX_DURATION).repayment().is_err());
+    }
+
+    fn metadata(seller_fee_basis_points: u16) -> MetadataArgs {
+        MetadataArgs {
+            name: "cNFT".to_string(),
+            symbol: String::new(),
+            uri: String::new(),
+            seller_fee_basis_points,
+            primary_sale_happened: false,
+            is_mutable: true,
+            edition_nonce: None,
+            token_standard: Some(TokenStandard::NonFungible),
+            collection: Some(Collection { verified: true, key: Pubkey::new_unique() }),
+            uses: None,
+            token_program_version: TokenProgramVersion::Original,
+            creators: vec![],
+        }
+    }
+
+    #[test]
+    fn leaf_metadata_has_to_match_the_data_hash() {
+        let metadata = metadata(500);
+        let leaf = CompressedLeaf {
+            root: [0; 32],
+            data_hash: hash_metadata(&metadata).unwrap(),
+            creator_hash: [0; 32],
+            nonce: 0,
+            index: 0,
+        };
+
+        assert_eq!(leaf.metadata(&metadata.try_to_vec().unwrap()).unwrap(), metadata);
+
+        // Royalties are hashed on their own as well, they can't be changed either
+        let mut other = metadata.clone();
+        other.seller_fee_basis_points = 0;
+        assert!(leaf.metadata(&other.try_to_vec().unwrap()).is_err());
+
+        let mut other = metadata.clone();
+        other.collection = None;
+        assert!(leaf.metadata(&other.try_to_vec().unwrap()).is_err());
+
+        assert!(leaf.metadata(&[]).is_err());
+    }
+
+    #[test]
+    fn loan_deadline_skips_the_time_spent_frozen() {
+        let mut loan = loan(10, 0, 100);
+
+        // The clock only stops once the loan is funded
+        loan.set_frozen(true, 10).unwrap();
+        loan.set_frozen(false, 50).unwrap();
+        assert_eq!(loan.deadline().unwrap(), 100);
+
+        loan.lender = Some(Pubkey::new_unique());
+        loan.set_frozen(true, 10).unwrap();
+        loan.set_frozen(true, 20).unwrap();
+        loan.set_frozen(false, 50).unwrap();
+        loan.set_frozen(false, 60).unwrap();
+        assert_eq!(loan.deadline().unwrap(), 140);
+
+        loan.set_frozen(true, 120).unwrap();
+        loan.set_frozen(false, 125).unwrap();
+        assert_eq!(loan.deadline().unwrap(), 145);
+        assert_eq!(loan.repayment().unwrap(), 10);
+    }
+}
@@ -0,0 +1,671 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{TokenAccount, Transfer, CloseAccount, transfer, close_account},
+    metadata::{MetadataAccount,
+        mpl_token_metadata::{
+            accounts::{Metadata, MasterEdition, TokenRecord},
+            instructions::{TransferCpi, TransferCpiAccounts, TransferInstructionArgs},
+            types::TokenStandard}
+        },
+};
+use mpl_token_metadata::types::TransferArgs;
+
+use crate::{
+    state::{Escrow, Config, Marketplace},
+    errors::EscrowError,
+};
+
+// Editions are always moved one at a time, whatever amount was requested
+pub fn is_non_fungible(token_standard: &TokenStandard) -> bool {
+    token_standard == &TokenStandard::NonFungible || token_standard == &TokenStandard::ProgrammableNonFungible
+}
+
+pub fn transfer_amount(token_standard: &TokenStandard, amount: u64) -> u64 {
+    match is_non_fungible(token_standard) {
+        true => 1,
+        false => amount,
+    }
+}
+
+// Moves a Token Metadata asset between two token accounts through the Token Metadata Transfer instruction.
+// The token standard decides which of the edition and token record accounts are needed, and every one
+// of them is checked against its PDA before the CPI
+pub struct AssetTransfer<'a, 'info> {
+    pub token: &'a AccountInfo<'info>,
+    pub token_owner: &'a AccountInfo<'info>,
+    pub destination_token: &'a AccountInfo<'info>,
+    pub destination_owner: &'a AccountInfo<'info>,
+    pub mint: &'a AccountInfo<'info>,
+    pub metadata: &'a Account<'info, MetadataAccount>,
+    pub master_edition: Option<&'a AccountInfo<'info>>,
+    pub token_record: Option<&'a AccountInfo<'info>>,
+    pub destination_token_record: Option<&'a AccountInfo<'info>>,
+    pub authority: &'a AccountInfo<'info>,
+    pub payer: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+    pub sysvar_instructions: &'a AccountInfo<'info>,
+    pub token_program: &'a AccountInfo<'info>,
+    pub associated_token_program: &'a AccountInfo<'info>,
+    pub token_metadata_program: &'a AccountInfo<'info>,
+    pub amount: u64,
+}
+
+impl<'a, 'info> AssetTransfer<'a, 'info> {
+    pub fn invoke(
+        &self,
+    ) -> Result<()> {
+
+        let metadata = self.metadata.to_account_info();
+        self.build(&metadata)?.invoke()?;
+
+        Ok(())
+    }
+
+    pub fn invoke_signed(
+        &self,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+
+        let metadata = self.metadata.to_account_info();
+        self.build(&metadata)?.invoke_signed(signer_seeds)?;
+
+        Ok(())
+    }
+
+    fn build<'b>(
+        &'b self,
+        metadata: &'b AccountInfo<'info>,
+    ) -> Result<TransferCpi<'info, 'b>> {
+
+        let mint_key = self.mint.key();
+        let token_standard = self.metadata.token_standard.as_ref().ok_or(EscrowError::InvalidTokenStandard)?;
+
+        // Set-up the Instruction based on the token standard - We set it up as a Fungible by default
+        let mut edition: Option<&AccountInfo> = None;
+        let mut token_record: Option<&AccountInfo> = None;
+        let mut destination_token_record: Option<&AccountInfo> = None;
+
+        // Check the Metadata account
+        let (metadata_pda, _) = Metadata::find_pda(&mint_key);
+        require_keys_eq!(metadata_pda, self.metadata.key(), EscrowError::MetadataAccountDoesNotMatch);
+
+        if is_non_fungible(token_standard) {
+            //Check the Master Edition account
+            let master_edition = self.master_edition.ok_or(EscrowError::MasterEditionAccountDoesNotMatch)?;
+            let (master_edition_pda, _) = MasterEdition::find_pda(&mint_key);
+            require_keys_eq!(master_edition_pda, master_edition.key(), EscrowError::MasterEditionAccountDoesNotMatch);
+
+            edition = Some(master_edition);
+        }
+
+        if token_standard == &TokenStandard::ProgrammableNonFungible {
+            //Check both token records
+            let origin_token_record = self.token_record.ok_or(EscrowError::TokenRecordAccountDoesNotMatch)?;
+            let (token_record_pda, _) = TokenRecord::find_pda(&mint_key, self.token.key);
+            require_keys_eq!(token_record_pda, origin_token_record.key(), EscrowError::TokenRecordAccountDoesNotMatch);
+
+            let target_token_record = self.destination_token_record.ok_or(EscrowError::TokenRecordAccountDoesNotMatch)?;
+            let (destination_token_record_pda, _) = TokenRecord::find_pda(&mint_key, self.destination_token.key);
+            require_keys_eq!(destination_token_record_pda, target_token_record.key(), EscrowError::TokenRecordAccountDoesNotMatch);
+
+            token_record = Some(origin_token_record);
+            destination_token_record = Some(target_token_record);
+        }
+
+        //TODO After
+        let authorization_rules_program = None;
+        let authorization_rules = None;
+
+        Ok(TransferCpi::new(
+            self.token_metadata_program,
+            TransferCpiAccounts {
+                token: self.token,
+                token_owner: self.token_owner,
+                destination_token: self.destination_token,
+                destination_owner: self.destination_owner,
+                mint: self.mint,
+                metadata,
+                edition,
+                token_record,
+                destination_token_record,
+                authority: self.authority,
+                payer: self.payer,
+                system_program: self.system_program,
+                sysvar_instructions: self.sysvar_instructions,
+                spl_token_program: self.token_program,
+                spl_ata_program: self.associated_token_program,
+                authorization_rules_program,
+                authorization_rules,
+            },
+            TransferInstructionArgs {
+                transfer_args: TransferArgs::V1 {
+                    amount: transfer_amount(token_standard, self.amount),
+                    authorization_data: None,
+                },
+            },
+        ))
+    }
+}
+
+// Releases the token held in an escrow vault, signing with the escrow seeds.
+// Used by every instruction that moves the escrowed asset to its new owner.
+// The owner of the vault is the escrow itself, or the maker for delegated listings
+pub struct VaultTransfer<'a, 'info> {
+    pub escrow: &'a AccountInfo<'info>,
+    pub owner: &'a AccountInfo<'info>,
+    pub vault: &'a Account<'info, TokenAccount>,
+    pub destination_ata: &'a AccountInfo<'info>,
+    pub destination_owner: &'a AccountInfo<'info>,
+    pub mint: &'a AccountInfo<'info>,
+    pub metadata: &'a Account<'info, MetadataAccount>,
+    pub master_edition: Option<&'a AccountInfo<'info>>,
+    pub vault_token_record: Option<&'a AccountInfo<'info>>,
+    pub destination_token_record: &'a AccountInfo<'info>,
+    pub payer: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+    pub sysvar_instructions: &'a AccountInfo<'info>,
+    pub token_program: &'a AccountInfo<'info>,
+    pub associated_token_program: &'a AccountInfo<'info>,
+    pub token_metadata_program: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> VaultTransfer<'a, 'info> {
+    pub fn invoke_signed(
+        self,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+
+        let vault = self.vault.to_account_info();
+
+        AssetTransfer {
+            token: &vault,
+            token_owner: self.owner,
+            destination_token: self.destination_ata,
+            destination_owner: self.destination_owner,
+            mint: self.mint,
+            metadata: self.metadata,
+            master_edition: self.master_edition,
+            token_record: self.vault_token_record,
+            destination_token_record: Some(self.destination_token_record),
+            authority: self.escrow,
+            payer: self.payer,
+            system_program: self.system_program,
+            sysvar_instructions: self.sysvar_instructions,
+            token_program: self.token_program,
+            associated_token_program: self.associated_token_program,
+            token_metadata_program: self.token_metadata_program,
+            amount: self.vault.amount,
+        }.invoke_signed(signer_seeds)
+    }
+}
+
+// Returns the rent of an emptied vault. Token Metadata keeps the token accounts of pNFTs frozen,
+// and frozen accounts can't be closed, so those are left to Token Metadata
+pub fn close_vault<'info>(
+    vault: &AccountInfo<'info>,
+    metadata: &MetadataAccount,
+    destination: &AccountInfo<'info>,
+    escrow: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+
+    if metadata.token_standard == Some(TokenStandard::ProgrammableNonFungible) {
+        return Ok(());
+    }
+
+    let cpi_accounts = CloseAccount {
+        account: vault.clone(),
+        destination: destination.clone(),
+        authority: escrow.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer_seeds);
+
+    close_account(cpi_ctx)
+}
+
+// The referral cut, the marketplace fee and the royalties all come out of the price, together they can't exceed it
+pub fn check_fees(referral_bps: u16, marketplace_fee_bps: u16, royalty_bps: u16) -> Result<()> {
+    require!(
+        referral_bps as u32 + marketplace_fee_bps as u32 + royalty_bps as u32 <= 10_000,
+        EscrowError::FeesExceedPrice
+    );
+
+    Ok(())
+}
+
+// Pays the referral cut of a fill from the taker's token account, the referrer being the owner of the given token account.
+// Every take path goes through here, so it's also where the fees of the fill are checked against the price.
+// Returns the referrer and the fee so they can be recorded in the Fill event
+#[allow(clippy::too_many_arguments)]
+pub fn pay_referral<'info>(
+    escrow: &Escrow,
+    config: &Config,
+    royalty_bps: u16,
+    price: u64,
+    referrer_ata: Option<&Account<'info, TokenAccount>>,
+    taker_ata: &AccountInfo<'info>,
+    taker: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+) -> Result<(Option<Pubkey>, u64)> {
+
+    let referral_bps = referrer_ata.map_or(0, |_| escrow.referral_bps(config));
+    check_fees(referral_bps, escrow.marketplace_fee_bps, royalty_bps)?;
+
+    let Some(referrer_ata) = referrer_ata else {
+        return Ok((None, 0));
+    };
+
+    // The taker can't refer itself to get a discount
+    require_keys_neq!(referrer_ata.owner, taker.key(), EscrowError::InvalidReferrer);
+    require_keys_eq!(referrer_ata.mint, escrow.mint_b, EscrowError::InvalidReferrer);
+
+    let fee = escrow.referral_fee(config, price);
+    if fee > 0 {
+        let cpi_accounts = Transfer {
+            from: taker_ata.clone(),
+            to: referrer_ata.to_account_info(),
+            authority: taker.clone(),
+        };
+        let cpi_ctx = CpiContext::new(token_program.clone(), cpi_accounts);
+
+        transfer(cpi_ctx, fee)?;
+    }
+
+    Ok((Some(referrer_ata.owner), fee))
+}
+
+// The fee a fill owes the marketplace the listing was made on, at the rate of the listing. The treasury
+// token account is only needed, and checked, when there is a fee to pay
+pub fn marketplace_fee(
+    escrow: &Escrow,
+    marketplace: Option<&Account<Marketplace>>,
+    price: u64,
+    treasury_ata: Option<&Account<TokenAccount>>,
+) -> Result<u64> {
+
+    // check_marketplace already matched the account against the escrow
+    let (Some(_), Some(marketplace)) = (escrow.marketplace, marketplace) else {
+        return Ok(0);
+    };
+
+    let fee = escrow.marketplace_fee(price);
+    if fee == 0 {
+        return Ok(0);
+    }
+
+    let treasury_ata = treasury_ata.ok_or(EscrowError::InvalidTreasury)?;
+    require_keys_eq!(treasury_ata.owner, marketplace.treasury, EscrowError::InvalidTreasury);
+    require_keys_eq!(treasury_ata.mint, escrow.mint_b, EscrowError::InvalidTreasury);
+
+    Ok(fee)
+}
+
+// Fills of listings made on a marketplace pay its fee to the treasury, out of what the maker receives
+pub fn pay_marketplace_fee<'info>(
+    escrow: &Escrow,
+    marketplace: Option<&Account<'info, Marketplace>>,
+    price: u64,
+    treasury_ata: Option<&Account<'info, TokenAccount>>,
+    taker_ata: &AccountInfo<'info>,
+    taker: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+) -> Result<u64> {
+
+    let fee = marketplace_fee(escrow, marketplace, price, treasury_ata)?;
+    if fee == 0 {
+        return Ok(0);
+    }
+
+    let cpi_accounts = Transfer {
+        from: taker_ata.clone(),
+        to: treasury_ata.unwrap().to_account_info(),
+        authority: taker.clone(),
+    };
+    let cpi_ctx = CpiContext::new(token_program.clone(), cpi_accounts);
+
+    transfer(cpi_ctx, fee)?;
+
+    Ok(fee)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_spl::{
+        token::spl_token::{self, state::{Account as SplTokenAccount, AccountState}},
+        metadata::mpl_token_metadata::{types::Key as MetadataKey, ID as MPL_TOKEN_METADATA_ID},
+    };
+    use solana_program::program_pack::Pack;
+
+    fn account_info(key: Pubkey, owner: Pubkey, data: Vec<u8>) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            false,
+            true,
+            Box::leak(Box::new(1_000_000)),
+            Box::leak(data.into_boxed_slice()),
+            Box::leak(Box::new(owner)),
+            false,
+            0,
+        )
+    }
+
+    fn metadata_account(mint: Pubkey, token_standard: TokenStandard) -> Account<'static, MetadataAccount> {
+        let metadata = Metadata {
+            key: MetadataKey::MetadataV1,
+            update_authority: Pubkey::new_unique(),
+            mint,
+            name: String::new(),
+            symbol: String::new(),
+            uri: String::new(),
+            seller_fee_basis_points: 0,
+            creators: None,
+            primary_sale_happened: false,
+            is_mutable: true,
+            edition_nonce: None,
+            token_standard: Some(token_standard),
+            collection: None,
+            uses: None,
+            collection_details: None,
+            programmable_config: None,
+        };
+        let info = account_info(Metadata::find_pda(&mint).0, MPL_TOKEN_METADATA_ID, metadata.try_to_vec().unwrap());
+
+        Account::try_from(Box::leak(Box::new(info))).unwrap()
+    }
+
+    fn token_account(owner: Pubkey, mint: Pubkey) -> Account<'static, TokenAccount> {
+        let mut data = vec![0; SplTokenAccount::LEN];
+        SplTokenAccount::pack(
+            SplTokenAccount { mint, owner, amount: 1_000, state: AccountState::Initialized, ..Default::default() },
+            &mut data,
+        ).unwrap();
+        let info = account_info(Pubkey::new_unique(), spl_token::ID, data);
+
+        Account::try_from(Box::leak(Box::new(info))).unwrap()
+    }
+
+    // Snapshots the fee of the marketplace like make does
+    fn escrow(mint_b: Pubkey, referral_bps: Option<u16>, marketplace: Option<&Account<Marketplace>>) -> Escrow {
+        Escrow {
+            mint_a: Pubkey::new_unique(),
+            mint_b,
+            mint_b_amount: 100,
+            dutch_auction: None,
+            english_auction: None,
+            delegated: false,
+            rental: None,
+            collateral: false,
+            arbitration: None,
+            confirmation: None,
+            proceeds_recipient: None,
+            split: false,
+            referral_bps,
+            frozen: false,
+            marketplace: marketplace.map(|marketplace| marketplace.key()),
+            marketplace_fee_bps: marketplace.map_or(0, |marketplace| marketplace.fee_bps),
+            payer: Pubkey::new_unique(),
+        }
+    }
+
+    fn config(referral_bps: u16) -> Config {
+        Config { admin: Pubkey::new_unique(), paused: false, referral_bps }
+    }
+
+    fn marketplace(fee_bps: u16, treasury: Pubkey) -> Account<'static, Marketplace> {
+        let marketplace = Marketplace {
+            id: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            curated: false,
+            allowed_collections: vec![],
+            allowed_payment_mints: vec![],
+            fee_bps,
+            treasury,
+            requires_signoff: false,
+        };
+        let mut data = vec![];
+        marketplace.try_serialize(&mut data).unwrap();
+        let info = account_info(Pubkey::new_unique(), crate::ID, data);
+
+        Account::try_from(Box::leak(Box::new(info))).unwrap()
+    }
+
+    fn assert_error<T: std::fmt::Debug>(result: Result<T>, expected: EscrowError) {
+        match result.unwrap_err() {
+            Error::AnchorError(error) => assert_eq!(error.error_code_number, u32::from(expected)),
+            error => panic!("unexpected error {error:?}"),
+        }
+    }
+
+    // The edition, token records and amount a Token Metadata transfer was built with
+    type BuiltTransfer = (Option<Pubkey>, Option<Pubkey>, Option<Pubkey>, u64);
+
+    // Builds the transfer of `amount` tokens of a fresh mint, a default key standing for the right PDA
+    fn build_transfer(
+        token_standard: TokenStandard,
+        amount: u64,
+        master_edition: Option<Pubkey>,
+        token_records: Option<(Pubkey, Pubkey)>,
+    ) -> Result<BuiltTransfer> {
+        let mint = Pubkey::new_unique();
+        let any = account_info(Pubkey::new_unique(), Pubkey::default(), vec![]);
+        let mint_info = account_info(mint, spl_token::ID, vec![]);
+        let token = account_info(Pubkey::new_unique(), spl_token::ID, vec![]);
+        let destination_token = account_info(Pubkey::new_unique(), spl_token::ID, vec![]);
+        let metadata = metadata_account(mint, token_standard);
+        let master_edition = master_edition.map(|key| {
+            let key = if key == Pubkey::default() { MasterEdition::find_pda(&mint).0 } else { key };
+            account_info(key, MPL_TOKEN_METADATA_ID, vec![])
+        });
+        let token_records = token_records.map(|(origin, destination)| {
+            let origin = if origin == Pubkey::default() { TokenRecord::find_pda(&mint, token.key).0 } else { origin };
+            let destination = if destination == Pubkey::default() { TokenRecord::find_pda(&mint, destination_token.key).0 } else { destination };
+            (account_info(origin, MPL_TOKEN_METADATA_ID, vec![]), account_info(destination, MPL_TOKEN_METADATA_ID, vec![]))
+        });
+
+        let transfer = AssetTransfer {
+            token: &token,
+            token_owner: &any,
+            destination_token: &destination_token,
+            destination_owner: &any,
+            mint: &mint_info,
+            metadata: &metadata,
+            master_edition: master_edition.as_ref(),
+            token_record: token_records.as_ref().map(|(origin, _)| origin),
+            destination_token_record: token_records.as_ref().map(|(_, destination)| destination),
+            authority: &any,
+            payer: &any,
+            system_program: &any,
+            sysvar_instructions: &any,
+            token_program: &any,
+            associated_token_program: &any,
+            token_metadata_program: &any,
+            amount,
+        };
+        let metadata_info = metadata.to_account_info();
+        let cpi = transfer.build(&metadata_info)?;
+        let TransferArgs::V1 { amount, .. } = cpi.__args.transfer_args;
+
+        Ok((cpi.edition.map(|info| info.key()), cpi.token_record.map(|info| info.key()), cpi.destination_token_record.map(|info| info.key()), amount))
+    }
+
+    #[test]
+    fn fungible_transfers_skip_the_edition_and_token_records() {
+        let (edition, token_record, destination_token_record, amount) = build_transfer(TokenStandard::Fungible, 42, None, None).unwrap();
+
+        assert_eq!((edition, token_record, destination_token_record, amount), (None, None, None, 42));
+    }
+
+    #[test]
+    fn non_fungible_transfers_move_one_edition() {
+        let (edition, token_record, _, amount) = build_transfer(TokenStandard::NonFungible, 42, Some(Pubkey::default()), None).unwrap();
+
+        assert!(edition.is_some());
+        assert_eq!((token_record, amount), (None, 1));
+    }
+
+    #[test]
+    fn non_fungible_transfers_check_the_master_edition() {
+        assert_error(build_transfer(TokenStandard::NonFungible, 1, None, None), EscrowError::MasterEditionAccountDoesNotMatch);
+        assert_error(build_transfer(TokenStandard::NonFungible, 1, Some(Pubkey::new_unique()), None), EscrowError::MasterEditionAccountDoesNotMatch);
+    }
+
+    #[test]
+    fn programmable_transfers_use_both_token_records() {
+        let (edition, token_record, destination_token_record, amount) = build_transfer(
+            TokenStandard::ProgrammableNonFungible, 1, Some(Pubkey::default()), Some((Pubkey::default(), Pubkey::default())),
+        ).unwrap();
+
+        assert!(edition.is_some() && token_record.is_some() && destination_token_record.is_some());
+        assert_eq!(amount, 1);
+    }
+
+    #[test]
+    fn programmable_transfers_check_the_token_records() {
+        assert_error(
+            build_transfer(TokenStandard::ProgrammableNonFungible, 1, Some(Pubkey::default()), None),
+            EscrowError::TokenRecordAccountDoesNotMatch,
+        );
+        assert_error(
+            build_transfer(TokenStandard::ProgrammableNonFungible, 1, Some(Pubkey::default()), Some((Pubkey::new_unique(), Pubkey::default()))),
+            EscrowError::TokenRecordAccountDoesNotMatch,
+        );
+        assert_error(
+            build_transfer(TokenStandard::ProgrammableNonFungible, 1, Some(Pubkey::default()), Some((Pubkey::default(), Pubkey::new_unique()))),
+            EscrowError::TokenRecordAccountDoesNotMatch,
+        );
+    }
+
+    #[test]
+    fn fees_round_down_in_favour_of_the_maker() {
+        let marketplace = marketplace(333, Pubkey::new_unique());
+        let escrow = escrow(Pubkey::new_unique(), Some(250), Some(&marketplace));
+
+        assert_eq!(escrow.referral_fee(&config(0), 1_000), 25);
+        assert_eq!(escrow.referral_fee(&config(0), 39), 0);
+        assert_eq!(escrow.marketplace_fee(1_000), 33);
+        assert_eq!(escrow.marketplace_fee(u64::MAX), (u64::MAX as u128 * 333 / 10_000) as u64);
+    }
+
+    #[test]
+    fn referrals_are_optional() {
+        let taker = account_info(Pubkey::new_unique(), Pubkey::default(), vec![]);
+        let any = account_info(Pubkey::new_unique(), Pubkey::default(), vec![]);
+
+        let result = pay_referral(&escrow(Pubkey::new_unique(), Some(500), None), &config(0), 0, 100, None, &any, &taker, &any);
+
+        assert_eq!(result.unwrap(), (None, 0));
+    }
+
+    #[test]
+    fn the_taker_cant_refer_itself() {
+        let mint_b = Pubkey::new_unique();
+        let taker = account_info(Pubkey::new_unique(), Pubkey::default(), vec![]);
+        let any = account_info(Pubkey::new_unique(), Pubkey::default(), vec![]);
+        let taker_ata = token_account(taker.key(), mint_b);
+
+        let result = pay_referral(&escrow(mint_b, Some(500), None), &config(0), 0, 100, Some(&taker_ata), &any, &taker, &any);
+
+        assert_error(result, EscrowError::InvalidReferrer);
+    }
+
+    #[test]
+    fn the_referrer_is_paid_in_mint_b() {
+        let taker = account_info(Pubkey::new_unique(), Pubkey::default(), vec![]);
+        let any = account_info(Pubkey::new_unique(), Pubkey::default(), vec![]);
+        let referrer_ata = token_account(Pubkey::new_unique(), Pubkey::new_unique());
+
+        let result = pay_referral(&escrow(Pubkey::new_unique(), Some(500), None), &config(0), 0, 100, Some(&referrer_ata), &any, &taker, &any);
+
+        assert_error(result, EscrowError::InvalidReferrer);
+    }
+
+    #[test]
+    fn listings_off_a_marketplace_pay_no_fee() {
+        let any = account_info(Pubkey::new_unique(), Pubkey::default(), vec![]);
+        let marketplace = marketplace(500, Pubkey::new_unique());
+
+        let result = pay_marketplace_fee(&escrow(Pubkey::new_unique(), None, None), Some(&marketplace), 100, None, &any, &any, &any);
+
+        assert_eq!(result.unwrap(), 0);
+    }
+
+    #[test]
+    fn the_fee_is_paid_to_the_treasury_in_mint_b() {
+        let mint_b = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let any = account_info(Pubkey::new_unique(), Pubkey::default(), vec![]);
+        let marketplace = marketplace(500, treasury);
+        let escrow = escrow(mint_b, None, Some(&marketplace));
+
+        assert_error(
+            pay_marketplace_fee(&escrow, Some(&marketplace), 100, None, &any, &any, &any),
+            EscrowError::InvalidTreasury,
+        );
+        assert_error(
+            pay_marketplace_fee(&escrow, Some(&marketplace), 100, Some(&token_account(Pubkey::new_unique(), mint_b)), &any, &any, &any),
+            EscrowError::InvalidTreasury,
+        );
+        assert_error(
+            pay_marketplace_fee(&escrow, Some(&marketplace), 100, Some(&token_account(treasury, Pubkey::new_unique())), &any, &any, &any),
+            EscrowError::InvalidTreasury,
+        );
+    }
+
+    #[test]
+    fn listings_pay_the_fee_they_were_made_with() {
+        let mint_b = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let mut marketplace = marketplace(500, treasury);
+        let escrow = escrow(mint_b, None, Some(&marketplace));
+        marketplace.fee_bps = 9_000;
+
+        let result = marketplace_fee(&escrow, Some(&marketplace), 100, Some(&token_account(treasury, mint_b)));
+
+        assert_eq!(result.unwrap(), 5);
+    }
+
+    #[test]
+    fn listings_use_the_config_referral_cut_unless_they_override_it() {
+        let mint_b = Pubkey::new_unique();
+
+        assert_eq!(escrow(mint_b, None, None).referral_fee(&config(300), 1_000), 30);
+        assert_eq!(escrow(mint_b, Some(100), None).referral_fee(&config(300), 1_000), 10);
+        assert_eq!(escrow(mint_b, Some(0), None).referral_fee(&config(300), 1_000), 0);
+    }
+
+    #[test]
+    fn the_fees_cant_exceed_the_price() {
+        let mint_b = Pubkey::new_unique();
+        let taker = account_info(Pubkey::new_unique(), Pubkey::default(), vec![]);
+        let any = account_info(Pubkey::new_unique(), Pubkey::default(), vec![]);
+        let referrer_ata = token_account(Pubkey::new_unique(), mint_b);
+        let marketplace = marketplace(5_000, Pubkey::new_unique());
+        let escrow = escrow(mint_b, None, Some(&marketplace));
+
+        assert!(check_fees(2_000, 5_000, 3_000).is_ok());
+        assert_error(check_fees(2_000, 5_000, 3_001), EscrowError::FeesExceedPrice);
+        assert_error(check_fees(u16::MAX, 0, 0), EscrowError::FeesExceedPrice);
+
+        // The referral cut of the config counts as soon as there is a referrer
+        assert_error(
+            pay_referral(&escrow, &config(2_001), 3_000, 100, Some(&referrer_ata), &any, &taker, &any),
+            EscrowError::FeesExceedPrice,
+        );
+        // Royalties and the marketplace fee are checked even without one
+        assert_error(
+            pay_referral(&escrow, &config(2_001), 5_001, 100, None, &any, &taker, &any),
+            EscrowError::FeesExceedPrice,
+        );
+        assert_eq!(pay_referral(&escrow, &config(2_001), 5_000, 100, None, &any, &taker, &any).unwrap(), (None, 0));
+    }
+
+    #[test]
+    fn editions_move_one_at_a_time() {
+        assert_eq!(transfer_amount(&TokenStandard::NonFungible, 42), 1);
+        assert_eq!(transfer_amount(&TokenStandard::ProgrammableNonFungible, 42), 1);
+        assert_eq!(transfer_amount(&TokenStandard::Fungible, 42), 42);
+        assert_eq!(transfer_amount(&TokenStandard::FungibleAsset, 42), 42);
+    }
+}
@@ -0,0 +1,257 @@
+use anchor_lang::prelude::*;
+use anchor_nft_escrow::{
+    cpi::{self, accounts::{Make, TakeAtomic}},
+    program::NftEscrow,
+};
+
+declare_id!("6WwnjoJajaNcFSCcfYUxs2jaDdxGrEYsUNT6D57sinR2");
+
+// A minimal program that lists and buys through the escrow with a PDA, the way a vault or a DAO treasury would.
+// The PDA has no data, so it stays owned by the System Program and can pay for the accounts the escrow creates
+#[program]
+pub mod escrow_cpi_example {
+    use super::*;
+
+    pub fn list(ctx: Context<List>, maker_amount: u64, taker_amount: u64) -> Result<()> {
+        ctx.accounts.list(maker_amount, taker_amount, ctx.bumps)
+    }
+
+    pub fn buy(ctx: Context<Buy>) -> Result<()> {
+        ctx.accounts.buy(ctx.bumps)
+    }
+}
+
+// Every escrow account is forwarded as is, the escrow program validates them
+#[derive(Accounts)]
+pub struct List<'info> {
+    #[account(
+        mut,
+        seeds = [b"authority"],
+        bump,
+    )]
+    pub authority: SystemAccount<'info>,
+
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub maker_ata: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub mint_a: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub metadata_a: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub master_edition_a: Option<UncheckedAccount<'info>>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub maker_token_record_a: Option<UncheckedAccount<'info>>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub vault_token_record_a: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub mint_b: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub metadata_b: Option<UncheckedAccount<'info>>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub escrow: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub config: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub mint_denylist: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+    /// CHECK: validated by the escrow program
+    pub marketplace: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: validated by the escrow program
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub token_metadata_program: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub token_program: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub associated_token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub escrow_program: Program<'info, NftEscrow>,
+}
+
+impl<'info> List<'info> {
+    pub fn list(
+        &mut self,
+        maker_amount: u64,
+        taker_amount: u64,
+        bumps: ListBumps,
+    ) -> Result<()> {
+
+        let seeds = &[
+            "authority".as_bytes(),
+            &[bumps.authority]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = Make {
+            maker: self.authority.to_account_info(),
+            payer: self.authority.to_account_info(),
+            proceeds_recipient: None,
+            maker_ata: self.maker_ata.to_account_info(),
+            mint_a: self.mint_a.to_account_info(),
+            metadata_a: self.metadata_a.to_account_info(),
+            master_edition_a: self.master_edition_a.as_ref().map(|account| account.to_account_info()),
+            maker_token_record_a: self.maker_token_record_a.as_ref().map(|account| account.to_account_info()),
+            vault_token_record_a: self.vault_token_record_a.to_account_info(),
+            mint_b: self.mint_b.to_account_info(),
+            metadata_b: self.metadata_b.as_ref().map(|account| account.to_account_info()),
+            vault: self.vault.to_account_info(),
+            escrow: self.escrow.to_account_info(),
+            config: self.config.to_account_info(),
+            mint_denylist: self.mint_denylist.to_account_info(),
+            collection_denylist: self.collection_denylist.as_ref().map(|account| account.to_account_info()),
+            marketplace: self.marketplace.as_ref().map(|account| account.to_account_info()),
+            marketplace_authority: None,
+            sysvar_instructions: self.sysvar_instructions.to_account_info(),
+            token_metadata_program: self.token_metadata_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            associated_token_program: self.associated_token_program.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.escrow_program.to_account_info(), cpi_accounts, signer_seeds);
+
+        cpi::make(cpi_ctx, maker_amount, taker_amount, None, None)
+    }
+}
+
+#[derive(Accounts)]
+pub struct Buy<'info> {
+    #[account(
+        mut,
+        seeds = [b"authority"],
+        bump,
+    )]
+    pub authority: SystemAccount<'info>,
+
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub proceeds_recipient: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub mint_a: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub mint_b: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub metadata_a: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub master_edition_a: Option<UncheckedAccount<'info>>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub origin_token_record_a: Option<UncheckedAccount<'info>>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub taker_token_record_a: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub metadata_b: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub master_edition_b: Option<UncheckedAccount<'info>>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub taker_token_record_b: Option<UncheckedAccount<'info>>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub maker_token_record_b: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub origin_ata_a: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub taker_ata_a: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub taker_ata_b: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub treasury_ata_b: Option<UncheckedAccount<'info>>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub maker_ata_b: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub escrow: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub config: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub mint_denylist: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub collection_denylist: Option<UncheckedAccount<'info>>,
+    /// CHECK: validated by the escrow program
+    pub marketplace: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: validated by the escrow program
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub token_metadata_program: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub token_program: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub associated_token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub escrow_program: Program<'info, NftEscrow>,
+}
+
+impl<'info> Buy<'info> {
+    pub fn buy(
+        &mut self,
+        bumps: BuyBumps,
+    ) -> Result<()> {
+
+        let seeds = &[
+            "authority".as_bytes(),
+            &[bumps.authority]
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_accounts = TakeAtomic {
+            maker: self.maker.to_account_info(),
+            payer: self.payer.to_account_info(),
+            proceeds_recipient: self.proceeds_recipient.to_account_info(),
+            taker: self.authority.to_account_info(),
+            mint_a: self.mint_a.to_account_info(),
+            mint_b: self.mint_b.to_account_info(),
+            metadata_a: self.metadata_a.to_account_info(),
+            master_edition_a: self.master_edition_a.as_ref().map(|account| account.to_account_info()),
+            origin_token_record_a: self.origin_token_record_a.as_ref().map(|account| account.to_account_info()),
+            taker_token_record_a: self.taker_token_record_a.to_account_info(),
+            metadata_b: self.metadata_b.to_account_info(),
+            master_edition_b: self.master_edition_b.as_ref().map(|account| account.to_account_info()),
+            taker_token_record_b: self.taker_token_record_b.as_ref().map(|account| account.to_account_info()),
+            maker_token_record_b: self.maker_token_record_b.to_account_info(),
+            origin_ata_a: self.origin_ata_a.to_account_info(),
+            taker_ata_a: self.taker_ata_a.to_account_info(),
+            taker_ata_b: self.taker_ata_b.to_account_info(),
+            referrer_ata_b: None,
+            treasury_ata_b: self.treasury_ata_b.as_ref().map(|account| account.to_account_info()),
+            maker_ata_b: self.maker_ata_b.to_account_info(),
+            escrow: self.escrow.to_account_info(),
+            config: self.config.to_account_info(),
+            mint_denylist: self.mint_denylist.to_account_info(),
+            collection_denylist: self.collection_denylist.as_ref().map(|account| account.to_account_info()),
+            marketplace: self.marketplace.as_ref().map(|account| account.to_account_info()),
+            sysvar_instructions: self.sysvar_instructions.to_account_info(),
+            token_metadata_program: self.token_metadata_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            associated_token_program: self.associated_token_program.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.escrow_program.to_account_info(), cpi_accounts, signer_seeds);
+
+        cpi::take_atomic(cpi_ctx)
+    }
+}
\ No newline at end of file
@@ -6,13 +6,16 @@ import { PublicKey,
   SystemProgram, 
   LAMPORTS_PER_SOL,
   Transaction,
+  Ed25519Program,
 } from "@solana/web3.js";
 
 import {
   createFungible,
   createNft, 
   createProgrammableNft, 
-  mplTokenMetadata
+  mplTokenMetadata,
+  verifyCollectionV1,
+  findMetadataPda,
 } from "@metaplex-foundation/mpl-token-metadata";
 
 import {
@@ -21,7 +24,10 @@ import {
   ASSOCIATED_TOKEN_PROGRAM_ID,
   getAssociatedTokenAddressSync,
   mintTo,
-  createAssociatedTokenAccountIdempotentInstruction
+  approve,
+  createAssociatedTokenAccountIdempotentInstruction,
+  getAccount,
+  NATIVE_MINT,
 } from "@solana/spl-token";
 
 import {Connection} from "@solana/web3.js";
@@ -30,15 +36,42 @@ import {
   MPL_TOKEN_METADATA_PROGRAM_ID
 } from '@metaplex-foundation/mpl-token-metadata';
 
+import {
+  create as createCoreAsset,
+  mplCore,
+  ruleSet,
+  MPL_CORE_PROGRAM_ID
+} from "@metaplex-foundation/mpl-core";
+
+import {
+  createTree,
+  mintV1,
+  mplBubblegum,
+  hashLeaf,
+  hashMetadataData,
+  hashMetadataCreators,
+  getMerkleProof,
+  getMerkleRoot,
+  findLeafAssetIdPda,
+  findTreeConfigPda,
+  MPL_BUBBLEGUM_PROGRAM_ID,
+  MetadataArgsArgs,
+  getMetadataArgsSerializer,
+} from "@metaplex-foundation/mpl-bubblegum";
+
 import { createUmi } from "@metaplex-foundation/umi-bundle-defaults"
 
 import { base58 } from "@metaplex-foundation/umi/serializers";
 
+import { keccak_256 } from "@noble/hashes/sha3";
+
 import { 
   createSignerFromKeypair, 
   generateSigner, 
   percentAmount, 
-  signerIdentity 
+  signerIdentity,
+  some,
+  publicKey,
 } from "@metaplex-foundation/umi";
 
 
@@ -55,6 +88,12 @@ describe("anchor-nft-escrow", () => {
   const programId = new PublicKey("2VYDrwoKRKNgvmQo3DHfcLfQFAuijjNEAY9ZoXfv8GfZ");
   const program = new anchor.Program<NftEscrow>(IDL, programId, provider);
 
+  // The program-wide config, created by the upgrade authority of the program
+  const config = PublicKey.findProgramAddressSync([Buffer.from('config')], programId)[0];
+  const findDenylist = (key: PublicKey): PublicKey => PublicKey.findProgramAddressSync([Buffer.from('denylist'), key.toBuffer()], programId)[0];
+  const admin = (anchor.AnchorProvider.env().wallet as anchor.Wallet).payer;
+  const programData = PublicKey.findProgramAddressSync([programId.toBuffer()], new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111"))[0];
+
   // Helpers
   function wait(ms: number) {
     return new Promise( resolve => setTimeout(resolve, ms) );
@@ -74,6 +113,77 @@ describe("anchor-nft-escrow", () => {
     return signature;
   }
 
+  // Fails unless the transaction is rejected by the escrow program with the given error code
+  const expectError = async (promise: Promise<any>, code: string) => {
+    try {
+      await promise;
+    } catch(e) {
+      const error = e instanceof anchor.AnchorError ? e : anchor.AnchorError.parse(e.logs);
+      const actual = error?.error.errorCode.code;
+      if (actual != code) throw new Error(`Expected ${code}, got ${actual ?? e}`);
+      return;
+    }
+    throw new Error(`Expected the transaction to fail with ${code}`);
+  }
+
+  const createAsset = async (owner: anchor.web3.Keypair, kind: "ft" | "nft" | "pnft", amount = 100): Promise<PublicKey> => {
+    const umi = createUmi(connection.rpcEndpoint);
+    let umiKeypair = umi.eddsa.createKeypairFromSecretKey(owner.secretKey);
+    const signerKeypair = createSignerFromKeypair(umi, umiKeypair);
+    umi.use(signerIdentity(signerKeypair));
+    umi.use(mplTokenMetadata())
+    const mint = generateSigner(umi);
+
+    const create = kind == "ft" ? createFungible : kind == "nft" ? createNft : createProgrammableNft;
+    await create(
+      umi, 
+      {
+        mint: mint,
+        authority: signerKeypair,
+        updateAuthority: umiKeypair.publicKey,
+        name: "NFT Example",
+        symbol: "EXM",
+        uri: "",
+        sellerFeeBasisPoints: percentAmount(0),
+        creators: [
+            {address: umiKeypair.publicKey, verified: true, share: 100 }
+        ],
+        collection: null,
+        uses: null,
+        isMutable: true,
+        collectionDetails: null,
+      }
+    ).sendAndConfirm(umi, { send: { skipPreflight: true }, confirm: { commitment } });
+
+    if (kind == "ft") {
+      let ata = await getOrCreateAssociatedTokenAccount(connection, owner, new PublicKey(mint.publicKey), owner.publicKey);
+      await mintTo(connection, owner, new PublicKey(mint.publicKey), ata.address, owner.publicKey, amount);
+    }
+
+    return new PublicKey(mint.publicKey);
+  }
+
+  const findMetadata = (mint: PublicKey): PublicKey => PublicKey.findProgramAddressSync([
+    Buffer.from('metadata'),
+    new PublicKey(MPL_TOKEN_METADATA_PROGRAM_ID).toBuffer(),
+    mint.toBuffer(),
+  ], new PublicKey(MPL_TOKEN_METADATA_PROGRAM_ID))[0];
+
+  const findMasterEdition = (mint: PublicKey): PublicKey => PublicKey.findProgramAddressSync([
+    Buffer.from('metadata'),
+    new PublicKey(MPL_TOKEN_METADATA_PROGRAM_ID).toBuffer(),
+    mint.toBuffer(),
+    Buffer.from("edition"),
+  ], new PublicKey(MPL_TOKEN_METADATA_PROGRAM_ID))[0];
+
+  const findTokenRecord = (mint: PublicKey, ata: PublicKey): PublicKey => PublicKey.findProgramAddressSync([
+    Buffer.from('metadata'),
+    new PublicKey(MPL_TOKEN_METADATA_PROGRAM_ID).toBuffer(),
+    mint.toBuffer(),
+    Buffer.from("token_record"),
+    ata.toBuffer(),
+  ], new PublicKey(MPL_TOKEN_METADATA_PROGRAM_ID))[0];
+
   //Variables
   let makerAtaA: anchor.web3.PublicKey;
   let makerAtaB: anchor.web3.PublicKey;
@@ -109,6 +219,19 @@ describe("anchor-nft-escrow", () => {
     .then(log)
   })
 
+  it("Initialize the Config", async () => {
+    await program.methods
+    .initializeConfig()
+    .accounts({
+      admin: admin.publicKey,
+      config,
+      program: programId,
+      programData,
+      systemProgram: SystemProgram.programId,
+    })
+    .signers([admin]).rpc({skipPreflight: true}).then(confirm).then(log);
+  })
+
   describe("FT escrow", () => {
 
     it("Creates a FtA", async () => {
@@ -274,9 +397,16 @@ describe("anchor-nft-escrow", () => {
       );
 
       const signature = await program.methods
-      .make(new anchor.BN(10), new anchor.BN(10))
+      .make(new anchor.BN(10), new anchor.BN(10), null, null)
       .accounts({
         maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        marketplaceAuthority: null,
+        proceedsRecipient: null,
         makerAta: makerAtaA,
         mintA,
         metadataA,
@@ -301,6 +431,7 @@ describe("anchor-nft-escrow", () => {
       .close()
       .accounts({
         maker: maker.publicKey,
+        payer: maker.publicKey,
         makerAta: makerAtaA,
         mintA,
         mintB,
@@ -309,6 +440,7 @@ describe("anchor-nft-escrow", () => {
         makerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
         vaultTokenRecordA: null,
         vault,
+        payees: null,
         escrow,
         sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
         tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
@@ -324,7 +456,12 @@ describe("anchor-nft-escrow", () => {
       const take1 = await program.methods
       .takeFromEscrow()
       .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
         maker: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
         taker: taker.publicKey,
         mintA,
         mintB,
@@ -346,7 +483,12 @@ describe("anchor-nft-escrow", () => {
       const take2 = await program.methods
       .takerToMaker(new anchor.BN(10))
       .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
         maker: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
         taker: taker.publicKey,
         mintA,
         mintB,
@@ -557,9 +699,16 @@ describe("anchor-nft-escrow", () => {
       );
 
       const signature = await program.methods
-      .make(new anchor.BN(10), new anchor.BN(10))
+      .make(new anchor.BN(10), new anchor.BN(10), null, null)
       .accounts({
         maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        marketplaceAuthority: null,
+        proceedsRecipient: null,
         makerAta: makerAtaA,
         mintA,
         metadataA,
@@ -584,6 +733,7 @@ describe("anchor-nft-escrow", () => {
       .close()
       .accounts({
         maker: maker.publicKey,
+        payer: maker.publicKey,
         makerAta: makerAtaA,
         mintA,
         mintB,
@@ -592,6 +742,7 @@ describe("anchor-nft-escrow", () => {
         makerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
         vaultTokenRecordA: null,
         vault,
+        payees: null,
         escrow,
         sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
         tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
@@ -607,7 +758,12 @@ describe("anchor-nft-escrow", () => {
       const take1 = await program.methods
       .takeFromEscrow()
       .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
         maker: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
         taker: taker.publicKey,
         mintA,
         mintB,
@@ -629,7 +785,12 @@ describe("anchor-nft-escrow", () => {
       const take2 = await program.methods
       .takerToMaker(new anchor.BN(1))
       .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
         maker: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
         taker: taker.publicKey,
         mintA,
         mintB,
@@ -884,9 +1045,16 @@ describe("anchor-nft-escrow", () => {
       vaultTokenRecordA = PublicKey.findProgramAddressSync(taker_token_record_seeds, new PublicKey(MPL_TOKEN_METADATA_PROGRAM_ID))[0];
 
       const signature = await program.methods
-      .make(new anchor.BN(10), new anchor.BN(10))
+      .make(new anchor.BN(10), new anchor.BN(10), null, null)
       .accounts({
         maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        marketplaceAuthority: null,
+        proceedsRecipient: null,
         makerAta: makerAtaA,
         mintA,
         metadataA,
@@ -911,6 +1079,7 @@ describe("anchor-nft-escrow", () => {
       .close()
       .accounts({
         maker: maker.publicKey,
+        payer: maker.publicKey,
         makerAta: makerAtaA,
         mintA,
         mintB,
@@ -919,6 +1088,7 @@ describe("anchor-nft-escrow", () => {
         makerTokenRecordA,
         vaultTokenRecordA,
         vault,
+        payees: null,
         escrow,
         sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
         tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
@@ -934,7 +1104,12 @@ describe("anchor-nft-escrow", () => {
       const take1 = await program.methods
       .takeFromEscrow()
       .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
         maker: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
         taker: taker.publicKey,
         mintA,
         mintB,
@@ -956,7 +1131,12 @@ describe("anchor-nft-escrow", () => {
       const take2 = await program.methods
       .takerToMaker(new anchor.BN(1))
       .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
         maker: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
         taker: taker.publicKey,
         mintA,
         mintB,
@@ -992,5 +1172,3118 @@ describe("anchor-nft-escrow", () => {
     });
     
   });
+
+  describe("Trait bid", () => {
+    let bid: anchor.web3.PublicKey;
+    let bidVault: anchor.web3.PublicKey;
+    let merkleRoot: number[];
+
+    it("Creates a NftA and a FtB", async () => {
+      mintA = await createAsset(maker, "nft");
+      mintB = await createAsset(taker, "ft");
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+
+      metadataA = findMetadata(mintA);
+      masterEditionA = findMasterEdition(mintA);
+    });
+
+    it("Make Bid", async () => {
+      // A single leaf tree: the root is the leaf itself and the proof is empty
+      merkleRoot = Array.from(keccak_256(mintA.toBuffer()));
+
+      bid = PublicKey.findProgramAddressSync([
+        Buffer.from('bid'),
+        taker.publicKey.toBuffer(),
+        mintB.toBuffer(),
+        Buffer.from(merkleRoot),
+      ], programId)[0];
+
+      bidVault = getAssociatedTokenAddressSync(mintB, bid, true);
+
+      await program.methods
+      .makeBid(new anchor.BN(10), merkleRoot)
+      .accounts({
+        config,
+        bidder: taker.publicKey,
+        bidderAta: takerAtaB,
+        mintB,
+        bidVault,
+        bid,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    const takeBid = async (mintA: PublicKey, proof: number[][]) => {
+      await program.methods
+      .takeBid(proof)
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        seller: maker.publicKey,
+        bidder: taker.publicKey,
+        sellerAtaA: getAssociatedTokenAddressSync(mintA, maker.publicKey),
+        sellerAtaB: makerAtaB,
+        bidderAtaA: getAssociatedTokenAddressSync(mintA, taker.publicKey),
+        mintA,
+        mintB,
+        metadataA: findMetadata(mintA),
+        masterEditionA: findMasterEdition(mintA),
+        sellerTokenRecordA: null,
+        bidderTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        bidVault,
+        bid,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    it("An NFT outside the trait set can't fill the Bid", async () => {
+      const outsider = await createAsset(maker, "nft");
+      await expectError(takeBid(outsider, []), "InvalidMerkleProof");
+    });
+
+    it("A bad proof can't fill the Bid", async () => {
+      await expectError(takeBid(mintA, [Array.from(keccak_256(Buffer.from("not a sibling")))]), "InvalidMerkleProof");
+    });
+
+    it("Take Bid", async () => {
+      await takeBid(mintA, []);
+    });
+
+  });
+
+  describe("Offer", () => {
+    let offer: anchor.web3.PublicKey;
+    let offerVault: anchor.web3.PublicKey;
+
+    it("Creates a NftA and a FtB", async () => {
+      mintA = await createAsset(maker, "nft");
+      mintB = await createAsset(taker, "ft");
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+
+      metadataA = findMetadata(mintA);
+      masterEditionA = findMasterEdition(mintA);
+
+      offer = PublicKey.findProgramAddressSync([
+        Buffer.from('offer'),
+        taker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+
+      offerVault = getAssociatedTokenAddressSync(mintB, offer, true);
+    });
+
+    it("Make Offer", async () => {
+      await program.methods
+      .makeOffer(new anchor.BN(10))
+      .accounts({
+        config,
+        buyer: taker.publicKey,
+        buyerAta: takerAtaB,
+        mintA,
+        mintB,
+        offerVault,
+        offer,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    it("Cancel Offer", async () => {
+      await program.methods
+      .cancelOffer()
+      .accounts({
+        buyer: taker.publicKey,
+        buyerAta: takerAtaB,
+        mintA,
+        mintB,
+        offerVault,
+        offer,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    it("Make Offer again", async () => {
+      await program.methods
+      .makeOffer(new anchor.BN(10))
+      .accounts({
+        config,
+        buyer: taker.publicKey,
+        buyerAta: takerAtaB,
+        mintA,
+        mintB,
+        offerVault,
+        offer,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    it("Accept Offer", async () => {
+      await program.methods
+      .acceptOffer()
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        seller: maker.publicKey,
+        buyer: taker.publicKey,
+        sellerAtaA: makerAtaA,
+        sellerAtaB: makerAtaB,
+        buyerAtaA: takerAtaA,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA,
+        sellerTokenRecordA: null,
+        buyerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        offerVault,
+        offer,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+  });
+
+  describe("Dutch auction", () => {
+    const startPrice = new anchor.BN(2000);
+    const endPrice = new anchor.BN(1000);
+
+    const now = async (): Promise<number> => {
+      return await connection.getBlockTime(await connection.getSlot());
+    }
+
+    // Lists 10 FtA for a fresh FtB with the auction running from now + startOffset to now + endOffset
+    const makeDutch = async (startOffset: number, endOffset: number) => {
+      mintA = await createAsset(maker, "ft");
+      mintB = await createAsset(taker, "ft");
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+      metadataA = findMetadata(mintA);
+      metadataB = findMetadata(mintB);
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+      vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+
+      const timestamp = await now();
+
+      await program.methods
+      .make(new anchor.BN(10), startPrice, {
+        startPrice,
+        endPrice,
+        startTime: new anchor.BN(timestamp + startOffset),
+        endTime: new anchor.BN(timestamp + endOffset),
+      }, null)
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        marketplaceAuthority: null,
+        proceedsRecipient: null,
+        makerAta: makerAtaA,
+        mintA,
+        metadataA,
+        masterEditionA: null,
+        makerTokenRecordA: null,
+        vaultTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        mintB,
+        metadataB,
+        vault,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    const takeDutch = async (price: anchor.BN) => {
+      const take1 = await program.methods
+      .takeFromEscrow()
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        maker: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
+        taker: taker.publicKey,
+        mintA,
+        mintB,
+        metadata: metadataA,
+        masterEdition: null,
+        originTokenRecord: null,
+        destinationTokenRecord: anchor.web3.Keypair.generate().publicKey,
+        originAta: vault,
+        destinationAta: takerAtaA,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction()
+
+      const take2 = await program.methods
+      .takerToMaker(price)
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        maker: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
+        taker: taker.publicKey,
+        mintA,
+        mintB,
+        metadata: metadataB,
+        masterEdition: null,
+        originTokenRecord: null,
+        destinationTokenRecord: anchor.web3.Keypair.generate().publicKey,
+        originAta: takerAtaB,
+        destinationAta: makerAtaB,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction()
+
+      let tx = new Transaction();
+
+      tx.instructions = [
+        createAssociatedTokenAccountIdempotentInstruction(taker.publicKey, takerAtaA, taker.publicKey, mintA),
+        createAssociatedTokenAccountIdempotentInstruction(taker.publicKey, makerAtaB, maker.publicKey, mintB),
+        take1,
+        take2
+      ]
+
+      await provider.sendAndConfirm(tx, [ taker ]).then(confirm).then(log);
+    }
+
+    it("Charges the start price before the auction starts", async () => {
+      await makeDutch(1_000_000, 2_000_000);
+      await expectError(takeDutch(endPrice), "InvalidAmount");
+      await takeDutch(startPrice);
+    });
+
+    it("Charges the decayed price while the auction runs", async () => {
+      // Halfway through a 2_000_000s auction, the price only moves by 1 every 2000s
+      await makeDutch(-1_000_000, 1_000_000);
+      await expectError(takeDutch(startPrice), "InvalidAmount");
+      await takeDutch(new anchor.BN(1500));
+    });
+
+    it("Charges the end price after the auction ends", async () => {
+      await makeDutch(-2_000_000, -1_000_000);
+      await expectError(takeDutch(startPrice), "InvalidAmount");
+      await takeDutch(endPrice);
+    });
+
+  });
+
+  describe("English auction", () => {
+    let bidVault: anchor.web3.PublicKey;
+
+    it("Make Auction", async () => {
+      mintA = await createAsset(maker, "nft");
+      mintB = await createAsset(taker, "ft");
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+      metadataA = findMetadata(mintA);
+      masterEditionA = findMasterEdition(mintA);
+      metadataB = findMetadata(mintB);
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+      vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+      bidVault = getAssociatedTokenAddressSync(mintB, escrow, true);
+
+      const timestamp = await connection.getBlockTime(await connection.getSlot());
+
+      await program.methods
+      .makeAuction(new anchor.BN(1), new anchor.BN(10), new anchor.BN(timestamp + 10), new anchor.BN(0), 500)
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        marketplaceAuthority: null,
+        proceedsRecipient: null,
+        makerAta: makerAtaA,
+        mintA,
+        metadataA,
+        masterEditionA,
+        makerTokenRecordA: null,
+        vaultTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        mintB,
+        metadataB,
+        vault,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    it("Place Bids", async () => {
+      const placeBid = (amount: number, previousBidderAta: PublicKey | null) => program.methods
+      .placeBid(new anchor.BN(amount))
+      .accounts({
+        config,
+        bidder: taker.publicKey,
+        bidderAta: takerAtaB,
+        maker: maker.publicKey,
+        mintA,
+        mintB,
+        metadataA: findMetadata(mintA),
+        bidVault,
+        previousBidderAta,
+        escrow,
+        marketplace: null,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      await placeBid(10, null);
+
+      // 5% of 10 rounds up to 1, so the next bid needs to be at least 11
+      let failed = false;
+      try {
+        await placeBid(10, takerAtaB);
+      } catch(e) {
+        failed = true;
+      }
+      if (!failed) throw new Error("Expected the bid to be rejected");
+
+      await placeBid(11, takerAtaB);
+    });
+
+    it("Settle", async () => {
+      await wait(15_000);
+
+      // The taker placed the first bid and paid for the bid vault
+      const bidVaultRent = (await connection.getAccountInfo(bidVault)).lamports;
+      const takerBalance = await connection.getBalance(taker.publicKey);
+
+      // The bids are locked until the auction settles, so a pause doesn't block it
+      await program.methods
+      .setPaused(true)
+      .accounts({
+        admin: admin.publicKey,
+        config,
+      })
+      .signers([admin]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      await program.methods
+      .settle()
+      .accounts({
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        cranker: maker.publicKey,
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
+        winner: taker.publicKey,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA,
+        vaultTokenRecordA: null,
+        winnerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        vault,
+        winnerAtaA: takerAtaA,
+        bidVault,
+        bidVaultPayer: taker.publicKey,
+        makerAtaB,
+        treasuryAtaB: null,
+        escrow,
+        marketplace: null,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log)
+      .finally(() => program.methods
+        .setPaused(false)
+        .accounts({
+          admin: admin.publicKey,
+          config,
+        })
+        .signers([admin]).rpc({skipPreflight: true}).then(confirm).then(log));
+
+      // The emptied vault is closed with the escrow
+      if (await connection.getAccountInfo(vault) != null) throw new Error("Expected the vault to be closed");
+
+      // The bid vault rent goes back to the taker, not to the maker
+      if (await connection.getAccountInfo(bidVault) != null) throw new Error("Expected the bid vault to be closed");
+      const refunded = await connection.getBalance(taker.publicKey) - takerBalance;
+      if (refunded != bidVaultRent) throw new Error(`Unexpected bid vault rent refund ${refunded}`);
+    });
+
+    it("A bid inside the extension window pushes the end back", async () => {
+      mintA = await createAsset(maker, "nft");
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      metadataA = findMetadata(mintA);
+      masterEditionA = findMasterEdition(mintA);
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+      vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+      bidVault = getAssociatedTokenAddressSync(mintB, escrow, true);
+
+      // Every bid of an auction ending in 60s lands in its 600s window
+      const timestamp = await connection.getBlockTime(await connection.getSlot());
+      const endTime = timestamp + 60;
+
+      await program.methods
+      .makeAuction(new anchor.BN(1), new anchor.BN(10), new anchor.BN(endTime), new anchor.BN(600), 500)
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        marketplaceAuthority: null,
+        proceedsRecipient: null,
+        makerAta: makerAtaA,
+        mintA,
+        metadataA,
+        masterEditionA,
+        makerTokenRecordA: null,
+        vaultTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        mintB,
+        metadataB,
+        vault,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      await program.methods
+      .placeBid(new anchor.BN(10))
+      .accounts({
+        config,
+        bidder: taker.publicKey,
+        bidderAta: takerAtaB,
+        maker: maker.publicKey,
+        mintA,
+        mintB,
+        metadataA: findMetadata(mintA),
+        bidVault,
+        previousBidderAta: null,
+        escrow,
+        marketplace: null,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      const extendedEndTime = (await program.account.escrow.fetch(escrow)).englishAuction.endTime.toNumber();
+      if (extendedEndTime < endTime + 500) throw new Error(`Expected the end to move back, got ${extendedEndTime}`);
+    });
+
+  });
+
+  describe("Delegated pNFT listing", () => {
+
+    it("Creates a PNftA and a FtB", async () => {
+      mintA = await createAsset(maker, "pnft");
+      mintB = await createAsset(taker, "ft");
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      metadataA = findMetadata(mintA);
+      masterEditionA = findMasterEdition(mintA);
+      makerTokenRecordA = findTokenRecord(mintA, makerAtaA);
+      metadataB = findMetadata(mintB);
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+    });
+
+    it("Make Delegated", async () => {
+      await program.methods
+      .makeDelegated(new anchor.BN(10))
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        proceedsRecipient: null,
+        makerAta: makerAtaA,
+        mintA,
+        metadataA,
+        masterEditionA,
+        makerTokenRecordA,
+        mintB,
+        metadataB,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    it("Close Delegated", async () => {
+      await program.methods
+      .closeDelegated()
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        makerAta: makerAtaA,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA,
+        makerTokenRecordA,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+  });
+
+  describe("Delegated NFT listing", () => {
+
+    const makeDelegated = async () => {
+      await program.methods
+      .makeDelegated(new anchor.BN(10))
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        proceedsRecipient: null,
+        makerAta: makerAtaA,
+        mintA,
+        metadataA,
+        masterEditionA,
+        makerTokenRecordA: null,
+        mintB,
+        metadataB,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    it("Creates a NftA and a FtB", async () => {
+      mintA = await createAsset(maker, "nft");
+      mintB = await createAsset(taker, "ft");
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+      metadataA = findMetadata(mintA);
+      masterEditionA = findMasterEdition(mintA);
+      metadataB = findMetadata(mintB);
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+    });
+
+    it("Make Delegated", async () => {
+      await makeDelegated();
+    });
+
+    it("Close Delegated", async () => {
+      await program.methods
+      .closeDelegated()
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        makerAta: makerAtaA,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA,
+        makerTokenRecordA: null,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    it("Take from a Delegated listing", async () => {
+      await makeDelegated();
+
+      const take1 = await program.methods
+      .takeFromEscrow()
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        maker: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
+        taker: taker.publicKey,
+        mintA,
+        mintB,
+        metadata: metadataA,
+        masterEdition: masterEditionA,
+        originTokenRecord: null,
+        destinationTokenRecord: anchor.web3.Keypair.generate().publicKey,
+        originAta: makerAtaA,
+        destinationAta: takerAtaA,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction()
+
+      const take2 = await program.methods
+      .takerToMaker(new anchor.BN(10))
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        maker: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
+        taker: taker.publicKey,
+        mintA,
+        mintB,
+        metadata: metadataB,
+        masterEdition: null,
+        originTokenRecord: null,
+        destinationTokenRecord: anchor.web3.Keypair.generate().publicKey,
+        originAta: takerAtaB,
+        destinationAta: makerAtaB,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction()
+
+      let tx = new Transaction();
+
+      tx.instructions = [
+        createAssociatedTokenAccountIdempotentInstruction(taker.publicKey, takerAtaA, taker.publicKey, mintA),
+        createAssociatedTokenAccountIdempotentInstruction(taker.publicKey, makerAtaB, maker.publicKey, mintB),
+        take1,
+        take2
+      ]
+
+      await provider.sendAndConfirm(tx, [ taker ]).then(confirm).then(log);
+    });
+
+  });
+
+  describe("Core asset", () => {
+
+    let asset: PublicKey;
+
+    const makeCore = async () => {
+      await program.methods
+      .makeCore(new anchor.BN(10))
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        assetDenylist: findDenylist(asset),
+        collectionDenylist: null,
+        proceedsRecipient: null,
+        asset,
+        collection: null,
+        mintB,
+        escrow,
+        coreProgram: MPL_CORE_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    it("Creates a Core Asset with Royalties and a FtB", async () => {
+      const umi = createUmi(connection.rpcEndpoint);
+      let umiKeypair = umi.eddsa.createKeypairFromSecretKey(maker.secretKey);
+      const signerKeypair = createSignerFromKeypair(umi, umiKeypair);
+      umi.use(signerIdentity(signerKeypair));
+      umi.use(mplCore())
+      const assetSigner = generateSigner(umi);
+
+      await createCoreAsset(umi, {
+        asset: assetSigner,
+        name: "Core Example",
+        uri: "",
+        plugins: [
+          {
+            type: "Royalties",
+            basisPoints: 500,
+            creators: [{ address: umiKeypair.publicKey, percentage: 100 }],
+            ruleSet: ruleSet("None"),
+          },
+        ],
+      }).sendAndConfirm(umi, { send: { skipPreflight: true }, confirm: { commitment } });
+
+      asset = new PublicKey(assetSigner.publicKey);
+      mintB = await createAsset(taker, "ft");
+
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        asset.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+    });
+
+    it("Make Core", async () => {
+      await makeCore();
+    });
+
+    it("Close Core", async () => {
+      await program.methods
+      .closeCore()
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        asset,
+        collection: null,
+        mintB,
+        escrow,
+        coreProgram: MPL_CORE_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    it("Take Core paying Royalties", async () => {
+      await makeCore();
+
+      // The maker is also the only creator, so the royalties end up in its ATA
+      await program.methods
+      .takeCore()
+      .accounts({
+        config,
+        assetDenylist: findDenylist(asset),
+        collectionDenylist: null,
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
+        taker: taker.publicKey,
+        asset,
+        collection: null,
+        mintB,
+        takerAtaB,
+        referrerAtaB: null,
+        makerAtaB,
+        escrow,
+        coreProgram: MPL_CORE_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts([
+        { pubkey: makerAtaB, isSigner: false, isWritable: true },
+      ])
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+  });
+
+  describe("Compressed NFT", () => {
+
+    const maxDepth = 3;
+    const noop = new PublicKey("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+    const compression = new PublicKey("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+
+    const umi = createUmi(connection.rpcEndpoint);
+    let merkleTree: PublicKey;
+    let treeConfig: PublicKey;
+    let assetId: PublicKey;
+    let metadata: MetadataArgsArgs;
+
+    // We keep track of the leaves ourselves so we don't need an indexer for the proofs
+    let leaves: PublicKey[] = [];
+
+    const setLeaf = (owner: PublicKey, delegate: PublicKey) => {
+      leaves[0] = new PublicKey(hashLeaf(umi, {
+        merkleTree: merkleTree.toBase58() as any,
+        owner: owner.toBase58() as any,
+        delegate: delegate.toBase58() as any,
+        leafIndex: 0,
+        metadata,
+      }));
+    }
+
+    const leafArgs = () => ({
+      root: Array.from(getMerkleRoot(leaves, maxDepth).toBytes()),
+      dataHash: Array.from(hashMetadataData(metadata)),
+      creatorHash: Array.from(hashMetadataCreators(metadata.creators)),
+      nonce: new anchor.BN(0),
+      index: 0,
+    });
+
+    // The program checks the collection of the leaf, so it needs the metadata behind its data hash
+    const metadataArgs = (args: MetadataArgsArgs = metadata) => Buffer.from(getMetadataArgsSerializer().serialize(args));
+
+    const proof = () => getMerkleProof(leaves, maxDepth, leaves[0]).map((node) => ({
+      pubkey: new PublicKey(node),
+      isSigner: false,
+      isWritable: false,
+    }));
+
+    const compressedAccounts = {
+      treeConfig: null as PublicKey,
+      merkleTree: null as PublicKey,
+      bubblegumProgram: new PublicKey(MPL_BUBBLEGUM_PROGRAM_ID),
+      logWrapper: noop,
+      compressionProgram: compression,
+      systemProgram: SystemProgram.programId,
+    };
+
+    const makeCompressed = async (delegated: boolean) => {
+      await program.methods
+      .makeCompressed(leafArgs(), metadataArgs(), new anchor.BN(10), delegated)
+      .accounts({
+        ...compressedAccounts,
+        treeConfig,
+        merkleTree,
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        assetDenylist: findDenylist(assetId),
+        collectionDenylist: null,
+        proceedsRecipient: null,
+        leafDelegate: maker.publicKey,
+        mintB,
+        escrow,
+      })
+      .remainingAccounts(proof())
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      setLeaf(delegated ? maker.publicKey : escrow, escrow);
+    }
+
+    it("Creates a Tree, a cNFT and a FtB", async () => {
+      let umiKeypair = umi.eddsa.createKeypairFromSecretKey(maker.secretKey);
+      const signerKeypair = createSignerFromKeypair(umi, umiKeypair);
+      umi.use(signerIdentity(signerKeypair));
+      umi.use(mplBubblegum())
+      const tree = generateSigner(umi);
+
+      await (await createTree(umi, {
+        merkleTree: tree,
+        maxDepth,
+        maxBufferSize: 8,
+      })).sendAndConfirm(umi, { send: { skipPreflight: true }, confirm: { commitment } });
+
+      metadata = {
+        name: "cNFT Example",
+        symbol: "EXM",
+        uri: "",
+        sellerFeeBasisPoints: 0,
+        collection: null,
+        creators: [],
+      };
+
+      await mintV1(umi, {
+        leafOwner: umiKeypair.publicKey,
+        merkleTree: tree.publicKey,
+        metadata,
+      }).sendAndConfirm(umi, { send: { skipPreflight: true }, confirm: { commitment } });
+
+      merkleTree = new PublicKey(tree.publicKey);
+      treeConfig = new PublicKey(findTreeConfigPda(umi, { merkleTree: tree.publicKey })[0]);
+      assetId = new PublicKey(findLeafAssetIdPda(umi, { merkleTree: tree.publicKey, leafIndex: 0 })[0]);
+      setLeaf(maker.publicKey, maker.publicKey);
+
+      mintB = await createAsset(taker, "ft");
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        assetId.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+    });
+
+    it("Make Compressed", async () => {
+      await makeCompressed(false);
+    });
+
+    it("Close Compressed", async () => {
+      await program.methods
+      .closeCompressed(leafArgs())
+      .accounts({
+        ...compressedAccounts,
+        treeConfig,
+        merkleTree,
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        mintB,
+        escrow,
+      })
+      .remainingAccounts(proof())
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      setLeaf(maker.publicKey, maker.publicKey);
+    });
+
+    it("Take a Delegated Compressed listing", async () => {
+      await makeCompressed(true);
+
+      const takeCompressed = (args: MetadataArgsArgs) => program.methods
+      .takeCompressed(leafArgs(), metadataArgs(args))
+      .accounts({
+        config,
+        assetDenylist: findDenylist(assetId),
+        collectionDenylist: null,
+        ...compressedAccounts,
+        treeConfig,
+        merkleTree,
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
+        taker: taker.publicKey,
+        mintB,
+        takerAtaB,
+        referrerAtaB: null,
+        makerAtaB,
+        escrow,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(proof())
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      // Metadata that doesn't hash to the leaf could hide a flagged collection
+      await expectError(takeCompressed({ ...metadata, name: "Another cNFT" }), "InvalidLeafMetadata");
+      await takeCompressed(metadata);
+    });
+
+  });
+
+  describe("Rental", () => {
+
+    it("Make Rental", async () => {
+      mintA = await createAsset(maker, "nft");
+      mintB = await createAsset(taker, "ft");
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+      metadataA = findMetadata(mintA);
+      masterEditionA = findMasterEdition(mintA);
+      metadataB = findMetadata(mintB);
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+      vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+
+      await program.methods
+      .makeRental(new anchor.BN(5), new anchor.BN(2))
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        marketplaceAuthority: null,
+        proceedsRecipient: null,
+        makerAta: makerAtaA,
+        mintA,
+        metadataA,
+        masterEditionA,
+        makerTokenRecordA: null,
+        vaultTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        mintB,
+        metadataB,
+        vault,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    it("Rent", async () => {
+      await program.methods
+      .rentAsset()
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        renter: taker.publicKey,
+        renterAtaB: takerAtaB,
+        renterAtaA: takerAtaA,
+        maker: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
+        makerAtaB,
+        treasuryAtaB: null,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA,
+        vaultTokenRecordA: null,
+        renterTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        vault,
+        escrow,
+        marketplace: null,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    it("Reclaim", async () => {
+      await wait(3000);
+
+      await program.methods
+      .reclaim()
+      .accounts({
+        payer: maker.publicKey,
+        maker: maker.publicKey,
+        renter: taker.publicKey,
+        renterAtaA: takerAtaA,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA,
+        renterTokenRecordA: null,
+        vaultTokenRecordA: findTokenRecord(mintA, vault),
+        vault,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    it("Close", async () => {
+      await program.methods
+      .close()
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        makerAta: makerAtaA,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA,
+        makerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        vaultTokenRecordA: null,
+        vault,
+        payees: null,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+  });
+
+  describe("Loan", () => {
+
+    let loan: PublicKey;
+
+    const makeLoan = async (duration: number) => {
+      await program.methods
+      .makeLoan(new anchor.BN(1), new anchor.BN(10), 0, new anchor.BN(duration))
+      .accounts({
+        make: {
+          maker: maker.publicKey,
+          payer: maker.publicKey,
+          config,
+          mintDenylist: findDenylist(mintA),
+          collectionDenylist: null,
+          marketplace: null,
+          marketplaceAuthority: null,
+          proceedsRecipient: null,
+          makerAta: makerAtaA,
+          mintA,
+          metadataA,
+          masterEditionA,
+          makerTokenRecordA: null,
+          vaultTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+          mintB,
+          metadataB,
+          vault,
+          escrow,
+          sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        },
+        loan,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    const fundLoan = async () => {
+      let tx = new Transaction();
+
+      tx.instructions = [
+        createAssociatedTokenAccountIdempotentInstruction(taker.publicKey, makerAtaB, maker.publicKey, mintB),
+        await program.methods
+        .fundLoan()
+        .accounts({
+          config,
+          lender: taker.publicKey,
+          maker: maker.publicKey,
+          mintA,
+          mintB,
+          lenderAtaB: takerAtaB,
+          makerAtaB,
+          escrow,
+          loan,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .instruction()
+      ]
+
+      await provider.sendAndConfirm(tx, [ taker ]).then(confirm).then(log);
+    }
+
+    it("Creates a NftA and a FtB", async () => {
+      mintA = await createAsset(maker, "nft");
+      mintB = await createAsset(taker, "ft");
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+      metadataA = findMetadata(mintA);
+      masterEditionA = findMasterEdition(mintA);
+      metadataB = findMetadata(mintB);
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+      vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+      loan = PublicKey.findProgramAddressSync([
+        Buffer.from('loan'),
+        escrow.toBuffer(),
+      ], programId)[0];
+    });
+
+    it("Make, Fund and Repay a Loan", async () => {
+      await makeLoan(60);
+      await fundLoan();
+
+      await program.methods
+      .closeLoan()
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        makerAtaA,
+        lender: taker.publicKey,
+        repayer: null,
+        mintA,
+        mintB,
+        makerAtaB,
+        lenderAtaB: takerAtaB,
+        metadataA,
+        masterEditionA,
+        vaultTokenRecordA: null,
+        makerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        vault,
+        escrow,
+        loan,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    it("Make, Fund and Foreclose a Loan", async () => {
+      await makeLoan(2);
+      await fundLoan();
+      await wait(3000);
+
+      await program.methods
+      .foreclose()
+      .accounts({
+        lender: taker.publicKey,
+        lenderAtaA: takerAtaA,
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA,
+        vaultTokenRecordA: null,
+        lenderTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        vault,
+        escrow,
+        loan,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+      if (await connection.getAccountInfo(vault) != null) throw new Error("Expected the vault to be closed");
+    });
+
+    // SOL loans are paid out and repaid in lamports, the native mint has no metadata
+    const useSolLoan = async () => {
+      mintA = await createAsset(maker, "nft");
+      mintB = NATIVE_MINT;
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      metadataA = findMetadata(mintA);
+      masterEditionA = findMasterEdition(mintA);
+      metadataB = null;
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+      vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+      loan = PublicKey.findProgramAddressSync([
+        Buffer.from('loan'),
+        escrow.toBuffer(),
+      ], programId)[0];
+    }
+
+    const fundSolLoan = async () => {
+      await program.methods
+      .fundLoan()
+      .accounts({
+        config,
+        lender: taker.publicKey,
+        maker: maker.publicKey,
+        mintA,
+        mintB,
+        lenderAtaB: null,
+        makerAtaB: null,
+        escrow,
+        loan,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    it("Make, Fund and Repay a SOL Loan", async () => {
+      await useSolLoan();
+      await makeLoan(60);
+
+      const makerBalance = await connection.getBalance(maker.publicKey);
+      await fundSolLoan();
+      const borrowed = await connection.getBalance(maker.publicKey) - makerBalance;
+      if (borrowed != 10) throw new Error(`Unexpected principal ${borrowed}`);
+
+      const takerBalance = await connection.getBalance(taker.publicKey);
+
+      await program.methods
+      .closeLoan()
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        makerAtaA,
+        lender: taker.publicKey,
+        repayer: null,
+        mintA,
+        mintB,
+        makerAtaB: null,
+        lenderAtaB: null,
+        metadataA,
+        masterEditionA,
+        vaultTokenRecordA: null,
+        makerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        vault,
+        escrow,
+        loan,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      const repaid = await connection.getBalance(taker.publicKey) - takerBalance;
+      if (repaid != 10) throw new Error(`Unexpected repayment ${repaid}`);
+      if ((await getAccount(connection, makerAtaA)).amount != BigInt(1)) throw new Error("Expected the collateral back");
+    });
+
+    it("A Loan's deadline doesn't run while its Escrow is frozen", async () => {
+      const setLoanFrozen = async (frozen: boolean) => {
+        await program.methods
+        .setEscrowFrozen(frozen)
+        .accounts({
+          admin: admin.publicKey,
+          config,
+          escrow,
+          loan,
+        })
+        .signers([admin]).rpc({skipPreflight: true}).then(confirm).then(log);
+      }
+
+      await useSolLoan();
+      await makeLoan(5);
+      await fundSolLoan();
+
+      await setLoanFrozen(true);
+      await wait(6000);
+      await setLoanFrozen(false);
+
+      await program.methods
+      .closeLoan()
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        makerAtaA,
+        lender: taker.publicKey,
+        repayer: null,
+        mintA,
+        mintB,
+        makerAtaB: null,
+        lenderAtaB: null,
+        metadataA,
+        masterEditionA,
+        vaultTokenRecordA: null,
+        makerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        vault,
+        escrow,
+        loan,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      if ((await getAccount(connection, makerAtaA)).amount != BigInt(1)) throw new Error("Expected the collateral back");
+    });
+
+    it("Make, Fund and Foreclose a SOL Loan", async () => {
+      await useSolLoan();
+      await makeLoan(2);
+      await fundSolLoan();
+      await wait(3000);
+
+      await program.methods
+      .foreclose()
+      .accounts({
+        lender: taker.publicKey,
+        lenderAtaA: takerAtaA,
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA,
+        vaultTokenRecordA: null,
+        lenderTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        vault,
+        escrow,
+        loan,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      if ((await getAccount(connection, takerAtaA)).amount != BigInt(1)) throw new Error("Expected the lender to get the collateral");
+    });
+
+  });
+
+  describe("Arbitrated escrow", () => {
+
+    const arbiter = anchor.web3.Keypair.generate();
+    let arbiterAtaB: PublicKey;
+    let paymentVault: PublicKey;
+
+    const makeArbitrated = async () => {
+      await program.methods
+      .make(new anchor.BN(1), new anchor.BN(10), null, {
+        arbiter: arbiter.publicKey,
+        feeBps: 500,
+        buyer: null,
+        disputed: false,
+      })
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        marketplaceAuthority: null,
+        proceedsRecipient: null,
+        makerAta: makerAtaA,
+        mintA,
+        metadataA,
+        masterEditionA,
+        makerTokenRecordA: null,
+        vaultTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        mintB,
+        metadataB,
+        vault,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      await program.methods
+      .depositPayment()
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        buyer: taker.publicKey,
+        maker: maker.publicKey,
+        mintA,
+        mintB,
+        metadataA: findMetadata(mintA),
+        buyerAtaB: takerAtaB,
+        paymentVault,
+        escrow,
+        marketplace: null,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    const settleAccounts = (authority: PublicKey, recipient: PublicKey) => ({
+      authority,
+      maker: maker.publicKey,
+      payer: maker.publicKey,
+      proceedsRecipient: maker.publicKey,
+      buyer: taker.publicKey,
+      recipient,
+      mintA,
+      mintB,
+      metadataA,
+      masterEditionA,
+      vaultTokenRecordA: null,
+      recipientTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+      vault,
+      recipientAtaA: getAssociatedTokenAddressSync(mintA, recipient),
+      paymentVault,
+      makerAtaB,
+      buyerAtaB: takerAtaB,
+      arbiterAtaB,
+      treasuryAtaB: null,
+      escrow,
+      mintDenylist: findDenylist(mintA),
+      collectionDenylist: null,
+      marketplace: null,
+      sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+      tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      systemProgram: SystemProgram.programId,
+    });
+
+    it("Creates a NftA and a FtB", async () => {
+      await connection.requestAirdrop(arbiter.publicKey, LAMPORTS_PER_SOL * 10)
+      .then(confirm)
+      .then(log)
+
+      mintA = await createAsset(maker, "nft");
+      mintB = await createAsset(taker, "ft");
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+      metadataA = findMetadata(mintA);
+      masterEditionA = findMasterEdition(mintA);
+      metadataB = findMetadata(mintB);
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+      vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+      paymentVault = getAssociatedTokenAddressSync(mintB, escrow, true);
+      arbiterAtaB = (await getOrCreateAssociatedTokenAccount(connection, arbiter, mintB, arbiter.publicKey)).address;
+    });
+
+    it("Make, Deposit and Release an Arbitrated Escrow", async () => {
+      await makeArbitrated();
+
+      await program.methods
+      .release()
+      .accounts(settleAccounts(taker.publicKey, taker.publicKey))
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+      if (await connection.getAccountInfo(vault) != null) throw new Error("Expected the vault to be closed");
+    });
+
+    it("Make, Deposit, Dispute and Refund an Arbitrated Escrow", async () => {
+      // The first NFT went to the buyer, so the maker lists a new one
+      mintA = await createAsset(maker, "nft");
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      metadataA = findMetadata(mintA);
+      masterEditionA = findMasterEdition(mintA);
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+      vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+      paymentVault = getAssociatedTokenAddressSync(mintB, escrow, true);
+
+      await makeArbitrated();
+
+      await program.methods
+      .dispute()
+      .accounts({
+        party: maker.publicKey,
+        maker: maker.publicKey,
+        mintA,
+        mintB,
+        escrow,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      await program.methods
+      .resolve({ refund: {} })
+      .accounts(settleAccounts(arbiter.publicKey, maker.publicKey))
+      .signers([arbiter]).rpc({skipPreflight: true}).then(confirm).then(log);
+      if (await connection.getAccountInfo(vault) != null) throw new Error("Expected the vault to be closed");
+    });
+
+  });
+
+  describe("Two-sided escrow", () => {
+
+    let paymentVault: PublicKey;
+
+    it("Creates a NftA and a FtB", async () => {
+      mintA = await createAsset(maker, "nft");
+      mintB = await createAsset(taker, "ft");
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+      metadataA = findMetadata(mintA);
+      masterEditionA = findMasterEdition(mintA);
+      metadataB = findMetadata(mintB);
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+      vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+      paymentVault = getAssociatedTokenAddressSync(mintB, escrow, true);
+    });
+
+    it("Make, Deposit, Confirm and Settle a Two-sided Escrow", async () => {
+      await program.methods
+      .makeTwoSided(new anchor.BN(1), new anchor.BN(10), new anchor.BN(3600))
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        marketplaceAuthority: null,
+        proceedsRecipient: null,
+        makerAta: makerAtaA,
+        mintA,
+        metadataA,
+        masterEditionA,
+        makerTokenRecordA: null,
+        vaultTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        mintB,
+        metadataB,
+        vault,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      await program.methods
+      .depositPayment()
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        buyer: taker.publicKey,
+        maker: maker.publicKey,
+        mintA,
+        mintB,
+        metadataA: findMetadata(mintA),
+        buyerAtaB: takerAtaB,
+        paymentVault,
+        escrow,
+        marketplace: null,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      for (const party of [maker, taker]) {
+        await program.methods
+        .confirm()
+        .accounts({
+          party: party.publicKey,
+          maker: maker.publicKey,
+          mintA,
+          mintB,
+          escrow,
+        })
+        .signers([party]).rpc({skipPreflight: true}).then(confirm).then(log);
+      }
+
+      await program.methods
+      .settleConfirmed()
+      .accounts({
+        authority: maker.publicKey,
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
+        buyer: taker.publicKey,
+        recipient: taker.publicKey,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA,
+        vaultTokenRecordA: null,
+        recipientTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        vault,
+        recipientAtaA: takerAtaA,
+        paymentVault,
+        makerAtaB,
+        buyerAtaB: takerAtaB,
+        arbiterAtaB: null,
+        treasuryAtaB: null,
+        escrow,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+  });
+    
+  describe("Signed order", () => {
+
+    let delegate: PublicKey;
+    let makerNonce: PublicKey;
+
+    const signOrder = async (nonce: number) => {
+      const order = {
+        maker: maker.publicKey,
+        mintA,
+        mintB,
+        makerAmount: new anchor.BN(10),
+        takerAmount: new anchor.BN(10),
+        expiry: new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+        nonce: new anchor.BN(nonce),
+      };
+      // The signed message is domain separated by a tag and the program id
+      const message = Buffer.concat([
+        Buffer.from("anchor-nft-escrow:order:v1"),
+        programId.toBuffer(),
+        program.coder.types.encode("Order", order),
+      ]);
+      const orderStatus = PublicKey.findProgramAddressSync([
+        Buffer.from('order'),
+        Buffer.from(keccak_256(message)),
+      ], programId)[0];
+
+      return { order, message, orderStatus };
+    }
+
+    const fillOrder = async ({ order, message, orderStatus }) => {
+      let tx = new Transaction();
+
+      tx.instructions = [
+        createAssociatedTokenAccountIdempotentInstruction(taker.publicKey, makerAtaB, maker.publicKey, mintB),
+        Ed25519Program.createInstructionWithPrivateKey({
+          privateKey: maker.secretKey,
+          message,
+        }),
+        await program.methods
+        .fillOrder(order)
+        .accounts({
+          config,
+          mintDenylist: findDenylist(mintA),
+          collectionDenylist: null,
+          taker: taker.publicKey,
+          maker: maker.publicKey,
+          mintA,
+          mintB,
+          metadataA: findMetadata(mintA),
+          makerAtaA,
+          takerAtaA,
+          takerAtaB,
+          makerAtaB,
+          makerNonce,
+          orderStatus,
+          delegate,
+          sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .instruction()
+      ]
+
+      await provider.sendAndConfirm(tx, [ taker ]).then(confirm).then(log);
+    }
+
+    it("Creates a FtA and a FtB", async () => {
+      mintA = await createAsset(maker, "ft");
+      mintB = await createAsset(taker, "ft");
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+
+      delegate = PublicKey.findProgramAddressSync([Buffer.from('order_delegate')], programId)[0];
+      makerNonce = PublicKey.findProgramAddressSync([Buffer.from('nonce'), maker.publicKey.toBuffer()], programId)[0];
+
+      // The maker only approves the delegate, nothing gets listed on-chain
+      await approve(connection, maker, makerAtaA, delegate, maker, 100);
+    });
+
+    it("Sign and Fill an Order", async () => {
+      await fillOrder(await signOrder(0));
+    });
+
+    it("Fails to Fill an Order twice", async () => {
+      try {
+        await fillOrder(await signOrder(0));
+        throw new Error("The order was filled twice");
+      } catch (e) {
+        if (e.message == "The order was filled twice") throw e;
+      }
+    });
+
+    it("Cancel an Order", async () => {
+      const signed = await signOrder(1);
+
+      await program.methods
+      .cancelOrder(signed.order)
+      .accounts({
+        maker: maker.publicKey,
+        orderStatus: signed.orderStatus,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      try {
+        await fillOrder(signed);
+        throw new Error("A cancelled order was filled");
+      } catch (e) {
+        if (e.message == "A cancelled order was filled") throw e;
+      }
+    });
+
+    it("Cancel every Order below a Nonce", async () => {
+      await program.methods
+      .incrementNonce(new anchor.BN(3))
+      .accounts({
+        maker: maker.publicKey,
+        makerNonce,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      try {
+        await fillOrder(await signOrder(2));
+        throw new Error("An order below the nonce was filled");
+      } catch (e) {
+        if (e.message == "An order below the nonce was filled") throw e;
+      }
+
+      await fillOrder(await signOrder(3));
+    });
+
+  });
+    
+  describe("Proceeds recipient", () => {
+    // A cold wallet that receives the sales of the hot-wallet maker
+    const treasury = anchor.web3.Keypair.generate();
+    let treasuryAtaB: PublicKey;
+
+    it("Make an Escrow that pays a Proceeds Recipient", async () => {
+      mintA = await createAsset(maker, "ft");
+      mintB = await createAsset(taker, "ft");
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+      treasuryAtaB = getAssociatedTokenAddressSync(mintB, treasury.publicKey);
+      metadataA = findMetadata(mintA);
+      metadataB = findMetadata(mintB);
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+      vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+
+      await program.methods
+      .make(new anchor.BN(10), new anchor.BN(10), null, null)
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        marketplaceAuthority: null,
+        proceedsRecipient: treasury.publicKey,
+        makerAta: makerAtaA,
+        mintA,
+        metadataA,
+        masterEditionA: null,
+        makerTokenRecordA: null,
+        vaultTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        mintB,
+        metadataB,
+        vault,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    const takeTo = async (proceedsRecipient: PublicKey, destinationAta: PublicKey) => {
+      const take1 = await program.methods
+      .takeFromEscrow()
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        maker: maker.publicKey,
+        proceedsRecipient,
+        taker: taker.publicKey,
+        mintA,
+        mintB,
+        metadata: metadataA,
+        masterEdition: null,
+        originTokenRecord: null,
+        destinationTokenRecord: anchor.web3.Keypair.generate().publicKey,
+        originAta: vault,
+        destinationAta: takerAtaA,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction()
+
+      const take2 = await program.methods
+      .takerToMaker(new anchor.BN(10))
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        maker: maker.publicKey,
+        proceedsRecipient,
+        taker: taker.publicKey,
+        mintA,
+        mintB,
+        metadata: metadataB,
+        masterEdition: null,
+        originTokenRecord: null,
+        destinationTokenRecord: anchor.web3.Keypair.generate().publicKey,
+        originAta: takerAtaB,
+        destinationAta,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction()
+
+      let tx = new Transaction();
+
+      tx.instructions = [
+        createAssociatedTokenAccountIdempotentInstruction(taker.publicKey, takerAtaA, taker.publicKey, mintA),
+        createAssociatedTokenAccountIdempotentInstruction(taker.publicKey, destinationAta, proceedsRecipient, mintB),
+        take1,
+        take2
+      ]
+
+      await provider.sendAndConfirm(tx, [ taker ]).then(confirm).then(log);
+    }
+
+    it("Paying the Maker instead of the Proceeds Recipient fails", async () => {
+      let failed = false;
+      try {
+        await takeTo(maker.publicKey, makerAtaB);
+      } catch(e) {
+        failed = true;
+      }
+      if (!failed) throw new Error("Expected the transaction to fail");
+    });
+
+    it("Take pays the Proceeds Recipient", async () => {
+      await takeTo(treasury.publicKey, treasuryAtaB);
+    });
+
+  });
+    
+  describe("Revenue split", () => {
+    // Three co-sellers share the sale, 10 FtB split 3334/3333/3333 pays 3 each and the 1 FtB of dust to the first payee
+    const coSellers = [maker.publicKey, anchor.web3.Keypair.generate().publicKey, anchor.web3.Keypair.generate().publicKey];
+    const shares = [3334, 3333, 3333];
+    let payees: PublicKey;
+
+    const makeSplit = async (shares: number[]) => {
+      await program.methods
+      .makeSplit(new anchor.BN(10), new anchor.BN(10), coSellers.map((address, i) => ({ address, shareBps: shares[i] })))
+      .accounts({
+        make: {
+          maker: maker.publicKey,
+          payer: maker.publicKey,
+          config,
+          mintDenylist: findDenylist(mintA),
+          collectionDenylist: null,
+          marketplace: null,
+          marketplaceAuthority: null,
+          proceedsRecipient: null,
+          makerAta: makerAtaA,
+          mintA,
+          metadataA,
+          masterEditionA: null,
+          makerTokenRecordA: null,
+          vaultTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+          mintB,
+          metadataB,
+          vault,
+          escrow,
+          sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        },
+        payees,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    it("Shares that don't add up to 10000 fail", async () => {
+      mintA = await createAsset(maker, "ft");
+      mintB = await createAsset(taker, "ft");
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+      metadataA = findMetadata(mintA);
+      metadataB = findMetadata(mintB);
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+      vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+      payees = PublicKey.findProgramAddressSync([Buffer.from('payees'), escrow.toBuffer()], programId)[0];
+
+      let failed = false;
+      try {
+        await makeSplit([3334, 3333, 3332]);
+      } catch(e) {
+        failed = true;
+      }
+      if (!failed) throw new Error("Expected the transaction to fail");
+    });
+
+    it("Make a Split Escrow", async () => {
+      await makeSplit(shares);
+    });
+
+    it("Take pays every Payee its share", async () => {
+      await program.methods
+      .takeSplit()
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        taker: taker.publicKey,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA: null,
+        vaultTokenRecordA: null,
+        takerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        vault,
+        takerAtaA,
+        takerAtaB,
+        referrerAtaB: null,
+        treasuryAtaB: null,
+        payees,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(coSellers.flatMap((address) => [
+        { pubkey: address, isSigner: false, isWritable: false },
+        { pubkey: getAssociatedTokenAddressSync(mintB, address), isSigner: false, isWritable: true },
+      ]))
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+      if (await connection.getAccountInfo(vault) != null) throw new Error("Expected the vault to be closed");
+
+      const received = await Promise.all(coSellers.map(async (address) =>
+        (await getAccount(connection, getAssociatedTokenAddressSync(mintB, address))).amount
+      ));
+      if (received.join() != [4, 3, 3].join()) throw new Error(`Unexpected split ${received.join()}`);
+    });
+
+  });
+    
+  describe("Referral", () => {
+    // A partner storefront gets 5% of the 100 FtB price
+    const referrer = anchor.web3.Keypair.generate();
+    let referrerAtaB: PublicKey;
+
+    const takeAtomic = async (referrerAtaB: PublicKey) => {
+      await program.methods
+      .takeAtomic()
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
+        taker: taker.publicKey,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA: null,
+        originTokenRecordA: null,
+        takerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        metadataB,
+        masterEditionB: null,
+        takerTokenRecordB: null,
+        makerTokenRecordB: anchor.web3.Keypair.generate().publicKey,
+        originAtaA: vault,
+        takerAtaA,
+        takerAtaB,
+        referrerAtaB,
+        treasuryAtaB: null,
+        makerAtaB,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    it("Make an Escrow with a Referral Fee", async () => {
+      mintA = await createAsset(maker, "ft");
+      mintB = await createAsset(taker, "ft", 1000);
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+      metadataA = findMetadata(mintA);
+      metadataB = findMetadata(mintB);
+      referrerAtaB = (await getOrCreateAssociatedTokenAccount(connection, maker, mintB, referrer.publicKey)).address;
+      treasuryAtaB: null,
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+      vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+
+      await program.methods
+      .make(new anchor.BN(10), new anchor.BN(100), null, null)
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        marketplaceAuthority: null,
+        proceedsRecipient: null,
+        makerAta: makerAtaA,
+        mintA,
+        metadataA,
+        masterEditionA: null,
+        makerTokenRecordA: null,
+        vaultTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        mintB,
+        metadataB,
+        vault,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      await program.methods
+      .setReferralFee(500)
+      .accounts({
+        maker: maker.publicKey,
+        escrow,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    it("The Taker can't refer itself", async () => {
+      let failed = false;
+      try {
+        await takeAtomic(takerAtaB);
+      } catch(e) {
+        failed = true;
+      }
+      if (!failed) throw new Error("Expected the transaction to fail");
+    });
+
+    const setDefaultReferralFee = async (referralBps: number, signer = admin) => {
+      await program.methods
+      .setDefaultReferralFee(referralBps)
+      .accounts({
+        admin: signer.publicKey,
+        config,
+      })
+      .signers([signer]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    it("Only the Admin can set the default Referral Fee", async () => {
+      await expectError(setDefaultReferralFee(300, maker), "InvalidAdmin");
+    });
+
+    it("Take pays the Referrer its cut", async () => {
+      // The Escrow's own 5% overrides the 3% default
+      await setDefaultReferralFee(300);
+      await takeAtomic(referrerAtaB);
+      await setDefaultReferralFee(0);
+
+      const received = (await getAccount(connection, referrerAtaB)).amount;
+      if (received != BigInt(5)) throw new Error(`Unexpected referral fee ${received}`);
+    });
+
+  });
+    
+  describe("Pause and freeze", () => {
+
+    const setPaused = async (paused: boolean, signer = admin) => {
+      await program.methods
+      .setPaused(paused)
+      .accounts({
+        admin: signer.publicKey,
+        config,
+      })
+      .signers([signer]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    const setEscrowFrozen = async (frozen: boolean) => {
+      await program.methods
+      .setEscrowFrozen(frozen)
+      .accounts({
+        admin: admin.publicKey,
+        config,
+        escrow,
+        loan: null,
+      })
+      .signers([admin]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    const take = async () => {
+      await program.methods
+      .takeAtomic()
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
+        taker: taker.publicKey,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA: null,
+        originTokenRecordA: null,
+        takerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        metadataB,
+        masterEditionB: null,
+        takerTokenRecordB: null,
+        makerTokenRecordB: anchor.web3.Keypair.generate().publicKey,
+        originAtaA: vault,
+        takerAtaA,
+        takerAtaB,
+        referrerAtaB: null,
+        treasuryAtaB: null,
+        makerAtaB,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    const close = async () => {
+      await program.methods
+      .close()
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        makerAta: makerAtaA,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA: null,
+        makerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        vaultTokenRecordA: null,
+        vault,
+        payees: null,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    it("Make an Escrow", async () => {
+      mintA = await createAsset(maker, "ft");
+      mintB = await createAsset(taker, "ft");
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+      metadataA = findMetadata(mintA);
+      metadataB = findMetadata(mintB);
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+      vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+
+      await program.methods
+      .make(new anchor.BN(10), new anchor.BN(10), null, null)
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        marketplaceAuthority: null,
+        proceedsRecipient: null,
+        makerAta: makerAtaA,
+        mintA,
+        metadataA,
+        masterEditionA: null,
+        makerTokenRecordA: null,
+        vaultTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        mintB,
+        metadataB,
+        vault,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    it("Only the Admin can pause", async () => {
+      await expectError(setPaused(true, maker), "InvalidAdmin");
+    });
+
+    it("A frozen Escrow can't be closed", async () => {
+      await setEscrowFrozen(true);
+      await expectError(close(), "EscrowFrozen");
+      await setEscrowFrozen(false);
+    });
+
+    it("A paused program can't fill, but the Maker can still close", async () => {
+      await setPaused(true);
+      await expectError(take(), "ProgramPaused");
+      await close();
+      await setPaused(false);
+    });
+
+    it("A paused program can't take new Offers", async () => {
+      const offer = PublicKey.findProgramAddressSync([
+        Buffer.from('offer'),
+        taker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+
+      await setPaused(true);
+      await expectError(
+        program.methods
+        .makeOffer(new anchor.BN(10))
+        .accounts({
+          config,
+          buyer: taker.publicKey,
+          buyerAta: takerAtaB,
+          mintA,
+          mintB,
+          offerVault: getAssociatedTokenAddressSync(mintB, offer, true),
+          offer,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([taker]).rpc({skipPreflight: true}),
+        "ProgramPaused"
+      );
+      await setPaused(false);
+    });
+
+  });
+    
+  describe("Denylist", () => {
+
+    const addToDenylist = async (key: PublicKey) => {
+      await program.methods
+      .addToDenylist(key)
+      .accounts({
+        admin: admin.publicKey,
+        config,
+        denylistEntry: findDenylist(key),
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([admin]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    const removeFromDenylist = async (key: PublicKey) => {
+      await program.methods
+      .removeFromDenylist()
+      .accounts({
+        admin: admin.publicKey,
+        config,
+        denylistEntry: findDenylist(key),
+      })
+      .signers([admin]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    const make = async () => {
+      await program.methods
+      .make(new anchor.BN(10), new anchor.BN(10), null, null)
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        marketplaceAuthority: null,
+        proceedsRecipient: null,
+        makerAta: makerAtaA,
+        mintA,
+        metadataA,
+        masterEditionA: null,
+        makerTokenRecordA: null,
+        vaultTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        mintB,
+        metadataB,
+        vault,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    const take = async () => {
+      await program.methods
+      .takeAtomic()
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace: null,
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
+        taker: taker.publicKey,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA: null,
+        originTokenRecordA: null,
+        takerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        metadataB,
+        masterEditionB: null,
+        takerTokenRecordB: null,
+        makerTokenRecordB: anchor.web3.Keypair.generate().publicKey,
+        originAtaA: vault,
+        takerAtaA,
+        takerAtaB,
+        referrerAtaB: null,
+        treasuryAtaB: null,
+        makerAtaB,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    it("A denylisted mint can't be listed", async () => {
+      mintA = await createAsset(maker, "ft");
+      mintB = await createAsset(taker, "ft");
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+      metadataA = findMetadata(mintA);
+      metadataB = findMetadata(mintB);
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+      vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+
+      await addToDenylist(mintA);
+      await expectError(make(), "AssetDenylisted");
+      await removeFromDenylist(mintA);
+    });
+
+    it("A listing flagged after it was made can't be taken, but can be closed", async () => {
+      await make();
+      await addToDenylist(mintA);
+      await expectError(take(), "AssetDenylisted");
+
+      await program.methods
+      .close()
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        makerAta: makerAtaA,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA: null,
+        makerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        vaultTokenRecordA: null,
+        vault,
+        payees: null,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+
+    it("A flagged asset can't be sold into a standing Offer", async () => {
+      const offer = PublicKey.findProgramAddressSync([
+        Buffer.from('offer'),
+        taker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+      ], programId)[0];
+      const offerVault = getAssociatedTokenAddressSync(mintB, offer, true);
+
+      await program.methods
+      .makeOffer(new anchor.BN(10))
+      .accounts({
+        config,
+        buyer: taker.publicKey,
+        buyerAta: takerAtaB,
+        mintA,
+        mintB,
+        offerVault,
+        offer,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      await expectError(
+        program.methods
+        .acceptOffer()
+        .accounts({
+          config,
+          mintDenylist: findDenylist(mintA),
+          collectionDenylist: null,
+          seller: maker.publicKey,
+          buyer: taker.publicKey,
+          sellerAtaA: makerAtaA,
+          sellerAtaB: makerAtaB,
+          buyerAtaA: takerAtaA,
+          mintA,
+          mintB,
+          metadataA,
+          masterEditionA: null,
+          sellerTokenRecordA: null,
+          buyerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+          offerVault,
+          offer,
+          sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([maker]).rpc({skipPreflight: true}),
+        "AssetDenylisted"
+      );
+    });
+
+  });
+    
+  describe("Curated marketplace", () => {
+    const curator = anchor.web3.Keypair.generate();
+    const marketplaceId = anchor.web3.Keypair.generate().publicKey;
+    const marketplace = PublicKey.findProgramAddressSync([Buffer.from('marketplace'), marketplaceId.toBuffer()], programId)[0];
+    let collection: PublicKey;
+    let outsider: PublicKey;
+
+    // Mints an NFT to the maker, verified in the given collection when there is one
+    const createNftIn = async (collection: PublicKey | null, isCollection = false): Promise<PublicKey> => {
+      const umi = createUmi(connection.rpcEndpoint);
+      let umiKeypair = umi.eddsa.createKeypairFromSecretKey(maker.secretKey);
+      const signerKeypair = createSignerFromKeypair(umi, umiKeypair);
+      umi.use(signerIdentity(signerKeypair));
+      umi.use(mplTokenMetadata())
+      const mint = generateSigner(umi);
+
+      await createNft(umi, {
+        mint,
+        authority: signerKeypair,
+        name: "NFT Example",
+        uri: "",
+        sellerFeeBasisPoints: percentAmount(0),
+        collection: collection ? some({ key: publicKey(collection), verified: false }) : null,
+        isCollection,
+      }).sendAndConfirm(umi, { send: { skipPreflight: true }, confirm: { commitment } });
+
+      if (collection) {
+        await verifyCollectionV1(umi, {
+          metadata: findMetadataPda(umi, { mint: mint.publicKey }),
+          collectionMint: publicKey(collection),
+          authority: signerKeypair,
+        }).sendAndConfirm(umi, { send: { skipPreflight: true }, confirm: { commitment } });
+      }
+
+      return new PublicKey(mint.publicKey);
+    }
+
+    const setMint = (mint: PublicKey) => {
+      mintA = mint;
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      metadataA = findMetadata(mintA);
+      masterEditionA = findMasterEdition(mintA);
+
+      escrow = PublicKey.findProgramAddressSync([
+        Buffer.from('escrow'),
+        maker.publicKey.toBuffer(),
+        mintA.toBuffer(),
+        mintB.toBuffer(),
+        marketplace.toBuffer(),
+      ], programId)[0];
+      vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+    }
+
+    const make = async () => {
+      await program.methods
+      .make(new anchor.BN(1), new anchor.BN(10), null, null)
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: findDenylist(collection),
+        marketplace,
+        marketplaceAuthority: null,
+        proceedsRecipient: null,
+        makerAta: makerAtaA,
+        mintA,
+        metadataA,
+        masterEditionA,
+        makerTokenRecordA: null,
+        vaultTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        mintB,
+        metadataB,
+        vault,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    const take = async (marketplace: PublicKey | null) => {
+      await program.methods
+      .takeAtomic()
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: findDenylist(collection),
+        marketplace,
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
+        taker: taker.publicKey,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA,
+        originTokenRecordA: null,
+        takerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        metadataB,
+        masterEditionB: null,
+        takerTokenRecordB: null,
+        makerTokenRecordB: anchor.web3.Keypair.generate().publicKey,
+        originAtaA: vault,
+        takerAtaA,
+        takerAtaB,
+        referrerAtaB: null,
+        treasuryAtaB: null,
+        makerAtaB,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    it("Create a Marketplace that only allows one Collection", async () => {
+      await connection.requestAirdrop(curator.publicKey, LAMPORTS_PER_SOL).then(confirm).then(log);
+
+      collection = await createNftIn(null, true);
+      outsider = await createNftIn(null);
+      mintB = await createAsset(taker, "ft");
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+      metadataB = findMetadata(mintB);
+
+      await program.methods
+      .createMarketplace(marketplaceId, true, [collection], [], 0, false)
+      .accounts({
+        authority: curator.publicKey,
+        marketplace,
+        treasury: curator.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([curator]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    it("An NFT outside the Collection can't be listed", async () => {
+      setMint(outsider);
+      await expectError(make(), "CollectionNotAllowed");
+    });
+
+    it("An NFT of the Collection is listed and taken through the Marketplace", async () => {
+      setMint(await createNftIn(collection));
+      await make();
+      await expectError(take(null), "InvalidMarketplace");
+      await take(marketplace);
+    });
+
+  });
+    
+  describe("Marketplace tenants", () => {
+    // Storefront A takes a 5% fee, only accepts FtB and signs off its listings, storefront B is open
+    const curatorA = anchor.web3.Keypair.generate();
+    const curatorB = anchor.web3.Keypair.generate();
+    const marketplaceIdA = anchor.web3.Keypair.generate().publicKey;
+    const marketplaceIdB = anchor.web3.Keypair.generate().publicKey;
+    const marketplaceA = PublicKey.findProgramAddressSync([Buffer.from('marketplace'), marketplaceIdA.toBuffer()], programId)[0];
+    const marketplaceB = PublicKey.findProgramAddressSync([Buffer.from('marketplace'), marketplaceIdB.toBuffer()], programId)[0];
+    let otherMintB: PublicKey;
+    let treasuryAtaB: PublicKey;
+
+    // Every marketplace has its own escrow for the same asset
+    const findEscrow = (marketplace: PublicKey, mintB: PublicKey): PublicKey => PublicKey.findProgramAddressSync([
+      Buffer.from('escrow'),
+      maker.publicKey.toBuffer(),
+      mintA.toBuffer(),
+      mintB.toBuffer(),
+      marketplace.toBuffer(),
+    ], programId)[0];
+
+    const make = async (marketplace: PublicKey, mintB: PublicKey, marketplaceAuthority: anchor.web3.Keypair | null) => {
+      const escrow = findEscrow(marketplace, mintB);
+
+      await program.methods
+      .make(new anchor.BN(10), new anchor.BN(100), null, null)
+      .accounts({
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace,
+        marketplaceAuthority: marketplaceAuthority ? marketplaceAuthority.publicKey : null,
+        proceedsRecipient: null,
+        makerAta: makerAtaA,
+        mintA,
+        metadataA,
+        masterEditionA: null,
+        makerTokenRecordA: null,
+        vaultTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        mintB,
+        metadataB: findMetadata(mintB),
+        vault: getAssociatedTokenAddressSync(mintA, escrow, true),
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers(marketplaceAuthority ? [maker, marketplaceAuthority] : [maker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    const takeAtomic = async (marketplace: PublicKey, treasuryAtaB: PublicKey | null) => {
+      const escrow = findEscrow(marketplace, mintB);
+
+      await program.methods
+      .takeAtomic()
+      .accounts({
+        config,
+        mintDenylist: findDenylist(mintA),
+        collectionDenylist: null,
+        marketplace,
+        maker: maker.publicKey,
+        payer: maker.publicKey,
+        proceedsRecipient: maker.publicKey,
+        taker: taker.publicKey,
+        mintA,
+        mintB,
+        metadataA,
+        masterEditionA: null,
+        originTokenRecordA: null,
+        takerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+        metadataB,
+        masterEditionB: null,
+        takerTokenRecordB: null,
+        makerTokenRecordB: anchor.web3.Keypair.generate().publicKey,
+        originAtaA: getAssociatedTokenAddressSync(mintA, escrow, true),
+        takerAtaA,
+        takerAtaB,
+        referrerAtaB: null,
+        treasuryAtaB,
+        makerAtaB,
+        escrow,
+        sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([taker]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    it("Create two Marketplaces", async () => {
+      await connection.requestAirdrop(curatorA.publicKey, LAMPORTS_PER_SOL).then(confirm).then(log);
+      await connection.requestAirdrop(curatorB.publicKey, LAMPORTS_PER_SOL).then(confirm).then(log);
+
+      mintA = await createAsset(maker, "ft");
+      mintB = await createAsset(taker, "ft", 1000);
+      otherMintB = await createAsset(taker, "ft", 1000);
+
+      makerAtaA = getAssociatedTokenAddressSync(mintA, maker.publicKey);
+      takerAtaA = getAssociatedTokenAddressSync(mintA, taker.publicKey);
+      makerAtaB = getAssociatedTokenAddressSync(mintB, maker.publicKey);
+      takerAtaB = getAssociatedTokenAddressSync(mintB, taker.publicKey);
+      metadataA = findMetadata(mintA);
+      metadataB = findMetadata(mintB);
+      treasuryAtaB = (await getOrCreateAssociatedTokenAccount(connection, maker, mintB, curatorA.publicKey)).address;
+
+      await program.methods
+      .createMarketplace(marketplaceIdA, false, [], [mintB], 500, true)
+      .accounts({
+        authority: curatorA.publicKey,
+        marketplace: marketplaceA,
+        treasury: curatorA.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([curatorA]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      await program.methods
+      .createMarketplace(marketplaceIdB, false, [], [], 0, false)
+      .accounts({
+        authority: curatorB.publicKey,
+        marketplace: marketplaceB,
+        treasury: curatorB.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([curatorB]).rpc({skipPreflight: true}).then(confirm).then(log);
+    });
+
+    it("A listing needs the sign-off of a Marketplace that requires it", async () => {
+      await expectError(make(marketplaceA, mintB, null), "MarketplaceSignoffRequired");
+    });
+
+    it("A Marketplace only accepts its Payment Mints", async () => {
+      await expectError(make(marketplaceA, otherMintB, curatorA), "PaymentMintNotAllowed");
+    });
+
+    it("The same asset is listed on both Marketplaces", async () => {
+      await make(marketplaceA, mintB, curatorA);
+      await make(marketplaceB, mintB, null);
+    });
+
+    it("A fill pays the Marketplace fee to its treasury", async () => {
+      await expectError(takeAtomic(marketplaceA, null), "InvalidTreasury");
+      await takeAtomic(marketplaceA, treasuryAtaB);
+
+      const fee = (await getAccount(connection, treasuryAtaB)).amount;
+      if (fee != BigInt(5)) throw new Error(`Unexpected marketplace fee ${fee}`);
+      const received = (await getAccount(connection, makerAtaB)).amount;
+      if (received != BigInt(95)) throw new Error(`Unexpected maker proceeds ${received}`);
+    });
+
+    it("The listing on the other Marketplace is untouched and fee-free", async () => {
+      await takeAtomic(marketplaceB, null);
+
+      const received = (await getAccount(connection, makerAtaB)).amount;
+      if (received != BigInt(195)) throw new Error(`Unexpected maker proceeds ${received}`);
+    });
+
+    it("The Authority hands a Marketplace over", async () => {
+      const newCurator = anchor.web3.Keypair.generate();
+
+      const updateMarketplace = (authority: anchor.web3.Keypair, newAuthority: anchor.web3.Keypair | null) => program.methods
+      .updateMarketplace(false, [], [], 0, false)
+      .accounts({
+        authority: authority.publicKey,
+        marketplace: marketplaceB,
+        treasury: curatorB.publicKey,
+        newAuthority: newAuthority?.publicKey ?? null,
+      })
+      .signers(newAuthority ? [authority, newAuthority] : [authority]).rpc({skipPreflight: true}).then(confirm).then(log);
+
+      await updateMarketplace(curatorB, newCurator);
+
+      // The address stays the same, only the new Authority can update it
+      const authority = (await program.account.marketplace.fetch(marketplaceB)).authority;
+      if (!authority.equals(newCurator.publicKey)) throw new Error(`Unexpected authority ${authority}`);
+      await expectError(updateMarketplace(curatorB, null), "ConstraintHasOne");
+      await updateMarketplace(newCurator, null);
+    });
+
+  });
     
-});
+});
\ No newline at end of file
@@ -0,0 +1,287 @@
+import * as anchor from "@coral-xyz/anchor";
+import { IDL, NftEscrow } from "../target/types/nft_escrow";
+import { IDL as CPI_IDL, EscrowCpiExample } from "../target/types/escrow_cpi_example";
+
+import { PublicKey,
+  Commitment,
+  SystemProgram,
+  LAMPORTS_PER_SOL,
+  Transaction,
+} from "@solana/web3.js";
+
+import {
+  createFungible,
+  mplTokenMetadata,
+  MPL_TOKEN_METADATA_PROGRAM_ID,
+} from "@metaplex-foundation/mpl-token-metadata";
+
+import {
+  getOrCreateAssociatedTokenAccount,
+  TOKEN_PROGRAM_ID,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  getAssociatedTokenAddressSync,
+  mintTo,
+} from "@solana/spl-token";
+
+import {Connection} from "@solana/web3.js";
+
+import { createUmi } from "@metaplex-foundation/umi-bundle-defaults"
+
+import {
+  createSignerFromKeypair,
+  generateSigner,
+  percentAmount,
+  signerIdentity
+} from "@metaplex-foundation/umi";
+
+// The example program owns a PDA that lists and buys through the escrow with CPIs,
+// the same way a vault or a DAO program composes with it
+describe("escrow-cpi-example", () => {
+  const user = anchor.web3.Keypair.generate();
+
+  const commitment: Commitment = "confirmed"; // processed, confirmed, finalized
+  const connection = new Connection("http://localhost:8899", {
+      commitment,
+      wsEndpoint: "ws://localhost:8900/",
+  });
+  const provider = new anchor.AnchorProvider(connection, new anchor.Wallet(user), { commitment });
+  const programId = new PublicKey("2VYDrwoKRKNgvmQo3DHfcLfQFAuijjNEAY9ZoXfv8GfZ");
+  const program = new anchor.Program<NftEscrow>(IDL, programId, provider);
+  const cpiProgramId = new PublicKey("6WwnjoJajaNcFSCcfYUxs2jaDdxGrEYsUNT6D57sinR2");
+  const cpiProgram = new anchor.Program<EscrowCpiExample>(CPI_IDL, cpiProgramId, provider);
+
+  const authority = PublicKey.findProgramAddressSync([Buffer.from('authority')], cpiProgramId)[0];
+  const config = PublicKey.findProgramAddressSync([Buffer.from('config')], programId)[0];
+  const findDenylist = (key: PublicKey): PublicKey => PublicKey.findProgramAddressSync([Buffer.from('denylist'), key.toBuffer()], programId)[0];
+
+  // Helpers
+  const confirm = async (signature: string): Promise<string> => {
+    const block = await connection.getLatestBlockhash();
+    await connection.confirmTransaction({
+      signature,
+      ...block
+    })
+    return signature
+  }
+
+  const log = async(signature: string): Promise<string> => {
+    console.log(`Your transaction signature: https://explorer.solana.com/transaction/${signature}?cluster=custom&customUrl=${connection.rpcEndpoint}`);
+    return signature;
+  }
+
+  // Mints 100 fungibles to the owner ATA of the given wallet, which can be a PDA
+  const createFt = async (holder: PublicKey): Promise<PublicKey> => {
+    const umi = createUmi(connection.rpcEndpoint);
+    let umiKeypair = umi.eddsa.createKeypairFromSecretKey(user.secretKey);
+    const signerKeypair = createSignerFromKeypair(umi, umiKeypair);
+    umi.use(signerIdentity(signerKeypair));
+    umi.use(mplTokenMetadata())
+    const mint = generateSigner(umi);
+
+    await createFungible(
+      umi,
+      {
+        mint: mint,
+        authority: signerKeypair,
+        updateAuthority: umiKeypair.publicKey,
+        name: "FT Example",
+        symbol: "EXM",
+        uri: "",
+        sellerFeeBasisPoints: percentAmount(0),
+        creators: null,
+        collection: null,
+        uses: null,
+        isMutable: true,
+        collectionDetails: null,
+      }
+    ).sendAndConfirm(umi, { send: { skipPreflight: true }, confirm: { commitment } });
+
+    let ata = await getOrCreateAssociatedTokenAccount(connection, user, new PublicKey(mint.publicKey), holder, true);
+    await mintTo(connection, user, new PublicKey(mint.publicKey), ata.address, user.publicKey, 100);
+
+    return new PublicKey(mint.publicKey);
+  }
+
+  const findMetadata = (mint: PublicKey): PublicKey => PublicKey.findProgramAddressSync([
+    Buffer.from('metadata'),
+    new PublicKey(MPL_TOKEN_METADATA_PROGRAM_ID).toBuffer(),
+    mint.toBuffer(),
+  ], new PublicKey(MPL_TOKEN_METADATA_PROGRAM_ID))[0];
+
+  const findEscrow = (maker: PublicKey, mintA: PublicKey, mintB: PublicKey): PublicKey => PublicKey.findProgramAddressSync([
+    Buffer.from('escrow'),
+    maker.toBuffer(),
+    mintA.toBuffer(),
+    mintB.toBuffer(),
+  ], programId)[0];
+
+  const programs = {
+    sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+    tokenMetadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
+    tokenProgram: TOKEN_PROGRAM_ID,
+    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    systemProgram: SystemProgram.programId,
+  };
+
+  //Variables
+  let mintA: PublicKey;
+  let mintB: PublicKey;
+  let mintC: PublicKey;
+
+  it("Airdrop and fund the PDA", async () => {
+    await connection.requestAirdrop(user.publicKey, LAMPORTS_PER_SOL * 100)
+    .then(confirm)
+    .then(log)
+
+    // The PDA pays for the accounts the escrow creates on its behalf
+    let tx = new Transaction().add(SystemProgram.transfer({
+      fromPubkey: user.publicKey,
+      toPubkey: authority,
+      lamports: LAMPORTS_PER_SOL,
+    }));
+    await provider.sendAndConfirm(tx).then(confirm).then(log);
+
+    // The config is created by the escrow tests, unless this file runs on its own
+    if (!(await connection.getAccountInfo(config))) {
+      const admin = (anchor.AnchorProvider.env().wallet as anchor.Wallet).payer;
+      await program.methods
+      .initializeConfig()
+      .accounts({
+        admin: admin.publicKey,
+        config,
+        program: programId,
+        programData: PublicKey.findProgramAddressSync([programId.toBuffer()], new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111"))[0],
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([admin]).rpc({skipPreflight: true}).then(confirm).then(log);
+    }
+
+    mintA = await createFt(authority);
+    mintB = await createFt(user.publicKey);
+    mintC = await createFt(user.publicKey);
+  });
+
+  it("The PDA lists through CPI and a wallet takes it atomically", async () => {
+    const escrow = findEscrow(authority, mintA, mintB);
+    const vault = getAssociatedTokenAddressSync(mintA, escrow, true);
+
+    await cpiProgram.methods
+    .list(new anchor.BN(10), new anchor.BN(10))
+    .accounts({
+      config,
+      mintDenylist: findDenylist(mintA),
+      collectionDenylist: null,
+      marketplace: null,
+      authority,
+      makerAta: getAssociatedTokenAddressSync(mintA, authority, true),
+      mintA,
+      metadataA: findMetadata(mintA),
+      masterEditionA: null,
+      makerTokenRecordA: null,
+      vaultTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+      mintB,
+      metadataB: findMetadata(mintB),
+      vault,
+      escrow,
+      ...programs,
+      escrowProgram: programId,
+    })
+    .rpc({skipPreflight: true}).then(confirm).then(log);
+
+    await program.methods
+    .takeAtomic()
+    .accounts({
+      config,
+      mintDenylist: findDenylist(mintA),
+      collectionDenylist: null,
+      marketplace: null,
+      maker: authority,
+      payer: authority,
+      proceedsRecipient: authority,
+      taker: user.publicKey,
+      mintA,
+      mintB,
+      metadataA: findMetadata(mintA),
+      masterEditionA: null,
+      originTokenRecordA: null,
+      takerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+      metadataB: findMetadata(mintB),
+      masterEditionB: null,
+      takerTokenRecordB: null,
+      makerTokenRecordB: anchor.web3.Keypair.generate().publicKey,
+      originAtaA: vault,
+      takerAtaA: getAssociatedTokenAddressSync(mintA, user.publicKey),
+      takerAtaB: getAssociatedTokenAddressSync(mintB, user.publicKey),
+      referrerAtaB: null,
+      treasuryAtaB: null,
+      makerAtaB: getAssociatedTokenAddressSync(mintB, authority, true),
+      escrow,
+      ...programs,
+    })
+    .rpc({skipPreflight: true}).then(confirm).then(log);
+  });
+
+  it("A wallet lists and the PDA buys through CPI", async () => {
+    const escrow = findEscrow(user.publicKey, mintC, mintB);
+    const vault = getAssociatedTokenAddressSync(mintC, escrow, true);
+
+    await program.methods
+    .make(new anchor.BN(10), new anchor.BN(10), null, null)
+    .accounts({
+      maker: user.publicKey,
+      payer: user.publicKey,
+      config,
+      mintDenylist: findDenylist(mintC),
+      collectionDenylist: null,
+      marketplace: null,
+      marketplaceAuthority: null,
+      proceedsRecipient: null,
+      makerAta: getAssociatedTokenAddressSync(mintC, user.publicKey),
+      mintA: mintC,
+      metadataA: findMetadata(mintC),
+      masterEditionA: null,
+      makerTokenRecordA: null,
+      vaultTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+      mintB,
+      metadataB: findMetadata(mintB),
+      vault,
+      escrow,
+      ...programs,
+    })
+    .rpc({skipPreflight: true}).then(confirm).then(log);
+
+    // The PDA pays with the FtB it got from its own sale
+    await cpiProgram.methods
+    .buy()
+    .accounts({
+      config,
+      mintDenylist: findDenylist(mintC),
+      collectionDenylist: null,
+      marketplace: null,
+      authority,
+      maker: user.publicKey,
+      payer: user.publicKey,
+      proceedsRecipient: user.publicKey,
+      mintA: mintC,
+      mintB,
+      metadataA: findMetadata(mintC),
+      masterEditionA: null,
+      originTokenRecordA: null,
+      takerTokenRecordA: anchor.web3.Keypair.generate().publicKey,
+      metadataB: findMetadata(mintB),
+      masterEditionB: null,
+      takerTokenRecordB: null,
+      makerTokenRecordB: anchor.web3.Keypair.generate().publicKey,
+      originAtaA: vault,
+      takerAtaA: getAssociatedTokenAddressSync(mintC, authority, true),
+      takerAtaB: getAssociatedTokenAddressSync(mintB, authority, true),
+      treasuryAtaB: null,
+      makerAtaB: getAssociatedTokenAddressSync(mintB, user.publicKey),
+      escrow,
+      ...programs,
+      escrowProgram: programId,
+    })
+    .rpc({skipPreflight: true}).then(confirm).then(log);
+  });
+
+});
\ No newline at end of file